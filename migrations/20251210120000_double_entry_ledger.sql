@@ -0,0 +1,114 @@
+-- Double-entry ledger. Wallet balances are derived from postings instead of a mutable column.
+-- Convention: an account's balance is the SUM of its postings; every journal entry sums to zero.
+
+CREATE TABLE IF NOT EXISTS ledger_accounts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    code TEXT NOT NULL UNIQUE, -- e.g., 'system:lightning', 'wallet:<uuid>'
+    kind TEXT NOT NULL, -- 'wallet' | 'system'
+    wallet_id UUID UNIQUE REFERENCES wallets(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((kind = 'wallet') = (wallet_id IS NOT NULL))
+);
+
+CREATE TABLE IF NOT EXISTS journal_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    entry_type TEXT NOT NULL, -- e.g., 'breez_receive', 'ussd_send', 'opening_balance'
+    transaction_id UUID REFERENCES transactions(id),
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS ledger_postings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    journal_entry_id UUID NOT NULL REFERENCES journal_entries(id),
+    account_id UUID NOT NULL REFERENCES ledger_accounts(id),
+    amount_sats BIGINT NOT NULL CHECK (amount_sats <> 0), -- positive = debit, negative = credit
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ledger_postings_account_id ON ledger_postings (account_id);
+CREATE INDEX IF NOT EXISTS idx_ledger_postings_journal_entry_id ON ledger_postings (journal_entry_id);
+CREATE INDEX IF NOT EXISTS idx_journal_entries_transaction_id ON journal_entries (transaction_id);
+
+-- Every journal entry must sum to zero by the time its database transaction commits.
+CREATE OR REPLACE FUNCTION ledger_check_entry_balanced()
+RETURNS TRIGGER AS $$
+DECLARE
+    imbalance BIGINT;
+BEGIN
+    SELECT COALESCE(SUM(amount_sats), 0) INTO imbalance
+    FROM ledger_postings
+    WHERE journal_entry_id = NEW.journal_entry_id;
+
+    IF imbalance <> 0 THEN
+        RAISE EXCEPTION 'Journal entry % is unbalanced by % sats', NEW.journal_entry_id, imbalance;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER ledger_postings_balanced
+AFTER INSERT ON ledger_postings
+DEFERRABLE INITIALLY DEFERRED
+FOR EACH ROW
+EXECUTE FUNCTION ledger_check_entry_balanced();
+
+-- Journal entries and postings are append-only; corrections are made with reversing entries.
+CREATE OR REPLACE FUNCTION ledger_reject_mutation()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION '% is append-only', TG_TABLE_NAME;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER journal_entries_append_only
+BEFORE UPDATE OR DELETE ON journal_entries
+FOR EACH ROW
+EXECUTE FUNCTION ledger_reject_mutation();
+
+CREATE TRIGGER ledger_postings_append_only
+BEFORE UPDATE OR DELETE ON ledger_postings
+FOR EACH ROW
+EXECUTE FUNCTION ledger_reject_mutation();
+
+-- System accounts
+INSERT INTO ledger_accounts (code, kind) VALUES
+    ('system:lightning', 'system'),       -- sats entering/leaving through the Lightning node
+    ('system:treasury', 'system'),        -- Sabi's own BTC float (counterparty for fiat trades)
+    ('system:fees', 'system'),            -- network and service fees
+    ('system:opening_balance', 'system')  -- balances carried over from wallets.balance_sats
+ON CONFLICT (code) DO NOTHING;
+
+-- One account per existing wallet
+INSERT INTO ledger_accounts (code, kind, wallet_id)
+SELECT 'wallet:' || id::TEXT, 'wallet', id FROM wallets
+ON CONFLICT (code) DO NOTHING;
+
+-- Carry existing balances over as opening-balance entries
+CREATE TEMPORARY TABLE opening_balances ON COMMIT DROP AS
+SELECT gen_random_uuid() AS entry_id, w.id AS wallet_id, w.balance_sats
+FROM wallets w
+WHERE w.balance_sats <> 0;
+
+INSERT INTO journal_entries (id, entry_type, description)
+SELECT entry_id, 'opening_balance', 'Carried over from wallets.balance_sats'
+FROM opening_balances;
+
+INSERT INTO ledger_postings (journal_entry_id, account_id, amount_sats)
+SELECT ob.entry_id, la.id, ob.balance_sats
+FROM opening_balances ob
+JOIN ledger_accounts la ON la.wallet_id = ob.wallet_id;
+
+INSERT INTO ledger_postings (journal_entry_id, account_id, amount_sats)
+SELECT ob.entry_id, la.id, -ob.balance_sats
+FROM opening_balances ob
+JOIN ledger_accounts la ON la.code = 'system:opening_balance';
+
+ALTER TABLE wallets DROP COLUMN IF EXISTS balance_sats;
+
+CREATE OR REPLACE VIEW wallet_balances AS
+SELECT la.wallet_id, COALESCE(SUM(lp.amount_sats), 0)::BIGINT AS balance_sats
+FROM ledger_accounts la
+LEFT JOIN ledger_postings lp ON lp.account_id = la.id
+WHERE la.kind = 'wallet'
+GROUP BY la.wallet_id;
@@ -0,0 +1,20 @@
+-- Provider-agnostic webhook ingestion: keep the exact raw payload and headers, retry with backoff.
+
+ALTER TABLE fiat_onramp_webhooks RENAME TO webhook_events;
+
+ALTER TABLE webhook_events
+    ADD COLUMN IF NOT EXISTS event_type TEXT,
+    ADD COLUMN IF NOT EXISTS raw_body BYTEA, -- exact bytes as received; NULL for rows ingested before this migration
+    ADD COLUMN IF NOT EXISTS headers JSONB NOT NULL DEFAULT '{}'::JSONB,
+    ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN IF NOT EXISTS last_error TEXT,
+    ADD COLUMN IF NOT EXISTS processed_at TIMESTAMPTZ;
+
+-- Event IDs are only unique per provider
+ALTER TABLE webhook_events DROP CONSTRAINT IF EXISTS fiat_onramp_webhooks_event_id_key;
+ALTER TABLE webhook_events ADD CONSTRAINT webhook_events_provider_event_id_key UNIQUE (provider, event_id);
+
+DROP INDEX IF EXISTS idx_fiat_onramp_webhooks_event_id;
+DROP INDEX IF EXISTS idx_fiat_onramp_webhooks_processed;
+CREATE INDEX IF NOT EXISTS idx_webhook_events_due ON webhook_events (next_attempt_at) WHERE processed = FALSE;
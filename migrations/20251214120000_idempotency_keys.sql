@@ -0,0 +1,14 @@
+-- Fallback store for Idempotency-Key responses when Redis is unavailable.
+
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    storage_key TEXT PRIMARY KEY, -- SHA-256 of method, path, caller and Idempotency-Key
+    request_hash TEXT NOT NULL, -- SHA-256 of the request, to detect key reuse with a different body
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    status_code INTEGER,
+    content_type TEXT,
+    response_body BYTEA,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_expires_at ON idempotency_keys (expires_at);
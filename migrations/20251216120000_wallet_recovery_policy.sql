@@ -0,0 +1,8 @@
+-- Per-wallet k-of-n policy for social recovery (Shamir secret sharing).
+
+ALTER TABLE wallets
+    ADD COLUMN IF NOT EXISTS recovery_threshold SMALLINT NOT NULL DEFAULT 3,
+    ADD COLUMN IF NOT EXISTS recovery_share_count SMALLINT NOT NULL DEFAULT 5;
+
+ALTER TABLE wallets ADD CONSTRAINT wallets_recovery_policy_check
+    CHECK (recovery_threshold >= 2 AND recovery_share_count >= recovery_threshold AND recovery_share_count <= 255);
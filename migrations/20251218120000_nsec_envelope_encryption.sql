@@ -0,0 +1,21 @@
+-- Envelope encryption for stored nsecs: each row has its own data key, wrapped by a versioned master key.
+-- Existing rows keep their plaintext in `legacy_nsec` until `rotate-master-key` encrypts them.
+
+ALTER TABLE encrypted_nostr_nsecs RENAME COLUMN encrypted_nsec TO legacy_nsec;
+ALTER TABLE encrypted_nostr_nsecs ALTER COLUMN legacy_nsec DROP NOT NULL;
+
+ALTER TABLE encrypted_nostr_nsecs
+    ADD COLUMN IF NOT EXISTS key_version INTEGER, -- master key version that wrapped the data key
+    ADD COLUMN IF NOT EXISTS wrapped_data_key BYTEA,
+    ADD COLUMN IF NOT EXISTS nonce BYTEA,
+    ADD COLUMN IF NOT EXISTS ciphertext BYTEA,
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- A row is either legacy plaintext or a complete envelope, never a mix
+ALTER TABLE encrypted_nostr_nsecs ADD CONSTRAINT encrypted_nostr_nsecs_envelope_check CHECK (
+    (legacy_nsec IS NOT NULL AND key_version IS NULL AND wrapped_data_key IS NULL AND nonce IS NULL AND ciphertext IS NULL)
+    OR (legacy_nsec IS NULL AND key_version IS NOT NULL AND wrapped_data_key IS NOT NULL AND nonce IS NOT NULL AND ciphertext IS NOT NULL)
+);
+
+CREATE INDEX IF NOT EXISTS idx_encrypted_nostr_nsecs_wallet_id ON encrypted_nostr_nsecs (wallet_id);
+CREATE INDEX IF NOT EXISTS idx_encrypted_nostr_nsecs_key_version ON encrypted_nostr_nsecs (key_version);
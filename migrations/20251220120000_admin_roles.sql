@@ -0,0 +1,18 @@
+-- Role-based access for the admin API. Roles are ordered: viewer < operator < superadmin.
+
+CREATE TABLE IF NOT EXISTS admin_roles (
+    admin_user_id UUID PRIMARY KEY REFERENCES admin_users(id) ON DELETE CASCADE,
+    role TEXT NOT NULL CHECK (role IN ('viewer', 'operator', 'superadmin')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE OR REPLACE TRIGGER update_admin_roles_updated_at
+BEFORE UPDATE ON admin_roles
+FOR EACH ROW
+EXECUTE FUNCTION update_updated_at_column();
+
+-- Admins created before roles existed had unrestricted access; keep it that way
+INSERT INTO admin_roles (admin_user_id, role)
+SELECT id, 'superadmin' FROM admin_users
+ON CONFLICT (admin_user_id) DO NOTHING;
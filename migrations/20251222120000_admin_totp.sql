@@ -0,0 +1,22 @@
+-- TOTP second factor for admin login. The secret is envelope-encrypted with the same
+-- master keys as stored nsecs; AAD is the admin user ID.
+
+ALTER TABLE admin_users
+    ADD COLUMN IF NOT EXISTS totp_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS totp_key_version INTEGER,
+    ADD COLUMN IF NOT EXISTS totp_wrapped_key BYTEA,
+    ADD COLUMN IF NOT EXISTS totp_nonce BYTEA,
+    ADD COLUMN IF NOT EXISTS totp_ciphertext BYTEA,
+    ADD COLUMN IF NOT EXISTS totp_last_used_step BIGINT; -- Rejects reuse of a code within its window
+
+ALTER TABLE admin_users ADD CONSTRAINT admin_users_totp_check
+    CHECK (NOT totp_enabled OR totp_ciphertext IS NOT NULL);
+
+CREATE TABLE IF NOT EXISTS admin_recovery_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    admin_user_id UUID NOT NULL REFERENCES admin_users(id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL, -- SHA-256 of the normalized code
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (admin_user_id, code_hash)
+);
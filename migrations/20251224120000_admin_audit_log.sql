@@ -0,0 +1,38 @@
+-- Append-only, hash-chained record of admin actions.
+-- Each row's hash covers its contents and the previous row's hash, so edits and deletions are detectable.
+
+CREATE TABLE IF NOT EXISTS admin_audit_log (
+    seq BIGINT PRIMARY KEY CHECK (seq >= 1), -- Assigned by the application under an advisory lock, without gaps
+    id UUID NOT NULL UNIQUE,
+    actor_admin_id UUID, -- No foreign key: rows must outlive the admin they name
+    actor_username TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target_type TEXT,
+    target_id TEXT,
+    before JSONB,
+    after JSONB,
+    request_id TEXT,
+    prev_hash TEXT NOT NULL,
+    hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_actor ON admin_audit_log (actor_admin_id, seq);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_target ON admin_audit_log (target_type, target_id);
+
+CREATE OR REPLACE FUNCTION reject_admin_audit_log_changes()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'admin_audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER admin_audit_log_append_only
+BEFORE UPDATE OR DELETE ON admin_audit_log
+FOR EACH ROW
+EXECUTE FUNCTION reject_admin_audit_log_changes();
+
+CREATE OR REPLACE TRIGGER admin_audit_log_no_truncate
+BEFORE TRUNCATE ON admin_audit_log
+FOR EACH STATEMENT
+EXECUTE FUNCTION reject_admin_audit_log_changes();
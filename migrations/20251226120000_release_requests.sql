@@ -0,0 +1,28 @@
+-- Four-eyes approval for large manual releases: one admin requests, a different admin approves or rejects.
+
+CREATE TABLE IF NOT EXISTS release_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL REFERENCES transactions(id),
+    amount_sats BIGINT NOT NULL CHECK (amount_sats > 0),
+    recipient TEXT NOT NULL,
+    notes TEXT,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'rejected', 'expired')),
+    requested_by UUID NOT NULL REFERENCES admin_users(id),
+    decided_by UUID REFERENCES admin_users(id),
+    decision_reason TEXT,
+    expires_at TIMESTAMPTZ NOT NULL,
+    decided_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (decided_by IS NULL OR decided_by <> requested_by)
+);
+
+-- At most one open request per transaction
+CREATE UNIQUE INDEX IF NOT EXISTS idx_release_requests_pending_transaction
+    ON release_requests (transaction_id) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_release_requests_status_expires ON release_requests (status, expires_at);
+
+CREATE OR REPLACE TRIGGER update_release_requests_updated_at
+BEFORE UPDATE ON release_requests
+FOR EACH ROW
+EXECUTE FUNCTION update_updated_at_column();
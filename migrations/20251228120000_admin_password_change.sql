@@ -0,0 +1,4 @@
+-- Admins whose password was set by someone else must choose their own before doing anything else.
+
+ALTER TABLE admin_users
+    ADD COLUMN IF NOT EXISTS must_change_password BOOLEAN NOT NULL DEFAULT FALSE;
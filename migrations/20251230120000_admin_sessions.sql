@@ -0,0 +1,33 @@
+-- Admin sessions: short-lived access JWTs are refreshed with a rotating, hashed refresh token.
+-- Revoking a session stops both its refresh token and every access token issued for it.
+
+CREATE TABLE IF NOT EXISTS admin_sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    admin_user_id UUID NOT NULL REFERENCES admin_users(id) ON DELETE CASCADE,
+    refresh_token_hash TEXT NOT NULL UNIQUE, -- SHA-256 hex; the token itself is never stored
+    previous_refresh_token_hash TEXT, -- Kept to detect reuse of a rotated-out token
+    expires_at TIMESTAMPTZ NOT NULL,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    revoked_reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_sessions_admin_user ON admin_sessions (admin_user_id) WHERE revoked_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_admin_sessions_previous_hash ON admin_sessions (previous_refresh_token_hash);
+
+CREATE OR REPLACE TRIGGER update_admin_sessions_updated_at
+BEFORE UPDATE ON admin_sessions
+FOR EACH ROW
+EXECUTE FUNCTION update_updated_at_column();
+
+-- Access tokens revoked before they expire, by JWT ID. Rows are useless once the token has expired.
+CREATE TABLE IF NOT EXISTS admin_revoked_tokens (
+    jti UUID PRIMARY KEY,
+    admin_user_id UUID NOT NULL REFERENCES admin_users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_admin_revoked_tokens_expires ON admin_revoked_tokens (expires_at);
@@ -0,0 +1,10 @@
+-- Indexes for the admin trade search: every filter is combined with keyset pagination
+-- on (created_at DESC, id DESC), so each index ends with those columns.
+
+CREATE INDEX IF NOT EXISTS idx_transactions_created_at_id ON transactions (created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_transactions_status_created_at ON transactions (status, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_transactions_tx_type_created_at ON transactions (tx_type, created_at DESC, id DESC);
+
+-- Supersedes the single-column wallet index
+CREATE INDEX IF NOT EXISTS idx_transactions_wallet_created_at ON transactions (wallet_id, created_at DESC, id DESC);
+DROP INDEX IF EXISTS idx_transactions_wallet_id;
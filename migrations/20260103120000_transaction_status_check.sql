@@ -0,0 +1,10 @@
+-- transactions.tx_type and status mirror the TransactionType and TransactionStatus enums.
+-- The database only checks the values; which status may follow which is enforced in transaction_service.
+
+ALTER TABLE transactions
+    ADD CONSTRAINT transactions_tx_type_check
+    CHECK (tx_type IN ('fiat_deposit', 'btc_withdrawal', 'nostr_send'));
+
+ALTER TABLE transactions
+    ADD CONSTRAINT transactions_status_check
+    CHECK (status IN ('pending', 'admin_hold', 'completed', 'failed', 'refunded'));
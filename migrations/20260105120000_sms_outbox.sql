@@ -0,0 +1,16 @@
+-- Outgoing SMS are queued in the same database transaction as the change they announce,
+-- and sent by a background worker once it commits.
+
+CREATE TABLE IF NOT EXISTS sms_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    phone_number TEXT NOT NULL, -- E.164
+    body TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    provider_message_id TEXT,
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_sms_messages_due ON sms_messages (next_attempt_at) WHERE sent_at IS NULL;
@@ -0,0 +1,23 @@
+-- Durable queue of Lightning payouts owed for fiat deposits. A job is committed together with its
+-- transaction, so a payout is never lost to a restart; its status is committed as 'sending' before
+-- the payment goes out, so an interrupted payout is held for an admin instead of being sent twice.
+
+CREATE TABLE IF NOT EXISTS payout_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transaction_id UUID NOT NULL UNIQUE REFERENCES transactions(id),
+    status TEXT NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'sending', 'succeeded', 'held', 'cancelled')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    payment_hash TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_payout_jobs_due ON payout_jobs (next_attempt_at) WHERE status = 'queued';
+CREATE INDEX IF NOT EXISTS idx_payout_jobs_sending ON payout_jobs (updated_at) WHERE status = 'sending';
+
+-- Deposits recorded before the queue existed were never paid out
+INSERT INTO payout_jobs (transaction_id)
+SELECT id FROM transactions WHERE tx_type = 'fiat_deposit' AND status = 'pending'
+ON CONFLICT (transaction_id) DO NOTHING;
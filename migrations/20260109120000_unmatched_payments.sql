@@ -0,0 +1,31 @@
+-- Lightning payments the node reports that match no transaction are parked here for admins
+-- to attribute to a wallet, refund, or dismiss. Received sats wait in the suspense account meanwhile.
+
+INSERT INTO ledger_accounts (code, kind) VALUES
+    ('system:suspense', 'system') -- received sats not yet attributed or refunded
+ON CONFLICT (code) DO NOTHING;
+
+ALTER TABLE transactions DROP CONSTRAINT IF EXISTS transactions_tx_type_check;
+ALTER TABLE transactions
+    ADD CONSTRAINT transactions_tx_type_check
+    CHECK (tx_type IN ('fiat_deposit', 'btc_withdrawal', 'nostr_send', 'lightning_receive'));
+
+CREATE TABLE IF NOT EXISTS unmatched_payments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    payment_hash TEXT NOT NULL UNIQUE,
+    direction TEXT NOT NULL CHECK (direction IN ('incoming', 'outgoing')),
+    amount_sats BIGINT NOT NULL,
+    fee_sats BIGINT NOT NULL DEFAULT 0,
+    bolt11 TEXT,
+    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'attributed', 'refunded', 'dismissed')),
+    wallet_id UUID REFERENCES wallets(id),
+    transaction_id UUID REFERENCES transactions(id),
+    refund_payment_hash TEXT,
+    resolved_by UUID REFERENCES admin_users(id),
+    resolution_notes TEXT,
+    resolved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_unmatched_payments_status_created_at ON unmatched_payments (status, created_at DESC);
@@ -0,0 +1,19 @@
+-- Locked-price quotes for buys (Paystack deposits) and sells (sell orders).
+-- A quote is honoured once, if used before it expires; otherwise the trade is re-quoted.
+
+CREATE TABLE IF NOT EXISTS quotes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    side TEXT NOT NULL CHECK (side IN ('buy', 'sell')),
+    market_rate TEXT NOT NULL,
+    rate TEXT NOT NULL,
+    spread_bps BIGINT NOT NULL,
+    amount_kobo BIGINT NOT NULL,
+    fee_kobo BIGINT NOT NULL,
+    amount_sats BIGINT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ,
+    used_reference TEXT UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_quotes_expires_at ON quotes (expires_at) WHERE used_at IS NULL;
@@ -0,0 +1,11 @@
+-- Helpers chosen when a wallet's social backup is set up. Helper i holds recovery share i,
+-- and recovery only sends shares to, and accepts shares from, these helpers.
+
+CREATE TABLE IF NOT EXISTS wallet_recovery_helpers (
+    wallet_id UUID NOT NULL REFERENCES wallets(id) ON DELETE CASCADE,
+    share_index SMALLINT NOT NULL CHECK (share_index >= 1),
+    helper_npub TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (wallet_id, share_index),
+    UNIQUE (wallet_id, helper_npub)
+);
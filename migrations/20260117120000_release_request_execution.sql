@@ -0,0 +1,13 @@
+-- Approved releases are sent outside the approval's database transaction: a request is 'executing'
+-- while its payment is in flight, then 'approved' once it went out or 'failed' if it did not.
+
+ALTER TABLE release_requests DROP CONSTRAINT IF EXISTS release_requests_status_check;
+ALTER TABLE release_requests
+    ADD CONSTRAINT release_requests_status_check
+    CHECK (status IN ('pending', 'executing', 'approved', 'failed', 'rejected', 'expired'));
+
+ALTER TABLE release_requests ADD COLUMN IF NOT EXISTS failure_reason TEXT;
+
+-- At most one payment in flight per transaction
+CREATE UNIQUE INDEX IF NOT EXISTS idx_release_requests_executing_transaction
+    ON release_requests (transaction_id) WHERE status = 'executing';
@@ -0,0 +1,4 @@
+-- Deposits are credited to the buyer's wallet. A wallet with a payout destination, a Lightning address
+-- or LNURL-pay code the user controls, also has each deposit forwarded there by the payout worker.
+
+ALTER TABLE wallets ADD COLUMN IF NOT EXISTS payout_destination TEXT;
@@ -0,0 +1,7 @@
+-- Refunds are sent outside the database transaction that claims them: an unmatched payment is
+-- 'refunding' while its refund is in flight, then 'refunded', or 'open' again if the send failed.
+
+ALTER TABLE unmatched_payments DROP CONSTRAINT IF EXISTS unmatched_payments_status_check;
+ALTER TABLE unmatched_payments
+    ADD CONSTRAINT unmatched_payments_status_check
+    CHECK (status IN ('open', 'refunding', 'attributed', 'refunded', 'dismissed'));
@@ -1,18 +1,38 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, StatusCode},
     Json,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 use tracing::info;
+use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
     app_state::AppState,
-    domain::{models::Transaction, types::Sats},
+    domain::{
+        admin::AdminRole,
+        audit::{AuditAction, AuditEvent},
+        models::{ReleaseRequest, UnmatchedPayment, WebhookEvent},
+        transaction::{TransactionStatus, TransactionType},
+        types::Sats,
+    },
     error::AppError,
-    services::admin_service,
+    middleware::admin_auth::AuthenticatedAdmin,
+    services::{
+        admin_service::{self, LoginOutcome},
+        admin_user_service::{self, AdminAccount},
+        audit_service::{self, ChainVerification},
+        lightning_payment_service, recovery_service,
+        release_service::{self, ReleaseOutcome},
+        session_service::{self, SessionTokens},
+        trade_service::{self, TradeFilter, TradePage},
+        totp_service::{self, TotpEnrollment},
+        webhook_service,
+    },
+    utils::client_ip::client_ip,
 };
 
 #[derive(Debug, Deserialize, Validate)]
@@ -27,54 +47,308 @@ pub struct AdminLoginPayload {
 pub struct AdminLoginResponse {
     pub success: bool,
     pub message: String,
-    pub token: Option<String>, // JWT token
+    pub token: Option<String>, // Access JWT
+    pub refresh_token: Option<String>, // Exchanged at /admin/token/refresh for a new token pair
+    pub expires_in: Option<i64>, // Seconds until `token` expires
+    pub totp_required: bool,
+    pub pre_auth_token: Option<String>, // Exchanged at /admin/login/totp when TOTP is enabled
+    pub password_change_required: bool,
+    pub password_change_token: Option<String>, // Exchanged at /admin/login/password with a new password
+}
+
+impl AdminLoginResponse {
+    fn authenticated(tokens: SessionTokens) -> Self {
+        Self {
+            success: true,
+            message: "Login successful".to_string(),
+            token: Some(tokens.access_token),
+            refresh_token: Some(tokens.refresh_token),
+            expires_in: Some(tokens.expires_in),
+            totp_required: false,
+            pre_auth_token: None,
+            password_change_required: false,
+            password_change_token: None,
+        }
+    }
+}
+
+impl From<LoginOutcome> for AdminLoginResponse {
+    fn from(outcome: LoginOutcome) -> Self {
+        match outcome {
+            LoginOutcome::Authenticated(tokens) => Self::authenticated(tokens),
+            LoginOutcome::TotpRequired(pre_auth_token) => Self {
+                success: true,
+                message: "Enter your TOTP code to finish logging in".to_string(),
+                token: None,
+                refresh_token: None,
+                expires_in: None,
+                totp_required: true,
+                pre_auth_token: Some(pre_auth_token),
+                password_change_required: false,
+                password_change_token: None,
+            },
+            LoginOutcome::PasswordChangeRequired(password_change_token) => Self {
+                success: true,
+                message: "Choose a new password to finish logging in".to_string(),
+                token: None,
+                refresh_token: None,
+                expires_in: None,
+                totp_required: false,
+                pre_auth_token: None,
+                password_change_required: true,
+                password_change_token: Some(password_change_token),
+            },
+        }
+    }
 }
 
 /// POST /admin/login
-/// Handles admin login and issues a JWT token upon successful authentication.
+/// Checks the password. Issues the JWT directly, or a short-lived pre-auth token if the admin has TOTP enabled.
+/// Repeated failures back off and then lock out with `429 Too Many Requests`.
 pub async fn login_handler(
     State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<AdminLoginPayload>,
 ) -> Result<Json<AdminLoginResponse>, AppError> {
-    info!("Admin login attempt for user: {}", payload.username);
+    let client_ip = client_ip(&headers, peer, app_state.config.trust_forwarded_for);
+    info!("Admin login attempt for user: {} from {}", payload.username, client_ip);
 
     payload.validate()?;
 
-    let token = admin_service::authenticate_admin_user(
-        app_state.db_pool.clone(),
-        app_state.config.clone(),
+    let outcome = admin_service::authenticate_admin_user(
+        &app_state,
         &payload.username,
         &payload.password,
+        client_ip,
+        audit_service::request_id(&headers),
     )
     .await?;
 
-    // TODO: Implement 2FA via Nostr NIP-46 or TOTP after initial login.
-    // This would likely involve an additional step/endpoint or a more complex token.
+    Ok(Json(outcome.into()))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TotpLoginPayload {
+    #[validate(length(min = 1, message = "Pre-auth token is required"))]
+    pub pre_auth_token: String,
+    #[validate(length(min = 1, message = "Code is required"))]
+    pub code: String, // TOTP code or recovery code
+}
+
+/// POST /admin/login/totp
+/// Second login step: exchanges the pre-auth token and a TOTP or recovery code for the JWT,
+/// or for a password-change token if the admin's password was reset.
+pub async fn login_totp_handler(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<TotpLoginPayload>,
+) -> Result<Json<AdminLoginResponse>, AppError> {
+    payload.validate()?;
+
+    let outcome = admin_service::complete_totp_login(
+        &app_state,
+        &payload.pre_auth_token,
+        &payload.code,
+        client_ip(&headers, peer, app_state.config.trust_forwarded_for),
+        audit_service::request_id(&headers),
+    )
+    .await?;
+
+    Ok(Json(outcome.into()))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PasswordChangeLoginPayload {
+    #[validate(length(min = 1, message = "Password change token is required"))]
+    pub password_change_token: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters long"))]
+    pub new_password: String,
+}
+
+/// POST /admin/login/password
+/// Last login step for admins whose password was set or reset by someone else:
+/// exchanges the password-change token and a new password for the JWT.
+pub async fn login_password_change_handler(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<PasswordChangeLoginPayload>,
+) -> Result<Json<AdminLoginResponse>, AppError> {
+    payload.validate()?;
+
+    let tokens = admin_service::change_password_with_token(
+        &app_state.db_pool,
+        &app_state.config,
+        &payload.password_change_token,
+        &payload.new_password,
+        audit_service::request_id(&headers),
+    )
+    .await?;
+
+    Ok(Json(AdminLoginResponse::authenticated(tokens)))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RefreshTokenPayload {
+    #[validate(length(min = 1, message = "Refresh token is required"))]
+    pub refresh_token: String,
+}
+
+/// POST /admin/token/refresh
+/// Exchanges a refresh token for a new access token and refresh token. Each refresh token works once.
+pub async fn refresh_token_handler(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<RefreshTokenPayload>,
+) -> Result<Json<AdminLoginResponse>, AppError> {
+    payload.validate()?;
+
+    let tokens = session_service::refresh(&app_state.db_pool, &app_state.config, &payload.refresh_token).await?;
 
     Ok(Json(AdminLoginResponse {
+        message: "Token refreshed".to_string(),
+        ..AdminLoginResponse::authenticated(tokens)
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogoutResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+/// POST /admin/logout
+/// Ends the caller's session: the access token and the session's refresh token stop working.
+pub async fn logout_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+) -> Result<Json<LogoutResponse>, AppError> {
+    session_service::logout(
+        &app_state.db_pool,
+        &admin.actor(),
+        admin.session_id,
+        admin.token_id,
+        admin.token_expires_at,
+    )
+    .await?;
+
+    Ok(Json(LogoutResponse {
+        success: true,
+        message: "Logged out".to_string(),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct TotpEnrollResponse {
+    pub success: bool,
+    pub enrollment: TotpEnrollment,
+}
+
+/// POST /admin/totp/enroll
+/// Generates a TOTP secret for the calling admin. It is inactive until confirmed with a code.
+pub async fn totp_enroll_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+) -> Result<Json<TotpEnrollResponse>, AppError> {
+    let enrollment = totp_service::begin_enrollment(
+        &app_state.db_pool,
+        app_state.key_provider.as_ref(),
+        admin.admin_id,
+        &admin.username,
+    )
+    .await?;
+    audit_service::record_now(
+        &app_state.db_pool,
+        AuditEvent::new(&admin.actor(), AuditAction::TotpEnroll).target("admin_user", admin.admin_id),
+    )
+    .await?;
+
+    Ok(Json(TotpEnrollResponse {
         success: true,
-        message: "Login successful".to_string(),
-        token: Some(token),
+        enrollment,
     }))
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct TotpConfirmPayload {
+    #[validate(length(equal = 6, message = "TOTP code must be 6 digits"))]
+    pub code: String,
+}
+
 #[derive(Debug, Serialize)]
-pub struct AdminTradesResponse {
-    pub trades: Vec<Transaction>, // Detailed transaction information
+pub struct TotpConfirmResponse {
+    pub success: bool,
+    pub message: String,
+    pub recovery_codes: Vec<String>, // Shown once; each can replace a TOTP code a single time
+}
+
+/// POST /admin/totp/confirm
+/// Activates TOTP for the calling admin and returns their recovery codes.
+pub async fn totp_confirm_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Json(payload): Json<TotpConfirmPayload>,
+) -> Result<Json<TotpConfirmResponse>, AppError> {
+    payload.validate()?;
+
+    let recovery_codes = totp_service::confirm_enrollment(
+        &app_state.db_pool,
+        app_state.key_provider.as_ref(),
+        admin.admin_id,
+        &payload.code,
+    )
+    .await?;
+    audit_service::record_now(
+        &app_state.db_pool,
+        AuditEvent::new(&admin.actor(), AuditAction::TotpEnable).target("admin_user", admin.admin_id),
+    )
+    .await?;
+
+    Ok(Json(TotpConfirmResponse {
+        success: true,
+        message: "TOTP enabled. Store these recovery codes somewhere safe.".to_string(),
+        recovery_codes,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TradesQuery {
+    pub cursor: Option<String>, // `next_cursor` of the previous page
+    pub limit: Option<i64>,     // Default 50, at most 200
+    pub status: Option<TransactionStatus>,
+    pub tx_type: Option<TransactionType>,
+    pub wallet_id: Option<Uuid>,
+    pub phone_number: Option<String>,
+    pub min_amount_sats: Option<i64>,
+    pub max_amount_sats: Option<i64>,
+    pub from: Option<DateTime<Utc>>, // RFC 3339, inclusive
+    pub to: Option<DateTime<Utc>>,   // RFC 3339, exclusive
+    pub external_id: Option<String>,
 }
 
 /// GET /admin/trades
-/// Retrieves a list of all trades/transactions for admin review.
+/// Searches transactions, newest first, one page at a time, with totals per status across all matches.
 pub async fn get_trades_handler(
     State(app_state): State<Arc<AppState>>,
-    // TODO: Add authentication layer here (e.g., `axum_extra::extract::PrivateClaim<Claims>`)
-    // _auth_guard: AuthGuard, // Placeholder for an authentication guard
-) -> Result<Json<AdminTradesResponse>, AppError> {
-    info!("Admin requested all trades.");
+    admin: AuthenticatedAdmin,
+    Query(query): Query<TradesQuery>,
+) -> Result<Json<TradePage>, AppError> {
+    info!("Admin {} searched trades.", admin.username);
 
-    let trades = admin_service::fetch_all_transactions(app_state.db_pool.clone()).await?;
+    let filter = TradeFilter {
+        status: query.status,
+        tx_type: query.tx_type,
+        wallet_id: query.wallet_id,
+        phone_number: query.phone_number,
+        min_amount_sats: query.min_amount_sats,
+        max_amount_sats: query.max_amount_sats,
+        from: query.from,
+        to: query.to,
+        external_id: query.external_id,
+    };
+    let page = trade_service::search(&app_state.db_pool, filter, query.cursor.as_deref(), query.limit).await?;
 
-    Ok(Json(AdminTradesResponse { trades }))
+    Ok(Json(page))
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -92,24 +366,28 @@ pub struct ManualReleaseResponse {
     pub success: bool,
     pub message: String,
     pub transaction_id: String,
+    pub status: String, // 'released' | 'pending_approval'
+    pub release_request: Option<ReleaseRequest>,
 }
 
 /// POST /admin/manual-release
-/// Allows an admin to manually release funds for a given transaction.
+/// Allows an operator to manually release funds for a given transaction.
+/// Amounts above the approval threshold return `202 Accepted` with a pending release request instead.
 pub async fn manual_release_handler(
     State(app_state): State<Arc<AppState>>,
-    // TODO: Add authentication layer here
+    admin: AuthenticatedAdmin,
     Json(payload): Json<ManualReleasePayload>,
-) -> Result<Json<ManualReleaseResponse>, AppError> {
+) -> Result<(StatusCode, Json<ManualReleaseResponse>), AppError> {
     info!(
-        "Admin initiated manual release for transaction ID: {}",
-        payload.transaction_id
+        "Admin {} initiated manual release for transaction ID: {}",
+        admin.username, payload.transaction_id
     );
 
     payload.validate()?;
 
-    admin_service::manual_release_funds(
+    let outcome = release_service::request_release(
         app_state.clone(),
+        &admin.actor(),
         &payload.transaction_id,
         payload.amount_sats,
         &payload.recipient_nostr_pubkey,
@@ -117,12 +395,527 @@ pub async fn manual_release_handler(
     )
     .await?;
 
-    Ok(Json(ManualReleaseResponse {
-        success: true,
-        message: format!(
-            "Funds manually released for transaction ID: {}",
-            payload.transaction_id
+    let response = match outcome {
+        ReleaseOutcome::Released(request) => (
+            StatusCode::OK,
+            Json(ManualReleaseResponse {
+                success: true,
+                message: format!(
+                    "Funds manually released for transaction ID: {}",
+                    payload.transaction_id
+                ),
+                transaction_id: payload.transaction_id,
+                status: "released".to_string(),
+                release_request: Some(request),
+            }),
+        ),
+        ReleaseOutcome::PendingApproval(request) => (
+            StatusCode::ACCEPTED,
+            Json(ManualReleaseResponse {
+                success: true,
+                message: "Release exceeds the approval threshold and needs a second admin's approval"
+                    .to_string(),
+                transaction_id: payload.transaction_id,
+                status: "pending_approval".to_string(),
+                release_request: Some(request),
+            }),
         ),
-        transaction_id: payload.transaction_id,
+    };
+    Ok(response)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReleaseRequestsQuery {
+    pub status: Option<String>, // 'pending' | 'executing' | 'approved' | 'failed' | 'rejected' | 'expired'
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReleaseRequestsResponse {
+    pub release_requests: Vec<ReleaseRequest>,
+}
+
+/// GET /admin/release-requests
+/// Lists release requests, newest first, optionally filtered by `?status=`.
+pub async fn list_release_requests_handler(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<ReleaseRequestsQuery>,
+) -> Result<Json<ReleaseRequestsResponse>, AppError> {
+    let release_requests = release_service::list(&app_state.db_pool, query.status.as_deref()).await?;
+    Ok(Json(ReleaseRequestsResponse { release_requests }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReleaseDecisionResponse {
+    pub success: bool,
+    pub message: String,
+    pub release_request: ReleaseRequest,
+}
+
+/// POST /admin/release-requests/:id/approve
+/// Approves a pending release and sends the payment. The approver must differ from the requester.
+pub async fn approve_release_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(request_id): Path<String>,
+) -> Result<Json<ReleaseDecisionResponse>, AppError> {
+    let request_id = Uuid::parse_str(&request_id)
+        .map_err(|_| AppError::BadRequest("Invalid release request ID format".to_string()))?;
+
+    info!("Admin {} approving release request {}", admin.username, request_id);
+
+    let release_request = release_service::approve(app_state.clone(), &admin.actor(), request_id).await?;
+
+    Ok(Json(ReleaseDecisionResponse {
+        success: true,
+        message: "Release approved and funds sent".to_string(),
+        release_request,
+    }))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RejectReleasePayload {
+    #[validate(length(min = 1, message = "A reason is required"))]
+    pub reason: String,
+}
+
+/// POST /admin/release-requests/:id/reject
+/// Rejects a pending release with a reason. Like approval, this needs a different admin.
+pub async fn reject_release_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(request_id): Path<String>,
+    Json(payload): Json<RejectReleasePayload>,
+) -> Result<Json<ReleaseDecisionResponse>, AppError> {
+    payload.validate()?;
+    let request_id = Uuid::parse_str(&request_id)
+        .map_err(|_| AppError::BadRequest("Invalid release request ID format".to_string()))?;
+
+    info!("Admin {} rejecting release request {}", admin.username, request_id);
+
+    let release_request =
+        release_service::reject(&app_state.db_pool, &admin.actor(), request_id, &payload.reason).await?;
+
+    Ok(Json(ReleaseDecisionResponse {
+        success: true,
+        message: "Release rejected".to_string(),
+        release_request,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnmatchedPaymentsQuery {
+    pub status: Option<String>, // 'open' | 'refunding' | 'attributed' | 'refunded' | 'dismissed'
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnmatchedPaymentsResponse {
+    pub unmatched_payments: Vec<UnmatchedPayment>,
+}
+
+/// GET /admin/unmatched-payments
+/// Lists Lightning payments that matched no transaction, newest first, optionally filtered by `?status=`.
+pub async fn list_unmatched_payments_handler(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<UnmatchedPaymentsQuery>,
+) -> Result<Json<UnmatchedPaymentsResponse>, AppError> {
+    let unmatched_payments =
+        lightning_payment_service::list_unmatched(&app_state.db_pool, query.status.as_deref()).await?;
+    Ok(Json(UnmatchedPaymentsResponse { unmatched_payments }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnmatchedPaymentResponse {
+    pub success: bool,
+    pub message: String,
+    pub unmatched_payment: UnmatchedPayment,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AttributeUnmatchedPayload {
+    pub wallet_id: Option<String>, // Not needed for a send whose transaction has since been recorded
+    pub notes: Option<String>,
+}
+
+/// POST /admin/unmatched-payments/:id/attribute
+/// Assigns an unmatched payment to a wallet and records it as a completed transaction.
+pub async fn attribute_unmatched_payment_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(unmatched_id): Path<String>,
+    Json(payload): Json<AttributeUnmatchedPayload>,
+) -> Result<Json<UnmatchedPaymentResponse>, AppError> {
+    payload.validate()?;
+    let unmatched_id = parse_unmatched_id(&unmatched_id)?;
+    let wallet_id = payload
+        .wallet_id
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|_| AppError::BadRequest("Invalid wallet ID format".to_string()))?;
+
+    info!("Admin {} attributing unmatched payment {}", admin.username, unmatched_id);
+
+    let unmatched_payment = lightning_payment_service::attribute(
+        &app_state.db_pool,
+        &admin.actor(),
+        unmatched_id,
+        wallet_id,
+        payload.notes.as_deref(),
+    )
+    .await?;
+
+    Ok(Json(UnmatchedPaymentResponse {
+        success: true,
+        message: "Payment attributed".to_string(),
+        unmatched_payment,
+    }))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RefundUnmatchedPayload {
+    #[validate(length(min = 1, message = "Recipient cannot be empty"))]
+    pub recipient: String, // Invoice or address to send the sats back to
+    pub notes: Option<String>,
+}
+
+/// POST /admin/unmatched-payments/:id/refund
+/// Sends an unmatched incoming payment back to the payer.
+pub async fn refund_unmatched_payment_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(unmatched_id): Path<String>,
+    Json(payload): Json<RefundUnmatchedPayload>,
+) -> Result<Json<UnmatchedPaymentResponse>, AppError> {
+    payload.validate()?;
+    let unmatched_id = parse_unmatched_id(&unmatched_id)?;
+
+    info!("Admin {} refunding unmatched payment {}", admin.username, unmatched_id);
+
+    let unmatched_payment = lightning_payment_service::refund(
+        &app_state,
+        &admin.actor(),
+        unmatched_id,
+        &payload.recipient,
+        payload.notes.as_deref(),
+    )
+    .await?;
+
+    Ok(Json(UnmatchedPaymentResponse {
+        success: true,
+        message: "Payment refunded".to_string(),
+        unmatched_payment,
+    }))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DismissUnmatchedPayload {
+    #[validate(length(min = 1, message = "Notes are required"))]
+    pub notes: String,
+}
+
+/// POST /admin/unmatched-payments/:id/dismiss
+/// Closes an unmatched payment without attributing or refunding it. Received sats go to the treasury.
+pub async fn dismiss_unmatched_payment_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(unmatched_id): Path<String>,
+    Json(payload): Json<DismissUnmatchedPayload>,
+) -> Result<Json<UnmatchedPaymentResponse>, AppError> {
+    payload.validate()?;
+    let unmatched_id = parse_unmatched_id(&unmatched_id)?;
+
+    info!("Admin {} dismissing unmatched payment {}", admin.username, unmatched_id);
+
+    let unmatched_payment =
+        lightning_payment_service::dismiss(&app_state.db_pool, &admin.actor(), unmatched_id, &payload.notes).await?;
+
+    Ok(Json(UnmatchedPaymentResponse {
+        success: true,
+        message: "Payment dismissed".to_string(),
+        unmatched_payment,
+    }))
+}
+
+fn parse_unmatched_id(unmatched_id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(unmatched_id).map_err(|_| AppError::BadRequest("Invalid unmatched payment ID format".to_string()))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct StartRecoveryPayload {
+    /// How the wallet's owner was verified before starting the recovery.
+    #[validate(length(min = 1, message = "Notes are required"))]
+    pub notes: String,
+    /// Nostr npub of the owner's new device, confirmed with the owner. The rebuilt key is sent only there.
+    #[validate(length(min = 1, message = "Device npub is required"))]
+    pub device_npub: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StartRecoveryResponse {
+    pub success: bool,
+    pub message: String,
+    pub recovery_id: Uuid,
+}
+
+/// POST /admin/wallets/:id/recovery
+/// Sends the wallet's recovery shares to the helpers stored when its social backup was set up.
+pub async fn start_recovery_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(wallet_id): Path<String>,
+    Json(payload): Json<StartRecoveryPayload>,
+) -> Result<Json<StartRecoveryResponse>, AppError> {
+    payload.validate()?;
+    let wallet_id = Uuid::parse_str(&wallet_id)
+        .map_err(|_| AppError::BadRequest("Invalid wallet ID format".to_string()))?;
+
+    info!("Admin {} starting recovery of wallet {}", admin.username, wallet_id);
+
+    let recovery_id =
+        recovery_service::initiate_recovery_request(
+        &app_state,
+        &admin.actor(),
+        wallet_id,
+        &payload.device_npub,
+        &payload.notes,
+    )
+    .await?;
+
+    Ok(Json(StartRecoveryResponse {
+        success: true,
+        message: "Recovery shares sent to the wallet's helpers".to_string(),
+        recovery_id,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplayWebhookResponse {
+    pub success: bool,
+    pub message: String,
+    pub webhook: WebhookEvent,
+}
+
+/// POST /admin/webhooks/:id/replay
+/// Re-processes a stored webhook from its original raw payload and returns its new state.
+pub async fn replay_webhook_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(webhook_id): Path<String>,
+) -> Result<Json<ReplayWebhookResponse>, AppError> {
+    let webhook_id = Uuid::parse_str(&webhook_id)
+        .map_err(|_| AppError::BadRequest("Invalid webhook ID format".to_string()))?;
+
+    info!("Admin {} requested replay of webhook {}", admin.username, webhook_id);
+
+    let before = webhook_service::get_event(&app_state.db_pool, webhook_id).await?;
+    webhook_service::replay(&app_state.db_pool, webhook_id).await?;
+    webhook_service::process_event(&app_state, webhook_id).await?;
+    let webhook = webhook_service::get_event(&app_state.db_pool, webhook_id).await?;
+
+    audit_service::record_now(
+        &app_state.db_pool,
+        AuditEvent::new(&admin.actor(), AuditAction::WebhookReplay)
+            .target("webhook_event", webhook_id)
+            .before(&before)
+            .after(&webhook),
+    )
+    .await?;
+
+    let message = if webhook.processed {
+        "Webhook replayed successfully".to_string()
+    } else {
+        format!(
+            "Webhook replay failed and will be retried: {}",
+            webhook.last_error.as_deref().unwrap_or("unknown error")
+        )
+    };
+
+    Ok(Json(ReplayWebhookResponse {
+        success: webhook.processed,
+        message,
+        webhook,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditVerifyResponse {
+    pub success: bool,
+    pub verification: ChainVerification,
+}
+
+/// GET /admin/audit/verify
+/// Re-computes the audit log hash chain and reports the first tampered or missing row, if any.
+pub async fn verify_audit_log_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+) -> Result<Json<AuditVerifyResponse>, AppError> {
+    info!("Admin {} requested audit log verification", admin.username);
+
+    let verification = audit_service::verify_chain(&app_state.db_pool).await?;
+
+    Ok(Json(AuditVerifyResponse {
+        success: verification.valid,
+        verification,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminUsersResponse {
+    pub admins: Vec<AdminAccount>,
+}
+
+/// GET /admin/users
+/// Lists all admin accounts, including inactive ones.
+pub async fn list_admin_users_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<AdminUsersResponse>, AppError> {
+    let admins = admin_user_service::list(&app_state.db_pool).await?;
+    Ok(Json(AdminUsersResponse { admins }))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateAdminUserPayload {
+    #[validate(length(min = 1, message = "Username cannot be empty"))]
+    pub username: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters long"))]
+    pub password: String, // Temporary; the new admin must change it on first login
+    pub role: AdminRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminUserResponse {
+    pub success: bool,
+    pub message: String,
+    pub admin: AdminAccount,
+}
+
+/// POST /admin/users
+/// Creates an admin with a temporary password and a role.
+pub async fn create_admin_user_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Json(payload): Json<CreateAdminUserPayload>,
+) -> Result<(StatusCode, Json<AdminUserResponse>), AppError> {
+    payload.validate()?;
+
+    let created = admin_user_service::create(
+        &app_state.db_pool,
+        &admin.actor(),
+        &payload.username,
+        &payload.password,
+        payload.role,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(AdminUserResponse {
+            success: true,
+            message: "Admin created; they must change their password on first login".to_string(),
+            admin: created,
+        }),
+    ))
+}
+
+/// POST /admin/users/:id/deactivate
+/// Deactivates an admin. Their tokens stop working immediately.
+pub async fn deactivate_admin_user_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(admin_id): Path<String>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let admin_id = parse_admin_id(&admin_id)?;
+
+    let deactivated = admin_user_service::deactivate(&app_state.db_pool, &admin.actor(), admin_id).await?;
+
+    Ok(Json(AdminUserResponse {
+        success: true,
+        message: "Admin deactivated".to_string(),
+        admin: deactivated,
+    }))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResetAdminPasswordPayload {
+    #[validate(length(min = 8, message = "Password must be at least 8 characters long"))]
+    pub new_password: String, // Temporary; must be changed at the next login
+}
+
+/// POST /admin/users/:id/reset-password
+/// Sets a temporary password and forces the admin to change it at their next login.
+pub async fn reset_admin_password_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(admin_id): Path<String>,
+    Json(payload): Json<ResetAdminPasswordPayload>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    payload.validate()?;
+    let admin_id = parse_admin_id(&admin_id)?;
+
+    let reset = admin_user_service::reset_password(
+        &app_state.db_pool,
+        &admin.actor(),
+        admin_id,
+        &payload.new_password,
+    )
+    .await?;
+
+    Ok(Json(AdminUserResponse {
+        success: true,
+        message: "Password reset; the admin must change it at their next login".to_string(),
+        admin: reset,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetAdminRolePayload {
+    pub role: AdminRole,
+}
+
+/// PUT /admin/users/:id/role
+/// Assigns a role to an admin. Takes effect on their next request.
+pub async fn set_admin_role_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(admin_id): Path<String>,
+    Json(payload): Json<SetAdminRolePayload>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let admin_id = parse_admin_id(&admin_id)?;
+
+    let updated = admin_user_service::set_role(&app_state.db_pool, &admin.actor(), admin_id, payload.role).await?;
+
+    Ok(Json(AdminUserResponse {
+        success: true,
+        message: format!("Role set to {}", payload.role),
+        admin: updated,
+    }))
+}
+
+fn parse_admin_id(admin_id: &str) -> Result<Uuid, AppError> {
+    Uuid::parse_str(admin_id).map_err(|_| AppError::BadRequest("Invalid admin ID format".to_string()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeSessionsResponse {
+    pub success: bool,
+    pub message: String,
+    pub sessions_revoked: u64,
+}
+
+/// POST /admin/users/:id/revoke-sessions
+/// Signs an admin out of every session, e.g. after a lost laptop.
+pub async fn revoke_admin_sessions_handler(
+    State(app_state): State<Arc<AppState>>,
+    admin: AuthenticatedAdmin,
+    Path(admin_id): Path<String>,
+) -> Result<Json<RevokeSessionsResponse>, AppError> {
+    let admin_id = parse_admin_id(&admin_id)?;
+
+    let sessions_revoked = session_service::revoke_all_sessions(&app_state.db_pool, &admin.actor(), admin_id).await?;
+
+    Ok(Json(RevokeSessionsResponse {
+        success: true,
+        message: format!("Revoked {} sessions", sessions_revoked),
+        sessions_revoked,
     }))
 }
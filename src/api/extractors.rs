@@ -0,0 +1,157 @@
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::HeaderMap,
+};
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::{
+    app_state::AppState,
+    bitcoin::breez_webhook,
+    config::UnsignedWebhookPolicy,
+    error::AppError,
+    utils::signature::{verify_hmac_sha256, verify_hmac_sha512},
+};
+
+pub const PAYSTACK_SIGNATURE_HEADER: &str = "x-paystack-signature";
+
+/// A Paystack webhook whose `x-paystack-signature` has been verified against the raw body.
+///
+/// The raw bytes and headers are kept so the exact payload can be persisted, and the body is
+/// only deserialized after the signature checks out.
+#[derive(Debug)]
+pub struct VerifiedPaystackWebhook<T> {
+    pub headers: HeaderMap,
+    pub raw_body: Bytes,
+    pub payload: T,
+}
+
+#[async_trait]
+impl<T> FromRequest<Arc<AppState>> for VerifiedPaystackWebhook<T>
+where
+    T: DeserializeOwned,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let headers = req.headers().clone();
+        let signature = headers
+            .get(PAYSTACK_SIGNATURE_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| {
+                warn!("Paystack webhook received without signature header.");
+                AppError::Unauthorized("Missing Paystack signature".to_string())
+            })?
+            .to_string();
+
+        let raw_body = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read request body: {}", e)))?;
+
+        let secret = state.config.paystack_secret_key.expose_secret();
+        if !verify_hmac_sha512(secret.as_bytes(), &raw_body, &signature) {
+            warn!("Paystack webhook signature mismatch.");
+            return Err(AppError::Unauthorized("Invalid Paystack signature".to_string()));
+        }
+
+        let payload = serde_json::from_slice(&raw_body)?;
+
+        Ok(Self {
+            headers,
+            raw_body,
+            payload,
+        })
+    }
+}
+
+/// A Breez webhook that passed signature, timestamp and replay checks, or an unsigned one let
+/// through by `BREEZ_WEBHOOK_UNSIGNED_POLICY=warn`.
+///
+/// `event_id` is set for signed deliveries, whose ID has been claimed in Redis; release it if
+/// storing the delivery fails so the redelivery is not taken for a replay.
+#[derive(Debug)]
+pub struct VerifiedBreezWebhook<T> {
+    pub event_id: Option<String>,
+    pub headers: HeaderMap,
+    pub raw_body: Bytes,
+    pub payload: T,
+}
+
+#[async_trait]
+impl<T> FromRequest<Arc<AppState>> for VerifiedBreezWebhook<T>
+where
+    T: DeserializeOwned,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let headers = req.headers().clone();
+        let raw_body = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read request body: {}", e)))?;
+
+        let config = &state.config;
+        let signature = header_str(&headers, breez_webhook::SIGNATURE_HEADER);
+        let event_id = match (signature, config.breez_webhook_secret.as_ref()) {
+            (Some(signature), Some(secret)) => {
+                let timestamp = header_str(&headers, breez_webhook::TIMESTAMP_HEADER)
+                    .and_then(|t| t.parse::<i64>().ok())
+                    .ok_or_else(|| AppError::Unauthorized("Missing or invalid Breez timestamp".to_string()))?;
+                let event_id = header_str(&headers, breez_webhook::EVENT_ID_HEADER)
+                    .filter(|id| !id.is_empty())
+                    .ok_or_else(|| AppError::Unauthorized("Missing Breez event ID".to_string()))?;
+
+                let now = Utc::now().timestamp();
+                if let Err(e) = breez_webhook::check_timestamp(timestamp, now, config.breez_webhook_tolerance_seconds) {
+                    warn!("Breez webhook {} rejected: {}", event_id, e);
+                    return Err(e);
+                }
+                let signed = breez_webhook::signed_payload(timestamp, event_id, &raw_body);
+                if !verify_hmac_sha256(secret.expose_secret().as_bytes(), &signed, signature) {
+                    warn!("Breez webhook {} signature mismatch.", event_id);
+                    return Err(AppError::Unauthorized("Invalid Breez signature".to_string()));
+                }
+
+                // Only checked once the signature holds, so forged requests cannot use up real IDs
+                if !breez_webhook::claim_event_id(&state.redis_client, event_id, config.breez_webhook_tolerance_seconds)
+                    .await?
+                {
+                    warn!("Breez webhook {} replayed.", event_id);
+                    return Err(AppError::Conflict(format!("Breez webhook {} was already received", event_id)));
+                }
+                Some(event_id.to_string())
+            }
+            (signature, _) => match config.breez_webhook_unsigned_policy {
+                UnsignedWebhookPolicy::Reject => {
+                    warn!("Breez webhook received without signature header.");
+                    return Err(AppError::Unauthorized("Missing Breez signature".to_string()));
+                }
+                UnsignedWebhookPolicy::Warn => {
+                    warn!(
+                        "Accepting Breez webhook without {} (BREEZ_WEBHOOK_UNSIGNED_POLICY=warn)",
+                        if signature.is_some() { "a configured secret" } else { "a signature" }
+                    );
+                    None
+                }
+            },
+        };
+
+        let payload = serde_json::from_slice(&raw_body)?;
+
+        Ok(Self {
+            event_id,
+            headers,
+            raw_body,
+            payload,
+        })
+    }
+}
+
+fn header_str<'h>(headers: &'h HeaderMap, name: &str) -> Option<&'h str> {
+    headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim)
+}
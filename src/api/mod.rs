@@ -1,4 +1,6 @@
 pub mod admin;
+pub mod extractors;
+pub mod quotes;
 pub mod recovery;
 pub mod ussd;
 pub mod wallet;
@@ -0,0 +1,87 @@
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        money::ExchangeRate,
+        quote::QuoteSide,
+        types::{Kobo, Sats},
+    },
+    error::AppError,
+    services::quote_service::{self, TradeAmount},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateQuoteRequest {
+    pub side: QuoteSide,
+    pub amount_kobo: Option<i64>, // Buy: the Naira the user will pay
+    pub amount_sats: Option<i64>, // Sell: the sats the user will sell
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuoteData {
+    pub quote_id: String, // Signed; pass back as-is
+    pub side: QuoteSide,
+    pub market_rate: ExchangeRate,
+    pub rate: ExchangeRate,
+    pub spread_bps: i64,
+    pub amount_kobo: Kobo,
+    pub fee_kobo: Kobo,
+    pub net_kobo: Kobo,
+    pub amount_sats: Sats,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuoteResponse {
+    pub success: bool,
+    pub data: QuoteData,
+}
+
+/// POST /quotes
+/// Locks a buy price until `expires_at`. Buyers pass `quote_id` in the Paystack charge metadata.
+/// Sell quotes are refused until the off-ramp can redeem them.
+pub async fn create_quote_handler(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateQuoteRequest>,
+) -> Result<(StatusCode, Json<QuoteResponse>), AppError> {
+    let amount = match (payload.side, payload.amount_kobo, payload.amount_sats) {
+        (QuoteSide::Buy, Some(amount_kobo), None) => TradeAmount::Buy(Kobo(amount_kobo)),
+        (QuoteSide::Sell, None, Some(amount_sats)) => TradeAmount::Sell(Sats(amount_sats)),
+        (QuoteSide::Buy, _, _) => {
+            return Err(AppError::BadRequest("A buy quote takes amount_kobo only".to_string()))
+        }
+        (QuoteSide::Sell, _, _) => {
+            return Err(AppError::BadRequest("A sell quote takes amount_sats only".to_string()))
+        }
+    };
+    info!("Quote requested: {:?}", amount);
+
+    let (quote, quote_id) = quote_service::create(&app_state, amount).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(QuoteResponse {
+            success: true,
+            data: QuoteData {
+                quote_id,
+                side: quote.side,
+                market_rate: quote.market_rate.parse()?,
+                rate: quote.rate.parse()?,
+                spread_bps: quote.spread_bps,
+                amount_kobo: quote.amount_kobo,
+                fee_kobo: quote.fee_kobo,
+                net_kobo: quote
+                    .amount_kobo
+                    .checked_sub(quote.fee_kobo)
+                    .unwrap_or(Kobo::ZERO),
+                amount_sats: quote.amount_sats,
+                expires_at: quote.expires_at,
+            },
+        }),
+    ))
+}
@@ -1,49 +1,26 @@
-use axum::{extract::State, Json};
+use axum::{extract::State, http::StatusCode, Json};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::sync::Arc;
 use tracing::info;
 use validator::Validate;
 
-use crate::{app_state::AppState, error::AppError, services::recovery_service};
-
-#[derive(Debug, Deserialize, Validate)]
-pub struct RecoveryRequestPayload {
-    #[validate(length(min = 1, message = "Wallet ID is required"))]
-    pub wallet_id: String, // Uuid as string
-    #[validate(length(min = 1, message = "Nostr npub of helper is required"))]
-    pub helper_npubs: Vec<String>, // List of Nostr npubs of helpers
-}
-
-#[derive(Debug, Serialize)]
-pub struct RecoveryRequestResponse {
-    pub success: bool,
-    pub message: String,
-}
+use crate::{
+    app_state::AppState,
+    error::AppError,
+    services::recovery_service::{self, RecoveryProgress},
+};
 
 /// POST /recovery/request
-/// Initiates recovery share requests via Nostr.
-pub async fn request_recovery_handler(
-    State(app_state): State<Arc<AppState>>,
-    Json(payload): Json<RecoveryRequestPayload>,
-) -> Result<Json<RecoveryRequestResponse>, AppError> {
-    info!(
-        "Received recovery request for wallet ID: {}",
-        payload.wallet_id
-    );
-
-    payload.validate()?;
-
-    recovery_service::initiate_recovery_request(
-        app_state.clone(),
-        &payload.wallet_id,
-        &payload.helper_npubs,
+/// Retired: anyone could start a recovery and name its helpers. Operators now start recoveries
+/// under POST /admin/wallets/:id/recovery, with the helpers stored at wallet creation.
+pub async fn request_recovery_gone_handler() -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::GONE,
+        Json(json!({
+            "error": "Recoveries are started by an operator after verifying the wallet's owner; contact support",
+        })),
     )
-    .await?;
-
-    Ok(Json(RecoveryRequestResponse {
-        success: true,
-        message: "Recovery request initiated successfully".to_string(),
-    }))
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -60,10 +37,12 @@ pub struct SubmitSharePayload {
 pub struct SubmitShareResponse {
     pub success: bool,
     pub message: String,
+    pub progress: RecoveryProgress,
 }
 
 /// POST /recovery/submit
-/// Receives encrypted share from a helper.
+/// Receives encrypted share from a helper. Only the wallet's stored helper for that share can
+/// encrypt it to the coordinator key, so the share authenticates the helper.
 pub async fn submit_share_handler(
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<SubmitSharePayload>,
@@ -75,7 +54,7 @@ pub async fn submit_share_handler(
 
     payload.validate()?;
 
-    recovery_service::submit_recovery_share(
+    let progress = recovery_service::submit_recovery_share(
         app_state.clone(),
         &payload.wallet_id,
         &payload.encrypted_share,
@@ -83,8 +62,15 @@ pub async fn submit_share_handler(
     )
     .await?;
 
+    let message = if progress.key_delivered {
+        "Recovery share submitted; the wallet key was sent to the owner's new device"
+    } else {
+        "Recovery share submitted successfully"
+    };
+
     Ok(Json(SubmitShareResponse {
         success: true,
-        message: "Recovery share submitted successfully".to_string(),
+        message: message.to_string(),
+        progress,
     }))
 }
@@ -30,6 +30,7 @@ pub async fn ussd_callback_handler(
     let response_text = ussd_service::handle_ussd_request(
         app_state.redis_client.clone(),
         app_state.db_pool.clone(),
+        app_state.lightning.clone(),
         &payload.session_id,
         &payload.phone_number,
         &payload.text,
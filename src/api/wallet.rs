@@ -9,6 +9,7 @@ use uuid::Uuid;
 
 use crate::{
     app_state::AppState,
+    domain::{payout::PayoutDestination, recovery::RecoveryPolicy},
     error::AppError,
     services::wallet_service::{WalletInfo, WalletService},
 };
@@ -20,6 +21,15 @@ pub struct CreateWalletRequest {
     pub phone_number: String,
     #[serde(default = "default_backup_type")]
     pub backup_type: String,
+    /// Shares needed to recover the wallet key (social backup). Defaults to 3.
+    pub recovery_threshold: Option<u8>,
+    /// Helpers holding a share (social backup). Defaults to 5.
+    pub recovery_share_count: Option<u8>,
+    /// Nostr npubs of the helpers (social backup), one per share.
+    #[serde(default)]
+    pub recovery_helpers: Vec<String>,
+    /// Lightning address or LNURL to forward deposits to. Without one, deposits stay in the wallet.
+    pub payout_destination: Option<String>,
 }
 
 fn default_backup_type() -> String {
@@ -63,6 +73,23 @@ pub async fn create_wallet_handler(
         ));
     }
 
+    let default_policy = RecoveryPolicy::default();
+    let recovery_policy = RecoveryPolicy::new(
+        payload.recovery_threshold.unwrap_or(default_policy.threshold),
+        payload.recovery_share_count.unwrap_or(default_policy.share_count),
+    )?;
+    if backup_type != "social" && !payload.recovery_helpers.is_empty() {
+        return Err(AppError::BadRequest(
+            "recovery_helpers are only used with backup_type 'social'".to_string(),
+        ));
+    }
+
+    let payout_destination = payload
+        .payout_destination
+        .as_deref()
+        .map(PayoutDestination::parse)
+        .transpose()?;
+
     // Check if user already has a wallet
     let has_wallet = WalletService::user_has_wallet(&app_state.db_pool, user_id)
         .await?;
@@ -79,6 +106,9 @@ pub async fn create_wallet_handler(
         user_id,
         &payload.phone_number,
         &backup_type,
+        recovery_policy,
+        &payload.recovery_helpers,
+        payout_destination.as_ref(),
     )
     .await?;
 
@@ -1,28 +1,18 @@
-use axum::{extract::State, http::HeaderMap, Json};
-use serde::{Deserialize, Serialize};
+use axum::{extract::State, Json};
+use serde::Serialize;
 use std::sync::Arc;
-use tracing::{debug, error, info};
+use tracing::info;
 use validator::Validate;
 
 use crate::{
+    api::extractors::{VerifiedBreezWebhook, VerifiedPaystackWebhook},
     app_state::AppState,
-    domain::types::Sats,
+    bitcoin::breez_webhook::{self, BreezWebhookRequest},
     error::AppError,
-    services::fiat_service,
+    paystack::{self, webhook::PaystackEventEnvelope},
+    services::webhook_service::{self, IncomingWebhook, IngestOutcome},
 };
 
-#[derive(Debug, Deserialize, Validate)]
-pub struct BreezWebhookRequest {
-    // Breez SDK webhook payload details
-    // Example fields, adjust based on actual Breez webhook structure
-    pub payment_hash: String,
-    pub bolt11: Option<String>,
-    pub amount_msat: u64,
-    pub fee_msat: u64,
-    pub status: String, // e.g., "PAID", "FAILED"
-    // Other fields as necessary
-}
-
 #[derive(Debug, Serialize)]
 pub struct BreezWebhookResponse {
     pub success: bool,
@@ -31,70 +21,46 @@ pub struct BreezWebhookResponse {
 
 /// POST /webhook/breez
 /// Receives payment confirmation from Breez SDK.
+/// The signature, timestamp and event ID are checked before the payload is parsed.
 pub async fn breez_webhook_handler(
     State(app_state): State<Arc<AppState>>,
-    headers: HeaderMap,
-    Json(payload): Json<BreezWebhookRequest>,
+    webhook: VerifiedBreezWebhook<BreezWebhookRequest>,
 ) -> Result<Json<BreezWebhookResponse>, AppError> {
+    let payload = webhook.payload;
     info!("Received Breez webhook for payment hash: {}", payload.payment_hash);
 
-    // TODO: Implement replay protection and signature verification on Breez webhooks
-    // This is CRITICAL for security. The Breez SDK documentation should provide details.
-    if let Some(signature) = headers.get("X-Breez-Signature") {
-        debug!("Breez webhook signature: {:?}", signature);
-        // Verify signature here
-    } else {
-        error!("Breez webhook received without signature header.");
-        // Depending on policy, this might be an immediate rejection
-    }
-
     payload.validate()?;
 
-    // Delegate processing to a service layer
-    fiat_service::process_breez_payment(
-        app_state.db_pool.clone(),
-        payload.payment_hash,
-        Sats(payload.amount_msat as i64 / 1000), // convert msats to sats
-        Sats(payload.fee_msat as i64 / 1000),
-        payload.status,
+    // Stored and settled by the webhook worker, like Paystack deliveries
+    let outcome = webhook_service::ingest(
+        &app_state.db_pool,
+        IncomingWebhook {
+            provider: breez_webhook::PROVIDER,
+            event_id: payload.event_id(),
+            event_type: payload.status.clone(),
+            headers: &webhook.headers,
+            raw_body: &webhook.raw_body,
+        },
     )
-    .await?;
+    .await;
+    if let (Err(_), Some(event_id)) = (&outcome, &webhook.event_id) {
+        breez_webhook::release_event_id(&app_state.redis_client, event_id).await;
+    }
+
+    let message = match outcome? {
+        IngestOutcome::Accepted(_) => {
+            app_state.webhook_notify.notify_one();
+            "Webhook accepted for processing"
+        }
+        IngestOutcome::Duplicate(_) => "Duplicate webhook ignored",
+    };
 
     Ok(Json(BreezWebhookResponse {
         success: true,
-        message: "Breez webhook processed successfully".to_string(),
+        message: message.to_string(),
     }))
 }
 
-#[derive(Debug, Deserialize, Validate)]
-pub struct PaystackWebhookRequest {
-    // Paystack webhook payload details
-    // Example fields, adjust based on actual Paystack webhook structure
-    pub event: String, // e.g., "charge.success"
-    pub data: PaystackTransactionData,
-}
-
-#[derive(Debug, Deserialize, Validate)]
-pub struct PaystackTransactionData {
-    pub id: u64,
-    pub domain: String,
-    pub status: String, // e.g., "success"
-    pub reference: String,
-    pub amount: u64, // Amount in kobo
-    pub currency: String, // "NGN"
-    pub customer: PaystackCustomer,
-    // Add other relevant fields
-}
-
-#[derive(Debug, Deserialize)]
-pub struct PaystackCustomer {
-    pub id: u64,
-    pub email: String,
-    pub phone: Option<String>,
-    // Other customer details
-}
-
-
 #[derive(Debug, Serialize)]
 pub struct PaystackWebhookResponse {
     pub success: bool,
@@ -102,73 +68,65 @@ pub struct PaystackWebhookResponse {
 }
 
 /// POST /webhook/paystack
-/// Receives Naira transfer confirmation from Paystack → triggers BTC send.
+/// Receives Naira charge confirmations from Paystack → credits the deposit and triggers its BTC payout.
+/// The HMAC-SHA512 signature is verified against the raw body before the payload is parsed.
 pub async fn paystack_webhook_handler(
     State(app_state): State<Arc<AppState>>,
-    headers: HeaderMap,
-    Json(payload): Json<PaystackWebhookRequest>,
+    webhook: VerifiedPaystackWebhook<PaystackEventEnvelope>,
 ) -> Result<Json<PaystackWebhookResponse>, AppError> {
+    let payload = webhook.payload;
     info!(
         "Received Paystack webhook for event: {} and reference: {}",
         payload.event, payload.data.reference
     );
 
-    // TODO: Verify Paystack webhook signature using PAYSTACK_SECRET_KEY
-    // This is CRITICAL for security.
-    // Example: headers.get("x-paystack-signature")
-    if let Some(signature) = headers.get("x-paystack-signature") {
-        debug!("Paystack webhook signature: {:?}", signature);
-        // Implement signature verification using app_state.config.paystack_secret_key.expose_secret()
-    } else {
-        error!("Paystack webhook received without signature header.");
-        // Depending on policy, this might be an immediate rejection
-    }
-
-    // TODO: Implement idempotency key check (e.g., using X-Idempotency-Key header or a field in payload)
-    // This is CRITICAL to prevent double processing.
-
-    if payload.event == "charge.success" && payload.data.status == "success" {
-        // Delegate processing to a service layer
-        fiat_service::process_paystack_deposit(
-            app_state.db_pool.clone(),
-            app_state.redis_client.clone(),
-            payload.data.reference,
-            Kobo(payload.data.amount as i64),
-            payload.data.customer.phone, // Phone number might need canonicalization
-            payload.data.customer.email,
-        )
-        .await?;
+    // Persist the exact delivery first; processing happens in the webhook worker so
+    // Paystack gets a fast 200 and failures can be retried or replayed from the stored body.
+    let outcome = webhook_service::ingest(
+        &app_state.db_pool,
+        IncomingWebhook {
+            provider: paystack::webhook::PROVIDER,
+            event_id: payload.event_id(),
+            event_type: payload.event.clone(),
+            headers: &webhook.headers,
+            raw_body: &webhook.raw_body,
+        },
+    )
+    .await?;
 
-        Ok(Json(PaystackWebhookResponse {
-            success: true,
-            message: "Paystack webhook processed successfully".to_string(),
-        }))
-    } else {
-        info!("Paystack webhook not 'charge.success' or status not 'success'. Ignoring.");
-        Ok(Json(PaystackWebhookResponse {
-            success: true, // Acknowledge receipt even if not processed
-            message: "Webhook received, but not eligible for processing".to_string(),
-        }))
-    }
+    let message = match outcome {
+        IngestOutcome::Accepted(_) => {
+            app_state.webhook_notify.notify_one();
+            "Webhook accepted for processing"
+        }
+        IngestOutcome::Duplicate(_) => "Duplicate webhook ignored",
+    };
+
+    Ok(Json(PaystackWebhookResponse {
+        success: true, // Acknowledge receipt so Paystack stops retrying
+        message: message.to_string(),
+    }))
 }
 
 #[derive(Debug, Serialize)]
 pub struct RatesResponse {
     pub naira_to_btc: f64,
     pub last_updated_at: String,
+    pub sources: Vec<String>,
 }
 
 /// GET /rates
-/// Returns cached Naira -> BTC rate.
+/// Returns the aggregated Naira -> BTC rate and the sources that contributed to it.
+/// Responds with 503 when no source has a fresh rate.
 pub async fn get_rates_handler(
     State(app_state): State<Arc<AppState>>,
 ) -> Result<Json<RatesResponse>, AppError> {
     info!("Fetching current BTC/Naira rates.");
-    // Delegate to a service layer that fetches from multiple sources with fallback and caching
-    let (rate, last_updated) = fiat_service::get_cached_btc_naira_rate(app_state.redis_client.clone()).await?;
+    let rate = app_state.rate_oracle.current_rate().await?;
 
     Ok(Json(RatesResponse {
-        naira_to_btc: rate,
-        last_updated_at: last_updated.to_rfc3339(),
+        naira_to_btc: rate.naira_per_btc.to_f64(), // Display only; conversions use the exact rate
+        last_updated_at: rate.fetched_at.to_rfc3339(),
+        sources: rate.sources,
     }))
 }
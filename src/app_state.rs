@@ -1,8 +1,12 @@
 use std::sync::Arc;
 
 use redis::Client as RedisClient;
+use tokio::sync::Notify;
 
-use crate::{config::Config, database::AnyPool};
+use crate::{
+    bitcoin::lightning::LightningBackend, config::Config, crypto::key_provider::KeyProvider,
+    database::AnyPool, rates::oracle::RateOracle,
+};
 
 /// Shared application state for Axum handlers.
 #[derive(Clone)]
@@ -10,15 +14,30 @@ pub struct AppState {
     pub config: Config,
     pub db_pool: AnyPool,
     pub redis_client: RedisClient,
-    // Other services (e.g., Nostr client, Breez SDK client) will be added here
+    pub lightning: Arc<dyn LightningBackend>,
+    pub rate_oracle: Arc<RateOracle>,
+    pub key_provider: Arc<dyn KeyProvider>,
+    pub webhook_notify: Arc<Notify>, // Wakes the webhook worker when a new event is stored
+    // Other services (e.g., Nostr client) will be added here
 }
 
 impl AppState {
-    pub fn new(config: Config, db_pool: AnyPool, redis_client: RedisClient) -> Arc<Self> {
+    pub fn new(
+        config: Config,
+        db_pool: AnyPool,
+        redis_client: RedisClient,
+        lightning: Arc<dyn LightningBackend>,
+        rate_oracle: Arc<RateOracle>,
+        key_provider: Arc<dyn KeyProvider>,
+    ) -> Arc<Self> {
         Arc::new(Self {
             config,
             db_pool,
             redis_client,
+            lightning,
+            rate_oracle,
+            key_provider,
+            webhook_notify: Arc::new(Notify::new()),
         })
     }
 }
@@ -4,22 +4,17 @@ use secrecy::{ExposeSecret, SecretString};
 use tracing::{info, warn};
 use uuid::Uuid;
 
-use crate::{domain::types::Sats, error::AppError};
+use crate::{
+    bitcoin::lightning::{LightningBackend, NodeInfo, PaymentInfo},
+    domain::types::Sats,
+    error::AppError,
+};
 
-// Mock Breez SDK structures. Replace with actual `breez-sdk-core` types.
+// Mock Breez SDK implementation. Replace with actual `breez-sdk-core` calls.
 // The `breez-sdk` typically involves a FFI layer (like `uniffi`) which would
 // generate actual Rust bindings from a UniFFI definition file.
 // For this scaffold, we'll use simple Rust structs and mock functions.
 
-#[derive(Debug, Clone)]
-pub struct PaymentInfo {
-    pub payment_hash: String,
-    pub amount_sats: Sats,
-    pub fee_sats: Sats,
-    pub status: String,
-    pub description: Option<String>,
-}
-
 pub struct BreezService {
     // In a real implementation, this would hold the initialized Breez SDK client instance.
     api_key: String,
@@ -45,10 +40,13 @@ impl BreezService {
             _initialized: true,
         })
     }
+}
 
+#[async_trait]
+impl LightningBackend for BreezService {
     /// Sends a Lightning payment to an invoice or a Bitcoin address.
     /// In a real scenario, this would handle on-chain or off-chain payments.
-    pub async fn send_payment(&self, amount_sats: Sats, recipient: &str) -> Result<PaymentInfo, AppError> {
+    async fn send_payment(&self, amount_sats: Sats, recipient: &str) -> Result<PaymentInfo, AppError> {
         info!(
             "Breez (mock): Sending {} Sats to recipient: {}",
             amount_sats.0, recipient
@@ -70,7 +68,7 @@ impl BreezService {
     }
 
     /// Generates a new Lightning invoice for receiving payments.
-    pub async fn receive_payment(&self, amount_sats: Sats, description: &str) -> Result<String, AppError> {
+    async fn receive_payment(&self, amount_sats: Sats, description: &str) -> Result<String, AppError> {
         info!(
             "Breez (mock): Generating invoice for {} Sats, description: {}",
             amount_sats.0, description
@@ -82,7 +80,7 @@ impl BreezService {
     }
 
     /// Gets the current balance of the Breez wallet.
-    pub async fn get_balance(&self) -> Result<Sats, AppError> {
+    async fn get_balance(&self) -> Result<Sats, AppError> {
         info!("Breez (mock): Getting wallet balance.");
         // TODO: Replace with actual Breez SDK balance query.
         tokio::time::sleep(std::time::Duration::from_millis(500)).await; // Simulate balance query
@@ -91,11 +89,23 @@ impl BreezService {
     }
 
     /// Retrieves an on-chain Bitcoin address for receiving funds.
-    pub async fn get_onchain_address(&self) -> Result<String, AppError> {
+    async fn get_onchain_address(&self) -> Result<String, AppError> {
         info!("Breez (mock): Getting on-chain address.");
         // TODO: Replace with actual Breez SDK on-chain address generation.
         tokio::time::sleep(std::time::Duration::from_millis(500)).await; // Simulate address query
 
         Ok(format!("bc1qmockaddress{}", Uuid::new_v4().to_string().replace('-', "").chars().take(10).collect::<String>()))
     }
+
+    /// Returns node identity and sync state.
+    async fn node_info(&self) -> Result<NodeInfo, AppError> {
+        info!("Breez (mock): Getting node info.");
+        // TODO: Replace with actual Breez SDK `node_info()`.
+        Ok(NodeInfo {
+            node_id: "mock_breez_node".to_string(),
+            network: "bitcoin".to_string(),
+            block_height: 0,
+            balance_sats: self.get_balance().await?.0,
+        })
+    }
 }
@@ -0,0 +1,126 @@
+use async_trait::async_trait;
+use std::sync::Mutex;
+use tracing::info;
+
+use crate::{
+    bitcoin::lightning::{LightningBackend, NodeInfo, PaymentInfo},
+    domain::types::Sats,
+    error::AppError,
+};
+
+/// Deterministic Lightning backend for tests and local development.
+/// Payments settle instantly, hashes and addresses are derived from a counter, and no network is used.
+pub struct InMemoryLightning {
+    state: Mutex<InMemoryState>,
+}
+
+struct InMemoryState {
+    balance: Sats,
+    counter: u64,
+    payments: Vec<PaymentInfo>,
+}
+
+impl InMemoryLightning {
+    pub fn new(initial_balance: Sats) -> Self {
+        Self {
+            state: Mutex::new(InMemoryState {
+                balance: initial_balance,
+                counter: 0,
+                payments: Vec::new(),
+            }),
+        }
+    }
+
+    /// Returns every payment sent so far, oldest first.
+    pub fn sent_payments(&self) -> Vec<PaymentInfo> {
+        self.state.lock().unwrap().payments.clone()
+    }
+
+    fn next_id(state: &mut InMemoryState) -> u64 {
+        state.counter += 1;
+        state.counter
+    }
+}
+
+#[async_trait]
+impl LightningBackend for InMemoryLightning {
+    async fn send_payment(&self, amount_sats: Sats, recipient: &str) -> Result<PaymentInfo, AppError> {
+        let mut state = self.state.lock().unwrap();
+        if amount_sats.0 <= 0 {
+            return Err(AppError::BadRequest("Payment amount must be positive".to_string()));
+        }
+        if amount_sats > state.balance {
+            return Err(AppError::BadRequest(format!(
+                "Insufficient node balance: {} available, {} requested",
+                state.balance, amount_sats
+            )));
+        }
+
+        let id = Self::next_id(&mut state);
+        state.balance = Sats(state.balance.0 - amount_sats.0);
+        let payment = PaymentInfo {
+            payment_hash: format!("{:064x}", id),
+            amount_sats,
+            fee_sats: Sats::ZERO,
+            status: "complete".to_string(),
+            description: Some(format!("Payment to {}", recipient)),
+        };
+        state.payments.push(payment.clone());
+
+        info!("In-memory Lightning: sent {} to {}", amount_sats, recipient);
+        Ok(payment)
+    }
+
+    async fn receive_payment(&self, amount_sats: Sats, _description: &str) -> Result<String, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let id = Self::next_id(&mut state);
+        Ok(format!("lnbcrt{}n1memory{:08}", amount_sats.0, id))
+    }
+
+    async fn get_balance(&self) -> Result<Sats, AppError> {
+        Ok(self.state.lock().unwrap().balance)
+    }
+
+    async fn get_onchain_address(&self) -> Result<String, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let id = Self::next_id(&mut state);
+        Ok(format!("bcrt1qmemory{:08}", id))
+    }
+
+    async fn node_info(&self) -> Result<NodeInfo, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(NodeInfo {
+            node_id: "in-memory".to_string(),
+            network: "regtest".to_string(),
+            block_height: 0,
+            balance_sats: state.balance.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_send_payment_is_deterministic_and_debits_balance() {
+        let node = InMemoryLightning::new(Sats(10_000));
+
+        let first = node.send_payment(Sats(1_000), "lnbc1...").await.unwrap();
+        let second = node.send_payment(Sats(2_000), "lnbc1...").await.unwrap();
+
+        assert_eq!(first.payment_hash, format!("{:064x}", 1));
+        assert_eq!(second.payment_hash, format!("{:064x}", 2));
+        assert_eq!(node.get_balance().await.unwrap(), Sats(7_000));
+        assert_eq!(node.sent_payments().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_payment_rejects_overdraft() {
+        let node = InMemoryLightning::new(Sats(500));
+
+        assert!(node.send_payment(Sats(501), "lnbc1...").await.is_err());
+        assert_eq!(node.get_balance().await.unwrap(), Sats(500));
+        assert!(node.sent_payments().is_empty());
+    }
+}
@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+
+use crate::{domain::types::Sats, error::AppError};
+
+#[derive(Debug, Clone)]
+pub struct PaymentInfo {
+    pub payment_hash: String,
+    pub amount_sats: Sats,
+    pub fee_sats: Sats,
+    pub status: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct NodeInfo {
+    pub node_id: String,
+    pub network: String,
+    pub block_height: u32,
+    pub balance_sats: i64,
+}
+
+/// Operations the backend needs from a Lightning node.
+/// Services depend on this trait so the node implementation can be swapped without touching them.
+#[async_trait]
+pub trait LightningBackend: Send + Sync {
+    /// Sends a Lightning payment to an invoice or a Bitcoin address.
+    async fn send_payment(&self, amount_sats: Sats, recipient: &str) -> Result<PaymentInfo, AppError>;
+
+    /// Generates a new Lightning invoice for receiving payments.
+    async fn receive_payment(&self, amount_sats: Sats, description: &str) -> Result<String, AppError>;
+
+    /// Gets the current balance of the node.
+    async fn get_balance(&self) -> Result<Sats, AppError>;
+
+    /// Retrieves an on-chain Bitcoin address for receiving funds.
+    async fn get_onchain_address(&self) -> Result<String, AppError>;
+
+    /// Returns identity and sync information about the node.
+    async fn node_info(&self) -> Result<NodeInfo, AppError>;
+}
@@ -1 +1,4 @@
 pub mod breez;
+pub mod breez_webhook;
+pub mod in_memory;
+pub mod lightning;
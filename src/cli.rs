@@ -1,49 +1,204 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    crypto::key_provider,
+    database,
+    domain::{admin::AdminRole, audit::AuditActor},
+    services::{
+        admin_service, admin_user_service, audit_service, nsec_service, session_service, totp_service,
+        webhook_service,
+    },
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
-    pub command: Commands,
+    pub command: Option<Commands>,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Runs database migrations
-    Migrate,
+    Migrate {
+        /// List pending migrations without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Initializes the default admin user if one doesn't exist
     InitAdmin,
-    /// Generates a new Nostr keypair and prints it
+    /// Generates a new Nostr keypair and prints it to stdout
     GenerateNostrKeys,
     /// Starts the Sabi Wallet backend API server (default if no subcommand)
     Serve,
+    /// Queues a stored webhook for re-processing from its original raw payload
+    ReplayWebhook {
+        /// ID of the row in `webhook_events`
+        id: Uuid,
+    },
+    /// Re-encrypts every stored nsec and admin TOTP secret under the current master key (NSEC_MASTER_KEY_VERSION),
+    /// including legacy plaintext nsec rows. Safe to re-run; keep old key versions configured until it succeeds.
+    RotateMasterKey,
+    /// Verifies the admin audit log hash chain; exits with an error if any row was altered or removed
+    VerifyAuditLog,
+    /// Manages admin accounts. Changes are recorded in the audit log with the actor `cli`
+    Admin {
+        #[command(subcommand)]
+        command: AdminCommands,
+    },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum AdminCommands {
+    /// Lists all admin accounts
+    List,
+    /// Creates an admin and prints a temporary password, which must be changed on first login
+    Create {
+        username: String,
+        /// viewer, operator or superadmin
+        #[arg(long, default_value = "viewer")]
+        role: String,
+    },
+    /// Deactivates an admin; their tokens stop working immediately
+    Deactivate { username: String },
+    /// Prints a new temporary password for an admin, which must be changed at the next login
+    ResetPassword { username: String },
+    /// Assigns a role (viewer, operator or superadmin) to an admin
+    SetRole { username: String, role: String },
+    /// Signs an admin out of every session, e.g. after a lost laptop
+    RevokeSessions { username: String },
+}
+
+/// Runs every subcommand except `serve`, which `main` handles itself.
 pub async fn run_cli_command(command: Commands) -> Result<()> {
     match command {
-        Commands::Migrate => {
-            info!("Running database migrations...");
-            // TODO: Call actual migration logic
-            info!("Migrations complete (placeholder).");
+        Commands::Migrate { dry_run } => {
+            let config = Config::load()?;
+            let db_pool = database::init_db_pool(&config).await?;
+            if dry_run {
+                let pending = database::pending_migrations(&db_pool).await?;
+                if pending.is_empty() {
+                    info!("No pending migrations.");
+                }
+                for migration in &pending {
+                    println!("{} {}", migration.version, migration.description);
+                }
+            } else {
+                info!("Running database migrations...");
+                database::run_migrations(&db_pool).await?;
+            }
         }
         Commands::InitAdmin => {
-            info!("Initializing default admin user...");
-            // TODO: Call logic to ensure default admin user exists
-            info!("Default admin user initialized (placeholder).");
+            let config = Config::load()?;
+            let db_pool = database::init_db_pool(&config).await?;
+            if admin_service::ensure_default_admin(&db_pool, &config).await? {
+                info!("Default admin user created. Its password must be changed at the first login.");
+            } else {
+                info!("Default admin user already exists; nothing to do.");
+            }
         }
         Commands::GenerateNostrKeys => {
-            info!("Generating a new Nostr keypair...");
-            // TODO: Implement actual key generation and print securely
+            // Printed, not logged, so the secret key never reaches log aggregation
             let keys = nostr_sdk::nostr::Keys::generate();
-            info!("New Nostr Public Key (npub): {}", keys.public_key().to_bech32()?);
-            info!("New Nostr Secret Key (nsec): {}", keys.secret_key()?.to_secret_key().to_bech32()?);
+            println!("npub: {}", keys.public_key().to_bech32()?);
+            println!("nsec: {}", keys.secret_key()?.to_secret_key().to_bech32()?);
         }
         Commands::Serve => {
-            info!("Starting API server...");
-            // This case is handled by `main` if no subcommand is given.
-            // This `run_cli_command` will probably not be called for `Serve`.
+            return Err(anyhow::anyhow!("`serve` is handled by main"));
+        }
+        Commands::ReplayWebhook { id } => {
+            let config = Config::load()?;
+            let db_pool = database::init_db_pool(&config).await?;
+            webhook_service::replay(&db_pool, id).await?;
+            // The running server's webhook worker picks the event up on its next poll.
+            info!("Webhook {} queued for replay.", id);
+        }
+        Commands::RotateMasterKey => {
+            let config = Config::load()?;
+            let db_pool = database::init_db_pool(&config).await?;
+            let provider = key_provider::from_config(&config)?;
+            info!(
+                "Re-encrypting stored nsecs and TOTP secrets under master key version {}...",
+                provider.current_version()
+            );
+            let summary = nsec_service::reencrypt_all(&db_pool, provider.as_ref()).await?;
+            let totp_rewrapped = totp_service::reencrypt_all(&db_pool, provider.as_ref()).await?;
+            info!(
+                "Master key rotation complete: {} nsecs re-wrapped, {} legacy rows encrypted, {} TOTP secrets re-wrapped.",
+                summary.rewrapped, summary.encrypted_legacy, totp_rewrapped
+            );
+        }
+        Commands::VerifyAuditLog => {
+            let config = Config::load()?;
+            let db_pool = database::init_db_pool(&config).await?;
+            let verification = audit_service::verify_chain(&db_pool).await?;
+            if !verification.valid {
+                return Err(anyhow::anyhow!(
+                    "Audit log chain broken at seq {}: {}",
+                    verification.broken_at_seq.unwrap_or_default(),
+                    verification.reason.unwrap_or_default()
+                ));
+            }
+            info!("Audit log intact: {} entries verified.", verification.entries_checked);
+        }
+        Commands::Admin { command } => {
+            let config = Config::load()?;
+            let db_pool = database::init_db_pool(&config).await?;
+            run_admin_command(&db_pool, command).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn run_admin_command(db_pool: &database::AnyPool, command: AdminCommands) -> Result<()> {
+    let actor = AuditActor::cli();
+    match command {
+        AdminCommands::List => {
+            for admin in admin_user_service::list(db_pool).await? {
+                println!(
+                    "{}  {:<24} {:<10} {:<8} totp={} must_change_password={}",
+                    admin.id,
+                    admin.username,
+                    admin.role.as_deref().unwrap_or("-"),
+                    if admin.is_active { "active" } else { "inactive" },
+                    admin.totp_enabled,
+                    admin.must_change_password
+                );
+            }
+        }
+        AdminCommands::Create { username, role } => {
+            let role = role.parse::<AdminRole>()?;
+            // Printed, not logged, like the keys from generate-nostr-keys
+            let password = admin_user_service::generate_temporary_password();
+            let admin = admin_user_service::create(db_pool, &actor, &username, &password, role).await?;
+            println!("Created admin '{}' ({}) with role {}.", admin.username, admin.id, role);
+            println!("Temporary password: {}", password);
+        }
+        AdminCommands::Deactivate { username } => {
+            let admin = admin_user_service::find_by_username(db_pool, &username).await?;
+            admin_user_service::deactivate(db_pool, &actor, admin.id).await?;
+            info!("Admin '{}' deactivated.", username);
+        }
+        AdminCommands::ResetPassword { username } => {
+            let admin = admin_user_service::find_by_username(db_pool, &username).await?;
+            let password = admin_user_service::generate_temporary_password();
+            admin_user_service::reset_password(db_pool, &actor, admin.id, &password).await?;
+            println!("Temporary password for '{}': {}", username, password);
+        }
+        AdminCommands::SetRole { username, role } => {
+            let role = role.parse::<AdminRole>()?;
+            let admin = admin_user_service::find_by_username(db_pool, &username).await?;
+            admin_user_service::set_role(db_pool, &actor, admin.id, role).await?;
+            info!("Admin '{}' now has role {}.", username, role);
+        }
+        AdminCommands::RevokeSessions { username } => {
+            let admin = admin_user_service::find_by_username(db_pool, &username).await?;
+            let revoked = session_service::revoke_all_sessions(db_pool, &actor, admin.id).await?;
+            info!("Revoked {} sessions of admin '{}'.", revoked, username);
         }
     }
     Ok(())
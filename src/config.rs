@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use secrecy::{ExposeSecret, Secret, SecretString};
 use serde::{Deserialize, Serialize};
 use std::env;
@@ -24,20 +24,52 @@ pub struct Config {
     pub sabi_nostr_nsec: SecretString,
     pub nostr_relays: Vec<String>,
 
+    // Master keys for envelope encryption of stored nsecs
+    pub nsec_master_keys: Option<SecretString>,
+    pub nsec_master_key_file: Option<String>,
+    pub nsec_master_key_version: Option<u32>, // Defaults to the highest configured version
+
     // Breez SDK
     pub breez_api_key: SecretString,
     pub breez_mnemonic: SecretString,
     pub breez_environment: String,
+    pub lightning_backend: String, // 'breez' | 'memory'
+    pub breez_webhook_secret: Option<SecretString>, // Shared secret for X-Breez-Signature
+    pub breez_webhook_tolerance_seconds: i64, // Max clock skew of X-Breez-Timestamp; event IDs are remembered twice as long
+    pub breez_webhook_unsigned_policy: UnsignedWebhookPolicy,
 
     // Paystack
     pub paystack_secret_key: SecretString,
 
-    // Africa's Talking (for USSD)
+    // Lightning payouts of fiat deposits
+    pub payout_max_attempts: i32, // Failed sends before the deposit is put on admin_hold
+
+    // BTC/NGN rate oracle
+    pub rate_sources: Vec<String>,
+    pub rate_refresh_seconds: i64,
+    pub rate_max_age_seconds: i64, // Refuse to quote once the newest rate is older than this
+
+    // Locked-price buy and sell quotes
+    pub quote_ttl_seconds: i64,
+    pub quote_spread_bps: i64, // Taken off the market rate on both sides
+    pub quote_slippage_bps: i64, // How much worse than an unusable quote a re-quote may be
+
+    // Africa's Talking (for USSD and SMS)
     pub at_api_key: SecretString,
-    pub at_username: String,
+    pub at_username: String, // 'sandbox' selects the Africa's Talking sandbox
+    pub at_sms_sender_id: Option<String>, // Registered alphanumeric sender; the shared short code if unset
+    pub ussd_service_code: String, // Told to new users in their welcome SMS, e.g. *333*777#
 
     // Admin
     pub default_admin_password: SecretString,
+    pub admin_access_token_ttl_seconds: i64,
+    pub admin_refresh_token_ttl_seconds: i64, // Counted from login; refreshing does not extend it
+    pub release_approval_threshold_sats: i64, // Manual releases above this need a second admin's approval
+    pub release_request_ttl_seconds: i64,
+    pub admin_login_max_failures: i64, // Failed logins per username before a lockout
+    pub admin_login_max_failures_per_ip: i64, // Failed logins per client IP, across usernames, before a lockout
+    pub admin_login_lockout_seconds: i64,
+    pub trust_forwarded_for: bool, // Take the client IP from X-Forwarded-For; only behind a proxy that sets it
 
     // Lettre (Email for Admin Alerts)
     pub smtp_username: Option<SecretString>,
@@ -48,6 +80,15 @@ pub struct Config {
     pub admin_email_recipient: String,
 }
 
+/// What to do with a Breez webhook that carries no signature, or one that cannot be checked
+/// because no secret is configured. Bad signatures are always rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsignedWebhookPolicy {
+    Reject,
+    Warn, // Accept and log; for local development against an unsigned sender
+}
+
 impl Config {
     pub fn load() -> Result<Self> {
         // Load from environment variables. 'config' crate can do more complex loading.
@@ -79,6 +120,14 @@ impl Config {
             .map(|s| s.to_string())
             .collect();
 
+        let nsec_master_keys = env::var("NSEC_MASTER_KEYS").ok().map(SecretString::new);
+        let nsec_master_key_file = env::var("NSEC_MASTER_KEY_FILE").ok();
+        let nsec_master_key_version = env::var("NSEC_MASTER_KEY_VERSION")
+            .ok()
+            .map(|s| s.parse::<u32>())
+            .transpose()
+            .context("NSEC_MASTER_KEY_VERSION must be a valid u32 if set")?;
+
         let breez_api_key = SecretString::new(
             env::var("BREEZ_API_KEY").context("BREEZ_API_KEY must be set")?,
         );
@@ -86,19 +135,119 @@ impl Config {
             env::var("BREEZ_MNEMONIC").context("BREEZ_MNEMONIC must be set")?,
         );
         let breez_environment = env::var("BREEZ_ENVIRONMENT").context("BREEZ_ENVIRONMENT must be set")?;
+        let lightning_backend = env::var("LIGHTNING_BACKEND").unwrap_or_else(|_| "breez".into());
+        let breez_webhook_secret = env::var("BREEZ_WEBHOOK_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(SecretString::new);
+        let breez_webhook_tolerance_seconds = env::var("BREEZ_WEBHOOK_TOLERANCE_SECONDS")
+            .unwrap_or_else(|_| "300".into())
+            .parse::<i64>()
+            .context("BREEZ_WEBHOOK_TOLERANCE_SECONDS must be a valid integer")?;
+        let breez_webhook_unsigned_policy = match env::var("BREEZ_WEBHOOK_UNSIGNED_POLICY").as_deref() {
+            Ok("reject") => UnsignedWebhookPolicy::Reject,
+            Ok("warn") => UnsignedWebhookPolicy::Warn,
+            Ok(other) => bail!("BREEZ_WEBHOOK_UNSIGNED_POLICY must be 'reject' or 'warn', got '{}'", other),
+            // APP_ENV itself defaults to dev, so only an explicit APP_ENV=dev relaxes the default
+            Err(_) if env::var("APP_ENV").as_deref() == Ok("dev") => UnsignedWebhookPolicy::Warn,
+            Err(_) => UnsignedWebhookPolicy::Reject,
+        };
+        if app_env == "prod" && breez_webhook_unsigned_policy == UnsignedWebhookPolicy::Warn {
+            bail!("BREEZ_WEBHOOK_UNSIGNED_POLICY cannot be 'warn' when APP_ENV is prod");
+        }
+        if breez_webhook_unsigned_policy == UnsignedWebhookPolicy::Reject && breez_webhook_secret.is_none() {
+            bail!("BREEZ_WEBHOOK_SECRET must be set unless BREEZ_WEBHOOK_UNSIGNED_POLICY is 'warn'");
+        }
 
         let paystack_secret_key = SecretString::new(
             env::var("PAYSTACK_SECRET_KEY").context("PAYSTACK_SECRET_KEY must be set")?,
         );
 
+        let payout_max_attempts = env::var("PAYOUT_MAX_ATTEMPTS")
+            .unwrap_or_else(|_| "5".into())
+            .parse::<i32>()
+            .context("PAYOUT_MAX_ATTEMPTS must be a valid integer")?;
+
+        let rate_sources: Vec<String> = env::var("RATE_SOURCES")
+            .unwrap_or_else(|_| "binance,luno,quidax,coingecko".into())
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        // The fixed source quotes a made-up rate, so only an explicit APP_ENV=dev may use it
+        if env::var("APP_ENV").as_deref() != Ok("dev") && rate_sources.iter().any(|source| source == "fixed") {
+            bail!("RATE_SOURCES cannot include 'fixed' unless APP_ENV is dev");
+        }
+        let rate_refresh_seconds = env::var("RATE_REFRESH_SECONDS")
+            .unwrap_or_else(|_| "60".into())
+            .parse::<i64>()
+            .context("RATE_REFRESH_SECONDS must be a valid integer")?;
+        let rate_max_age_seconds = env::var("RATE_MAX_AGE_SECONDS")
+            .unwrap_or_else(|_| "300".into())
+            .parse::<i64>()
+            .context("RATE_MAX_AGE_SECONDS must be a valid integer")?;
+
+        let quote_ttl_seconds = env::var("QUOTE_TTL_SECONDS")
+            .unwrap_or_else(|_| "300".into())
+            .parse::<i64>()
+            .context("QUOTE_TTL_SECONDS must be a valid integer")?;
+        let quote_spread_bps = env::var("QUOTE_SPREAD_BPS")
+            .unwrap_or_else(|_| "0".into())
+            .parse::<i64>()
+            .context("QUOTE_SPREAD_BPS must be a valid integer")?;
+        if !(0..10_000).contains(&quote_spread_bps) {
+            bail!("QUOTE_SPREAD_BPS must be between 0 and 9999, got {}", quote_spread_bps);
+        }
+        let quote_slippage_bps = env::var("QUOTE_SLIPPAGE_BPS")
+            .unwrap_or_else(|_| "100".into())
+            .parse::<i64>()
+            .context("QUOTE_SLIPPAGE_BPS must be a valid integer")?;
+        if !(0..=10_000).contains(&quote_slippage_bps) {
+            bail!("QUOTE_SLIPPAGE_BPS must be between 0 and 10000, got {}", quote_slippage_bps);
+        }
+
         let at_api_key = SecretString::new(
             env::var("AT_API_KEY").context("AT_API_KEY must be set")?,
         );
         let at_username = env::var("AT_USERNAME").context("AT_USERNAME must be set")?;
+        let at_sms_sender_id = env::var("AT_SMS_SENDER_ID").ok().filter(|s| !s.is_empty());
+        let ussd_service_code = env::var("USSD_SERVICE_CODE").unwrap_or_else(|_| "*333*777#".into());
 
         let default_admin_password = SecretString::new(
             env::var("DEFAULT_ADMIN_PASSWORD").context("DEFAULT_ADMIN_PASSWORD must be set")?,
         );
+        let admin_access_token_ttl_seconds = env::var("ADMIN_ACCESS_TOKEN_TTL_SECONDS")
+            .unwrap_or_else(|_| "900".into())
+            .parse::<i64>()
+            .context("ADMIN_ACCESS_TOKEN_TTL_SECONDS must be a valid integer")?;
+        let admin_refresh_token_ttl_seconds = env::var("ADMIN_REFRESH_TOKEN_TTL_SECONDS")
+            .unwrap_or_else(|_| "604800".into())
+            .parse::<i64>()
+            .context("ADMIN_REFRESH_TOKEN_TTL_SECONDS must be a valid integer")?;
+        let release_approval_threshold_sats = env::var("RELEASE_APPROVAL_THRESHOLD_SATS")
+            .unwrap_or_else(|_| "100000".into())
+            .parse::<i64>()
+            .context("RELEASE_APPROVAL_THRESHOLD_SATS must be a valid integer")?;
+        let release_request_ttl_seconds = env::var("RELEASE_REQUEST_TTL_SECONDS")
+            .unwrap_or_else(|_| "86400".into())
+            .parse::<i64>()
+            .context("RELEASE_REQUEST_TTL_SECONDS must be a valid integer")?;
+        let admin_login_max_failures = env::var("ADMIN_LOGIN_MAX_FAILURES")
+            .unwrap_or_else(|_| "5".into())
+            .parse::<i64>()
+            .context("ADMIN_LOGIN_MAX_FAILURES must be a valid integer")?;
+        let admin_login_max_failures_per_ip = env::var("ADMIN_LOGIN_MAX_FAILURES_PER_IP")
+            .unwrap_or_else(|_| "20".into())
+            .parse::<i64>()
+            .context("ADMIN_LOGIN_MAX_FAILURES_PER_IP must be a valid integer")?;
+        let admin_login_lockout_seconds = env::var("ADMIN_LOGIN_LOCKOUT_SECONDS")
+            .unwrap_or_else(|_| "900".into())
+            .parse::<i64>()
+            .context("ADMIN_LOGIN_LOCKOUT_SECONDS must be a valid integer")?;
+        let trust_forwarded_for = env::var("TRUST_FORWARDED_FOR")
+            .unwrap_or_else(|_| "false".into())
+            .parse::<bool>()
+            .context("TRUST_FORWARDED_FOR must be true or false")?;
 
         let smtp_username = env::var("SMTP_USERNAME").ok().map(SecretString::new);
         let smtp_password = env::var("SMTP_PASSWORD").ok().map(SecretString::new);
@@ -124,13 +273,37 @@ impl Config {
             sentry_dsn,
             sabi_nostr_nsec,
             nostr_relays,
+            nsec_master_keys,
+            nsec_master_key_file,
+            nsec_master_key_version,
             breez_api_key,
             breez_mnemonic,
             breez_environment,
+            lightning_backend,
+            breez_webhook_secret,
+            breez_webhook_tolerance_seconds,
+            breez_webhook_unsigned_policy,
             paystack_secret_key,
+            payout_max_attempts,
+            rate_sources,
+            rate_refresh_seconds,
+            rate_max_age_seconds,
+            quote_ttl_seconds,
+            quote_spread_bps,
+            quote_slippage_bps,
             at_api_key,
             at_username,
+            at_sms_sender_id,
+            ussd_service_code,
             default_admin_password,
+            admin_access_token_ttl_seconds,
+            admin_refresh_token_ttl_seconds,
+            release_approval_threshold_sats,
+            release_request_ttl_seconds,
+            admin_login_max_failures,
+            admin_login_max_failures_per_ip,
+            admin_login_lockout_seconds,
+            trust_forwarded_for,
             smtp_username,
             smtp_password,
             smtp_host,
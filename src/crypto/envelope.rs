@@ -0,0 +1,108 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use secrecy::ExposeSecret;
+
+use crate::{
+    crypto::key_provider::{generate_key, KeyProvider},
+    error::AppError,
+};
+
+const NONCE_LEN: usize = 12;
+
+/// A value encrypted under its own data key, with the data key wrapped by a versioned master key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub key_version: u32,
+    pub wrapped_key: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Encrypts `plaintext` with a fresh data key. `aad` is authenticated but not stored,
+/// so the envelope only opens for the same context (e.g. the owning wallet ID).
+pub async fn seal(provider: &dyn KeyProvider, plaintext: &[u8], aad: &[u8]) -> Result<Envelope, AppError> {
+    let data_key = generate_key();
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(data_key.expose_secret()));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plaintext, aad })
+        .map_err(|_| AppError::Internal("Failed to encrypt record".to_string()))?;
+
+    let (key_version, wrapped_key) = provider.wrap_key(&data_key).await?;
+    Ok(Envelope {
+        key_version,
+        wrapped_key,
+        nonce: nonce.to_vec(),
+        ciphertext,
+    })
+}
+
+/// Decrypts an envelope produced by `seal` with the same `aad`.
+pub async fn open(provider: &dyn KeyProvider, envelope: &Envelope, aad: &[u8]) -> Result<Vec<u8>, AppError> {
+    if envelope.nonce.len() != NONCE_LEN {
+        return Err(AppError::Internal("Envelope nonce has the wrong length".to_string()));
+    }
+
+    let data_key = provider
+        .unwrap_key(envelope.key_version, &envelope.wrapped_key)
+        .await?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(data_key.expose_secret()));
+    cipher
+        .decrypt(
+            Nonce::from_slice(&envelope.nonce),
+            Payload {
+                msg: &envelope.ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| AppError::Internal("Failed to decrypt record".to_string()))
+}
+
+/// Re-wraps the envelope's data key under the provider's current master key.
+/// The ciphertext itself is unchanged, so rotation never handles the plaintext.
+pub async fn rewrap(provider: &dyn KeyProvider, envelope: &Envelope) -> Result<Envelope, AppError> {
+    let data_key = provider
+        .unwrap_key(envelope.key_version, &envelope.wrapped_key)
+        .await?;
+    let (key_version, wrapped_key) = provider.wrap_key(&data_key).await?;
+    Ok(Envelope {
+        key_version,
+        wrapped_key,
+        ..envelope.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::key_provider::test_provider;
+
+    #[tokio::test]
+    async fn test_seal_open_round_trip_is_bound_to_aad() {
+        let provider = test_provider(&[1], 1);
+        let envelope = seal(&provider, b"nsec1secret", b"wallet-a").await.unwrap();
+
+        assert_eq!(envelope.key_version, 1);
+        assert_ne!(envelope.ciphertext, b"nsec1secret");
+        assert_eq!(open(&provider, &envelope, b"wallet-a").await.unwrap(), b"nsec1secret");
+        assert!(open(&provider, &envelope, b"wallet-b").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rewrap_moves_to_current_master_key() {
+        let old = test_provider(&[1], 1);
+        let envelope = seal(&old, b"nsec1secret", b"wallet-a").await.unwrap();
+
+        let rotated = test_provider(&[1, 2], 2);
+        let rewrapped = rewrap(&rotated, &envelope).await.unwrap();
+        assert_eq!(rewrapped.key_version, 2);
+        assert_eq!(rewrapped.ciphertext, envelope.ciphertext);
+
+        // Version 1 can be retired once everything is rewrapped
+        let retired = test_provider(&[2], 2);
+        assert_eq!(open(&retired, &rewrapped, b"wallet-a").await.unwrap(), b"nsec1secret");
+        assert!(open(&retired, &envelope, b"wallet-a").await.is_err());
+    }
+}
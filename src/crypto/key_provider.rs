@@ -0,0 +1,186 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, Secret};
+use std::{collections::BTreeMap, fs, sync::Arc};
+
+use crate::{config::Config, error::AppError};
+
+pub const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// A 256-bit key that is zeroed when dropped.
+pub type KeyBytes = Secret<[u8; KEY_LEN]>;
+
+/// Wraps and unwraps per-record data keys with a versioned master key.
+/// The local implementation holds master keys in memory; a KMS-backed one can replace it
+/// without touching callers.
+#[async_trait]
+pub trait KeyProvider: Send + Sync {
+    /// Version of the master key new data keys are wrapped with.
+    fn current_version(&self) -> u32;
+
+    /// Wraps `data_key` with the current master key. Returns the version used and the wrapped bytes.
+    async fn wrap_key(&self, data_key: &KeyBytes) -> Result<(u32, Vec<u8>), AppError>;
+
+    /// Unwraps a data key that was wrapped with master key `version`.
+    async fn unwrap_key(&self, version: u32, wrapped: &[u8]) -> Result<KeyBytes, AppError>;
+}
+
+/// Master keys loaded from the environment or a file, e.g. `1:<64 hex chars>,2:<64 hex chars>`.
+/// Old versions are kept so existing rows stay readable until they are rotated.
+pub struct LocalKeyProvider {
+    keys: BTreeMap<u32, KeyBytes>,
+    current: u32,
+}
+
+impl LocalKeyProvider {
+    /// `current` defaults to the highest version present.
+    pub fn new(keys: BTreeMap<u32, KeyBytes>, current: Option<u32>) -> Result<Self> {
+        let current = match current {
+            Some(version) => version,
+            None => *keys.keys().next_back().ok_or_else(|| anyhow!("No master keys configured"))?,
+        };
+        if !keys.contains_key(&current) {
+            return Err(anyhow!("Master key version {} is not configured", current));
+        }
+        Ok(Self { keys, current })
+    }
+
+    /// Parses `version:hex` pairs separated by commas or newlines. Blank lines and `#` comments are ignored.
+    pub fn parse_keys(spec: &str) -> Result<BTreeMap<u32, KeyBytes>> {
+        let mut keys = BTreeMap::new();
+        for entry in spec
+            .split(|c| c == ',' || c == '\n')
+            .map(str::trim)
+            .filter(|e| !e.is_empty() && !e.starts_with('#'))
+        {
+            let (version, key_hex) = entry
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Master key entry must be 'version:hex'"))?;
+            let version: u32 = version.trim().parse().context("Master key version must be a number")?;
+
+            let mut key = [0u8; KEY_LEN];
+            hex::decode_to_slice(key_hex.trim(), &mut key)
+                .with_context(|| format!("Master key {} must be {} hex-encoded bytes", version, KEY_LEN))?;
+
+            if keys.insert(version, Secret::new(key)).is_some() {
+                return Err(anyhow!("Master key version {} is configured twice", version));
+            }
+        }
+        Ok(keys)
+    }
+
+    fn key(&self, version: u32) -> Result<&KeyBytes, AppError> {
+        self.keys
+            .get(&version)
+            .ok_or_else(|| AppError::Internal(format!("Master key version {} is not available", version)))
+    }
+}
+
+#[async_trait]
+impl KeyProvider for LocalKeyProvider {
+    fn current_version(&self) -> u32 {
+        self.current
+    }
+
+    async fn wrap_key(&self, data_key: &KeyBytes) -> Result<(u32, Vec<u8>), AppError> {
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(self.key(self.current)?.expose_secret()));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, data_key.expose_secret().as_slice())
+            .map_err(|_| AppError::Internal("Failed to wrap data key".to_string()))?;
+
+        let mut wrapped = nonce.to_vec();
+        wrapped.extend_from_slice(&ciphertext);
+        Ok((self.current, wrapped))
+    }
+
+    async fn unwrap_key(&self, version: u32, wrapped: &[u8]) -> Result<KeyBytes, AppError> {
+        if wrapped.len() <= NONCE_LEN {
+            return Err(AppError::Internal("Wrapped data key is truncated".to_string()));
+        }
+        let (nonce, ciphertext) = wrapped.split_at(NONCE_LEN);
+
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(self.key(version)?.expose_secret()));
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| AppError::Internal(format!("Failed to unwrap data key with master key {}", version)))?;
+
+        let key: [u8; KEY_LEN] = plaintext
+            .as_slice()
+            .try_into()
+            .map_err(|_| AppError::Internal("Unwrapped data key has the wrong length".to_string()))?;
+        Ok(Secret::new(key))
+    }
+}
+
+/// Builds the key provider from `NSEC_MASTER_KEYS` or `NSEC_MASTER_KEY_FILE`.
+pub fn from_config(config: &Config) -> Result<Arc<dyn KeyProvider>> {
+    let spec = match (&config.nsec_master_keys, &config.nsec_master_key_file) {
+        (Some(keys), _) => keys.expose_secret().clone(),
+        (None, Some(path)) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read master key file {}", path))?,
+        (None, None) => return Err(anyhow!("NSEC_MASTER_KEYS or NSEC_MASTER_KEY_FILE must be set")),
+    };
+
+    let keys = LocalKeyProvider::parse_keys(&spec)?;
+    Ok(Arc::new(LocalKeyProvider::new(keys, config.nsec_master_key_version)?))
+}
+
+/// Generates a random 256-bit key.
+pub fn generate_key() -> KeyBytes {
+    Secret::new(Aes256Gcm::generate_key(OsRng).into())
+}
+
+/// A provider holding a made-up key for each of `versions`, sealing with `current`.
+#[cfg(test)]
+pub fn test_provider(versions: &[u32], current: u32) -> LocalKeyProvider {
+    let spec = versions
+        .iter()
+        .map(|v| format!("{}:{}", v, format!("{:02x}", v).repeat(KEY_LEN)))
+        .collect::<Vec<_>>()
+        .join(",");
+    LocalKeyProvider::new(LocalKeyProvider::parse_keys(&spec).unwrap(), Some(current)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(spec: &str, current: Option<u32>) -> LocalKeyProvider {
+        LocalKeyProvider::new(LocalKeyProvider::parse_keys(spec).unwrap(), current).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wrap_unwrap_and_old_versions_stay_readable() {
+        let v1 = format!("1:{}", "11".repeat(KEY_LEN));
+        let v2 = format!("2:{}", "22".repeat(KEY_LEN));
+        let old = provider(&v1, None);
+        let rotated = provider(&format!("{},{}", v1, v2), None);
+        assert_eq!(rotated.current_version(), 2);
+
+        let data_key = generate_key();
+        let (version, wrapped) = old.wrap_key(&data_key).await.unwrap();
+        assert_eq!(version, 1);
+
+        let unwrapped = rotated.unwrap_key(version, &wrapped).await.unwrap();
+        assert_eq!(unwrapped.expose_secret(), data_key.expose_secret());
+        assert!(rotated.unwrap_key(2, &wrapped).await.is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_specs() {
+        assert!(LocalKeyProvider::parse_keys("1:abcd").is_err());
+        assert!(LocalKeyProvider::parse_keys(&format!("x:{}", "11".repeat(KEY_LEN))).is_err());
+        assert!(LocalKeyProvider::new(BTreeMap::new(), None).is_err());
+        assert!(LocalKeyProvider::new(
+            LocalKeyProvider::parse_keys(&format!("1:{}", "11".repeat(KEY_LEN))).unwrap(),
+            Some(2)
+        )
+        .is_err());
+    }
+}
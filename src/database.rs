@@ -1,11 +1,12 @@
 use anyhow::{Context, Result};
 use secrecy::ExposeSecret;
 use sqlx::{
+    migrate::{Migrate, Migrator},
     postgres::{PgPoolOptions},
     sqlite::{SqlitePoolOptions},
     Pool, Any,
 };
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 use tracing::info;
 
 use crate::config::Config;
@@ -38,6 +39,60 @@ pub async fn init_db_pool(config: &Config) -> Result<AnyPool> {
     }
 }
 
+/// Migrations embedded from `./migrations` at compile time.
+pub fn migrator() -> Migrator {
+    sqlx::migrate!("./migrations")
+}
+
+pub async fn run_migrations(db_pool: &AnyPool) -> Result<()> {
+    migrator()
+        .run(db_pool)
+        .await
+        .context("Failed to apply database migrations")?;
+    info!("Database migrations applied successfully.");
+    Ok(())
+}
+
+/// A migration that `run_migrations` would apply.
+#[derive(Debug)]
+pub struct PendingMigration {
+    pub version: i64,
+    pub description: String,
+}
+
+/// Lists migrations not yet applied, in the order they would run. Fails if an applied
+/// migration has been edited since, as `run_migrations` would.
+pub async fn pending_migrations(db_pool: &AnyPool) -> Result<Vec<PendingMigration>> {
+    let mut conn = db_pool.acquire().await?;
+    // Only creates the empty bookkeeping table if missing, like `sqlx migrate info`
+    conn.ensure_migrations_table().await?;
+    let applied: HashMap<i64, _> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|m| (m.version, m.checksum))
+        .collect();
+
+    let mut pending = Vec::new();
+    for migration in migrator().iter().filter(|m| m.migration_type.is_up_migration()) {
+        match applied.get(&migration.version) {
+            Some(checksum) if *checksum != migration.checksum => {
+                return Err(anyhow::anyhow!(
+                    "Migration {} ({}) was modified after it was applied",
+                    migration.version,
+                    migration.description
+                ));
+            }
+            Some(_) => {}
+            None => pending.push(PendingMigration {
+                version: migration.version,
+                description: migration.description.to_string(),
+            }),
+        }
+    }
+    Ok(pending)
+}
+
 pub fn init_redis_client(config: &Config) -> Result<redis::Client> {
     let redis_url = config.redis_url.expose_secret();
     let client = redis::Client::open(redis_url.clone())
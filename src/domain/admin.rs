@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::error::AppError;
+
+/// What an admin may do. Roles are ordered, so a higher role can do everything a lower one can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AdminRole {
+    /// Read-only access to trades and webhooks.
+    Viewer,
+    /// Can move money: manual releases and webhook replays.
+    Operator,
+    /// Full access, including managing other admins.
+    Superadmin,
+}
+
+impl AdminRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminRole::Viewer => "viewer",
+            AdminRole::Operator => "operator",
+            AdminRole::Superadmin => "superadmin",
+        }
+    }
+}
+
+impl fmt::Display for AdminRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AdminRole {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(AdminRole::Viewer),
+            "operator" => Ok(AdminRole::Operator),
+            "superadmin" => Ok(AdminRole::Superadmin),
+            other => Err(AppError::BadRequest(format!("Unknown admin role '{}'", other))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles_are_ordered() {
+        assert!(AdminRole::Viewer < AdminRole::Operator);
+        assert!(AdminRole::Operator < AdminRole::Superadmin);
+    }
+
+    #[test]
+    fn test_role_round_trip() {
+        for role in [AdminRole::Viewer, AdminRole::Operator, AdminRole::Superadmin] {
+            assert_eq!(role.as_str().parse::<AdminRole>().unwrap(), role);
+        }
+        assert!("root".parse::<AdminRole>().is_err());
+    }
+}
@@ -0,0 +1,259 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::domain::models::AdminAuditLogEntry;
+
+/// `prev_hash` of the first row in the chain.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Admin actions recorded in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Login,
+    Logout,
+    SessionsRevoked,
+    ManualRelease,
+    ReleaseRequested,
+    ReleaseApproved,
+    ReleaseRejected,
+    ReleaseExpired,
+    ReleaseFailed,
+    WebhookReplay,
+    RoleChange,
+    AdminCreate,
+    AdminDeactivate,
+    PasswordReset,
+    PasswordChange,
+    TotpEnroll,
+    TotpEnable,
+    UnmatchedAttribute,
+    UnmatchedRefund,
+    UnmatchedDismiss,
+    RecoveryStart,
+    RecoveryComplete,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Login => "admin.login",
+            AuditAction::Logout => "admin.logout",
+            AuditAction::SessionsRevoked => "admin.sessions_revoke",
+            AuditAction::ManualRelease => "transaction.manual_release",
+            AuditAction::ReleaseRequested => "release_request.create",
+            AuditAction::ReleaseApproved => "release_request.approve",
+            AuditAction::ReleaseRejected => "release_request.reject",
+            AuditAction::ReleaseExpired => "release_request.expire",
+            AuditAction::ReleaseFailed => "release_request.fail",
+            AuditAction::WebhookReplay => "webhook.replay",
+            AuditAction::RoleChange => "admin.role_change",
+            AuditAction::AdminCreate => "admin.create",
+            AuditAction::AdminDeactivate => "admin.deactivate",
+            AuditAction::PasswordReset => "admin.password_reset",
+            AuditAction::PasswordChange => "admin.password_change",
+            AuditAction::TotpEnroll => "admin.totp_enroll",
+            AuditAction::TotpEnable => "admin.totp_enable",
+            AuditAction::UnmatchedAttribute => "unmatched_payment.attribute",
+            AuditAction::UnmatchedRefund => "unmatched_payment.refund",
+            AuditAction::UnmatchedDismiss => "unmatched_payment.dismiss",
+            AuditAction::RecoveryStart => "wallet.recovery_start",
+            AuditAction::RecoveryComplete => "wallet.recovery_complete",
+        }
+    }
+}
+
+/// Who performed an action, and the request it came from.
+#[derive(Debug, Clone)]
+pub struct AuditActor {
+    pub admin_id: Option<Uuid>,
+    pub username: String,
+    pub request_id: Option<String>,
+}
+
+impl AuditActor {
+    /// Actions taken by the backend itself, such as expiring stale requests.
+    pub fn system() -> Self {
+        Self {
+            admin_id: None,
+            username: "system".to_string(),
+            request_id: None,
+        }
+    }
+
+    /// Actions run from the command line, by whoever has shell and database access.
+    pub fn cli() -> Self {
+        Self {
+            admin_id: None,
+            username: "cli".to_string(),
+            request_id: None,
+        }
+    }
+}
+
+/// An action about to be appended to the audit log.
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub actor: AuditActor,
+    pub action: AuditAction,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl AuditEvent {
+    pub fn new(actor: &AuditActor, action: AuditAction) -> Self {
+        Self {
+            actor: actor.clone(),
+            action,
+            target_type: None,
+            target_id: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn target(mut self, target_type: &str, target_id: impl ToString) -> Self {
+        self.target_type = Some(target_type.to_string());
+        self.target_id = Some(target_id.to_string());
+        self
+    }
+
+    /// State of the target before the action. Anything that fails to serialize is recorded as null.
+    pub fn before(mut self, state: &impl Serialize) -> Self {
+        self.before = Some(serde_json::to_value(state).unwrap_or(Value::Null));
+        self
+    }
+
+    /// State of the target after the action.
+    pub fn after(mut self, state: &impl Serialize) -> Self {
+        self.after = Some(serde_json::to_value(state).unwrap_or(Value::Null));
+        self
+    }
+}
+
+/// Hash of a row's contents and its predecessor's hash. Values are serialized as a JSON array,
+/// and `serde_json` sorts object keys, so the result is the same after a round trip through JSONB.
+pub fn entry_hash(entry: &AdminAuditLogEntry) -> String {
+    let canonical = json!([
+        entry.seq,
+        entry.prev_hash,
+        entry.id,
+        entry.actor_admin_id,
+        entry.actor_username,
+        entry.action,
+        entry.target_type,
+        entry.target_id,
+        entry.before,
+        entry.after,
+        entry.request_id,
+        entry.created_at.timestamp_micros(),
+    ]);
+    hex::encode(Sha256::digest(canonical.to_string().as_bytes()))
+}
+
+/// Where and why a chain failed verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainBreak {
+    pub seq: i64,
+    pub reason: String,
+}
+
+/// Checks that `entries` continue a chain whose last verified row had `prev_seq` and `prev_hash`:
+/// sequence numbers have no gaps, each row links to the previous hash, and each hash matches its contents.
+/// Returns the seq and hash of the last row so verification can continue with the next batch.
+pub fn verify_chain(
+    entries: &[AdminAuditLogEntry],
+    mut prev_seq: i64,
+    mut prev_hash: String,
+) -> Result<(i64, String), ChainBreak> {
+    for entry in entries {
+        let broken = |reason: &str| ChainBreak {
+            seq: entry.seq,
+            reason: reason.to_string(),
+        };
+        if entry.seq != prev_seq + 1 {
+            return Err(broken("sequence gap: rows are missing"));
+        }
+        if entry.prev_hash != prev_hash {
+            return Err(broken("prev_hash does not match the previous row"));
+        }
+        if entry_hash(entry) != entry.hash {
+            return Err(broken("hash does not match the row contents"));
+        }
+        prev_seq = entry.seq;
+        prev_hash = entry.hash.clone();
+    }
+    Ok((prev_seq, prev_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn chain(len: i64) -> Vec<AdminAuditLogEntry> {
+        let mut prev_hash = GENESIS_HASH.to_string();
+        (1..=len)
+            .map(|seq| {
+                let mut entry = AdminAuditLogEntry {
+                    seq,
+                    id: Uuid::new_v4(),
+                    actor_admin_id: Some(Uuid::new_v4()),
+                    actor_username: "alice".to_string(),
+                    action: AuditAction::ManualRelease.as_str().to_string(),
+                    target_type: Some("transaction".to_string()),
+                    target_id: Some(Uuid::new_v4().to_string()),
+                    before: Some(json!({ "status": "pending", "amount_sats": seq })),
+                    after: Some(json!({ "status": "completed", "amount_sats": seq })),
+                    request_id: Some(format!("req-{}", seq)),
+                    prev_hash: prev_hash.clone(),
+                    hash: String::new(),
+                    created_at: Utc.timestamp_opt(1_700_000_000 + seq, 123_456_000).unwrap(),
+                };
+                entry.hash = entry_hash(&entry);
+                prev_hash = entry.hash.clone();
+                entry
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_intact_chain_verifies_in_batches() {
+        let entries = chain(5);
+        let (seq, hash) = verify_chain(&entries[..2], 0, GENESIS_HASH.to_string()).unwrap();
+        assert_eq!(verify_chain(&entries[2..], seq, hash).unwrap().0, 5);
+    }
+
+    #[test]
+    fn test_edited_row_is_detected() {
+        let mut entries = chain(3);
+        entries[1].after = Some(json!({ "status": "completed", "amount_sats": 999 }));
+
+        let err = verify_chain(&entries, 0, GENESIS_HASH.to_string()).unwrap_err();
+        assert_eq!(err.seq, 2);
+    }
+
+    #[test]
+    fn test_deleted_or_rehashed_row_is_detected() {
+        let mut entries = chain(3);
+        entries.remove(1);
+        assert_eq!(verify_chain(&entries, 0, GENESIS_HASH.to_string()).unwrap_err().seq, 3);
+
+        // Recomputing an edited row's hash still breaks the link from the next row
+        let mut entries = chain(3);
+        entries[1].actor_username = "mallory".to_string();
+        entries[1].hash = entry_hash(&entries[1]);
+        assert_eq!(verify_chain(&entries, 0, GENESIS_HASH.to_string()).unwrap_err().seq, 3);
+    }
+
+    #[test]
+    fn test_key_order_does_not_change_the_hash() {
+        let mut entry = chain(1).remove(0);
+        let original = entry.hash.clone();
+        entry.after = Some(serde_json::from_str(r#"{"amount_sats":1,"status":"completed"}"#).unwrap());
+        assert_eq!(entry_hash(&entry), original);
+    }
+}
@@ -0,0 +1,185 @@
+use uuid::Uuid;
+
+use crate::{domain::types::Sats, error::AppError};
+
+/// Sats entering or leaving through the Lightning node.
+pub const LIGHTNING_ACCOUNT: &str = "system:lightning";
+/// Sabi's own BTC float, the counterparty for fiat trades.
+pub const TREASURY_ACCOUNT: &str = "system:treasury";
+/// Network and service fees.
+pub const FEES_ACCOUNT: &str = "system:fees";
+/// Received sats not yet attributed to a wallet or refunded.
+pub const SUSPENSE_ACCOUNT: &str = "system:suspense";
+
+/// Identifies a ledger account without knowing its database ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountRef {
+    /// The account backing a user's wallet.
+    Wallet(Uuid),
+    /// A system account identified by its code (see the `*_ACCOUNT` constants).
+    System(&'static str),
+}
+
+/// A single leg of a journal entry. Positive amounts are debits, negative amounts credits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Posting {
+    pub account: AccountRef,
+    pub amount: Sats,
+}
+
+/// A balanced set of postings that is written to the ledger atomically.
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub entry_type: String,
+    pub transaction_id: Option<Uuid>,
+    pub description: Option<String>,
+    pub postings: Vec<Posting>,
+}
+
+impl JournalEntry {
+    pub fn new(entry_type: &str) -> Self {
+        Self {
+            entry_type: entry_type.to_string(),
+            transaction_id: None,
+            description: None,
+            postings: Vec::new(),
+        }
+    }
+
+    /// Links the entry to the `transactions` row that caused it.
+    pub fn for_transaction(mut self, transaction_id: Uuid) -> Self {
+        self.transaction_id = Some(transaction_id);
+        self
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Adds a raw posting. Zero amounts are ignored so callers can pass optional fees unconditionally.
+    pub fn post(mut self, account: AccountRef, amount: Sats) -> Self {
+        if amount.0 != 0 {
+            self.postings.push(Posting { account, amount });
+        }
+        self
+    }
+
+    /// Moves `amount` from one account to another.
+    pub fn transfer(self, from: AccountRef, to: AccountRef, amount: Sats) -> Self {
+        self.post(from, Sats(-amount.0)).post(to, amount)
+    }
+
+    /// An entry undoing this one, e.g. when a payment booked before sending did not go out.
+    pub fn reversed(&self, entry_type: &str) -> Self {
+        Self {
+            entry_type: entry_type.to_string(),
+            transaction_id: self.transaction_id,
+            description: self.description.clone(),
+            postings: self
+                .postings
+                .iter()
+                .map(|p| Posting { account: p.account, amount: Sats(-p.amount.0) })
+                .collect(),
+        }
+    }
+
+    /// Wallets this entry takes sats out of, in ID order. Only these need locking: their balance
+    /// must stay non-negative, while system accounts may go negative and are never checked.
+    pub fn credited_wallets(&self) -> Vec<Uuid> {
+        let mut wallets: Vec<Uuid> = self
+            .postings
+            .iter()
+            .filter(|p| p.amount.0 < 0)
+            .filter_map(|p| match p.account {
+                AccountRef::Wallet(wallet_id) => Some(wallet_id),
+                AccountRef::System(_) => None,
+            })
+            .collect();
+        wallets.sort();
+        wallets.dedup();
+        wallets
+    }
+
+    /// Checks that the entry has at least two postings and sums to exactly zero.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.postings.len() < 2 {
+            return Err(AppError::Internal(format!(
+                "Journal entry '{}' needs at least two postings",
+                self.entry_type
+            )));
+        }
+
+        let total = self
+            .postings
+            .iter()
+            .try_fold(0i64, |acc, p| acc.checked_add(p.amount.0))
+            .ok_or_else(|| {
+                AppError::Internal(format!("Journal entry '{}' overflows", self.entry_type))
+            })?;
+
+        if total != 0 {
+            return Err(AppError::Internal(format!(
+                "Journal entry '{}' is unbalanced by {} sats",
+                self.entry_type, total
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_is_balanced() {
+        let wallet = AccountRef::Wallet(Uuid::new_v4());
+        let entry = JournalEntry::new("ussd_send")
+            .transfer(wallet, AccountRef::System(LIGHTNING_ACCOUNT), Sats(1_000));
+
+        assert_eq!(entry.postings.len(), 2);
+        assert!(entry.validate().is_ok());
+    }
+
+    #[test]
+    fn test_only_wallets_losing_sats_are_credited_wallets() {
+        let (payer, payee) = (Uuid::new_v4(), Uuid::new_v4());
+        let entry = JournalEntry::new("transfer")
+            .transfer(AccountRef::Wallet(payer), AccountRef::Wallet(payee), Sats(1_000))
+            .transfer(AccountRef::Wallet(payer), AccountRef::System(FEES_ACCOUNT), Sats(10))
+            .transfer(AccountRef::System(TREASURY_ACCOUNT), AccountRef::System(LIGHTNING_ACCOUNT), Sats(5));
+
+        assert_eq!(entry.credited_wallets(), vec![payer]);
+    }
+
+    #[test]
+    fn test_reversed_entry_undoes_every_posting() {
+        let wallet = AccountRef::Wallet(Uuid::new_v4());
+        let entry = JournalEntry::new("deposit_payout")
+            .transfer(wallet, AccountRef::System(LIGHTNING_ACCOUNT), Sats(1_000));
+        let reversal = entry.reversed("deposit_payout_reversal");
+
+        assert!(reversal.validate().is_ok());
+        assert_eq!(reversal.credited_wallets(), Vec::<Uuid>::new());
+        assert_eq!(reversal.postings[0], Posting { account: wallet, amount: Sats(1_000) });
+    }
+
+    #[test]
+    fn test_unbalanced_and_degenerate_entries_are_rejected() {
+        let wallet = AccountRef::Wallet(Uuid::new_v4());
+
+        let unbalanced = JournalEntry::new("bad")
+            .post(wallet, Sats(1_000))
+            .post(AccountRef::System(LIGHTNING_ACCOUNT), Sats(-999));
+        assert!(unbalanced.validate().is_err());
+
+        let single_leg = JournalEntry::new("bad").post(wallet, Sats(1_000));
+        assert!(single_leg.validate().is_err());
+
+        let zero_fee = JournalEntry::new("bad").post(AccountRef::System(FEES_ACCOUNT), Sats(0));
+        assert!(zero_fee.postings.is_empty());
+        assert!(zero_fee.validate().is_err());
+    }
+}
@@ -1,2 +1,12 @@
+pub mod admin;
+pub mod audit;
+pub mod ledger;
 pub mod models;
+pub mod money;
+pub mod pagination;
+pub mod payout;
+pub mod quote;
+pub mod recovery;
+pub mod totp;
+pub mod transaction;
 pub mod types;
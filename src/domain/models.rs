@@ -3,7 +3,11 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
-use crate::domain::types::{Kobo, Sats};
+use crate::domain::{
+    quote::QuoteSide,
+    transaction::{PaymentDirection, TransactionStatus, TransactionType},
+    types::{Kobo, Sats},
+};
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct User {
@@ -19,7 +23,6 @@ pub struct Wallet {
     pub user_id: Uuid,
     pub nostr_npub: String,
     pub breez_wallet_id: String,
-    pub balance_sats: Sats, // Using Sats custom type
     pub backup_type: String, // 'none' | 'social' | 'seed'
     pub backup_status: String, // 'skipped' | 'pending' | 'completed' | 'failed'
     pub created_at: DateTime<Utc>,
@@ -30,18 +33,23 @@ pub struct Wallet {
 pub struct EncryptedNostrNsec {
     pub id: Uuid,
     pub wallet_id: Uuid,
-    pub encrypted_nsec: String,
+    pub legacy_nsec: Option<String>, // Plaintext from before envelope encryption; cleared by `rotate-master-key`
+    pub key_version: Option<i32>,
+    pub wrapped_data_key: Option<Vec<u8>>,
+    pub nonce: Option<Vec<u8>>,
+    pub ciphertext: Option<Vec<u8>>,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct Transaction {
     pub id: Uuid,
     pub wallet_id: Uuid,
-    pub tx_type: String, // Enum might be better here: FiatDeposit, BtcWithdrawal, etc.
+    pub tx_type: TransactionType,
     pub amount_sats: Sats, // Using Sats custom type
     pub fee_sats: Sats,
-    pub status: String, // Enum might be better: Pending, Completed, Failed
+    pub status: TransactionStatus, // Changed only through transaction_service::transition
     pub description: Option<String>,
     pub external_id: Option<String>,
     pub created_at: DateTime<Utc>,
@@ -54,16 +62,119 @@ pub struct AdminUser {
     pub username: String,
     pub password_hash: String,
     pub is_active: bool,
+    pub must_change_password: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
-pub struct FiatOnrampWebhook {
+pub struct ReleaseRequest {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub amount_sats: Sats,
+    pub recipient: String,
+    pub notes: Option<String>,
+    pub status: String, // 'pending' | 'executing' | 'approved' | 'failed' | 'rejected' | 'expired'
+    pub requested_by: Uuid,
+    pub decided_by: Option<Uuid>,
+    pub decision_reason: Option<String>,
+    pub failure_reason: Option<String>, // Why an approved release's payment did not go out
+    pub expires_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct AdminAuditLogEntry {
+    pub seq: i64, // Position in the hash chain, starting at 1
+    pub id: Uuid,
+    pub actor_admin_id: Option<Uuid>,
+    pub actor_username: String,
+    pub action: String, // e.g., 'transaction.manual_release'
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub request_id: Option<String>,
+    pub prev_hash: String,
+    pub hash: String, // SHA-256 over this row and `prev_hash`
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WebhookEvent {
     pub id: Uuid,
     pub provider: String, // e.g., 'paystack', 'gtbank'
     pub event_id: String,
+    pub event_type: Option<String>,
     pub payload: serde_json::Value,
+    #[serde(skip_serializing)]
+    pub raw_body: Option<Vec<u8>>, // Exact bytes as received
+    pub headers: serde_json::Value,
     pub processed: bool,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub processed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct PayoutJob {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub status: String, // 'queued' | 'sending' | 'succeeded' | 'held' | 'cancelled'
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub payment_hash: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A Lightning payment the node reported that matched no transaction, parked for an admin.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UnmatchedPayment {
+    pub id: Uuid,
+    pub payment_hash: String,
+    pub direction: PaymentDirection,
+    pub amount_sats: Sats,
+    pub fee_sats: Sats,
+    pub bolt11: Option<String>,
+    pub status: String, // 'open' | 'refunding' | 'attributed' | 'refunded' | 'dismissed'
+    pub wallet_id: Option<Uuid>, // Set when attributed
+    pub transaction_id: Option<Uuid>, // The transaction created by attributing it
+    pub refund_payment_hash: Option<String>,
+    pub resolved_by: Option<Uuid>,
+    pub resolution_notes: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A price locked for one buy or sell until `expires_at`. Honoured at most once.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Quote {
+    pub id: Uuid,
+    pub side: QuoteSide,
+    pub market_rate: String, // Exact decimals; parse as `ExchangeRate`
+    pub rate: String, // The market rate with the spread taken off
+    pub spread_bps: i64,
+    pub amount_kobo: Kobo, // Buy: paid in. Sell: what the sats are worth at `rate`
+    pub fee_kobo: Kobo, // Comes off the Naira side
+    pub amount_sats: Sats, // Buy: paid out. Sell: sold
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub used_reference: Option<String>, // Paystack charge or sell order reference it was honoured for
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct LedgerAccount {
+    pub id: Uuid,
+    pub code: String, // e.g., 'system:lightning', 'wallet:<uuid>'
+    pub kind: String, // 'wallet' | 'system'
+    pub wallet_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
 }
@@ -0,0 +1,349 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use crate::{
+    domain::types::{Kobo, Sats},
+    error::AppError,
+};
+
+const SATS_PER_BTC_EXP: u32 = 8;
+const KOBO_PER_NAIRA_EXP: u32 = 2;
+/// Most decimal places an exchange rate may carry.
+const MAX_RATE_SCALE: u32 = 18;
+/// Upper bound on a rate, far above any real BTC/NGN price. Keeps all intermediate math inside i128.
+const MAX_NAIRA_PER_BTC: i128 = 1_000_000_000_000_000; // 10^15
+
+/// How to round when an amount cannot be represented exactly in the target unit.
+/// Use `Floor` for amounts we pay out and `Ceiling` for fees we charge, so rounding never favours the user
+/// at the treasury's expense.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Floor,
+    Ceiling,
+}
+
+/// An exact BTC/NGN price: `mantissa / 10^scale` Naira per BTC.
+/// Parsed from decimal strings and never converted through floating point for money math.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExchangeRate {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl ExchangeRate {
+    /// A whole-Naira price per BTC.
+    pub fn from_naira_per_btc(naira: u64) -> Result<Self, AppError> {
+        Self::new(naira as i128, 0)
+    }
+
+    fn new(mantissa: i128, scale: u32) -> Result<Self, AppError> {
+        let mut rate = Self { mantissa, scale };
+        while rate.scale > 0 && rate.mantissa % 10 == 0 {
+            rate.mantissa /= 10;
+            rate.scale -= 1;
+        }
+
+        if rate.mantissa <= 0 {
+            return Err(AppError::BadRequest("Exchange rate must be positive".to_string()));
+        }
+        if rate.scale > MAX_RATE_SCALE {
+            return Err(AppError::BadRequest(format!(
+                "Exchange rate has more than {} decimal places",
+                MAX_RATE_SCALE
+            )));
+        }
+        if rate.mantissa > MAX_NAIRA_PER_BTC * pow10(rate.scale) {
+            return Err(AppError::BadRequest("Exchange rate is out of range".to_string()));
+        }
+        Ok(rate)
+    }
+
+    /// Both rates as mantissas over the same power of ten.
+    fn aligned(&self, other: &Self) -> (i128, i128, u32) {
+        let scale = self.scale.max(other.scale);
+        (
+            self.mantissa * pow10(scale - self.scale),
+            other.mantissa * pow10(scale - other.scale),
+            scale,
+        )
+    }
+
+    /// The exact midpoint of two rates (rounded down only if it needs more than `MAX_RATE_SCALE` decimals).
+    pub fn midpoint(&self, other: &Self) -> Self {
+        let (a, b, scale) = self.aligned(other);
+        let (mut mantissa, mut scale) = ((a + b) * 5, scale + 1);
+        if scale > MAX_RATE_SCALE {
+            mantissa /= 10;
+            scale -= 1;
+        }
+        Self::new(mantissa, scale).unwrap_or(*self.min(other))
+    }
+
+    /// Distance from `reference` in basis points of `reference`, rounded down.
+    pub fn deviation_bps(&self, reference: &Self) -> i128 {
+        let (a, b, _) = self.aligned(reference);
+        (a - b).abs() * 10_000 / b
+    }
+
+    /// This rate moved by `bps` basis points: positive marks it up, negative down. Exact unless it
+    /// needs more than `MAX_RATE_SCALE` decimals, in which case it is rounded down.
+    pub fn adjusted_by_bps(&self, bps: i64) -> Result<Self, AppError> {
+        let factor = 10_000 + bps as i128;
+        if factor <= 0 {
+            return Err(AppError::BadRequest(format!("Cannot adjust a rate by {} bps", bps)));
+        }
+        let (mut mantissa, mut scale) = (self.mantissa * factor, self.scale + 4);
+        while scale > MAX_RATE_SCALE {
+            mantissa /= 10;
+            scale -= 1;
+        }
+        Self::new(mantissa, scale)
+    }
+
+    /// Approximate value for display only. Never use this for conversions.
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+}
+
+impl Ord for ExchangeRate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b, _) = self.aligned(other);
+        a.cmp(&b)
+    }
+}
+
+impl PartialOrd for ExchangeRate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for ExchangeRate {
+    type Err = AppError;
+
+    /// Accepts plain decimals ("151000000.50") and the exponent form some JSON encoders emit ("1.51e8").
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || AppError::BadRequest(format!("Invalid exchange rate: '{}'", s));
+        let s = s.trim();
+
+        let (number, exponent) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], s[i + 1..].parse::<i32>().map_err(|_| invalid())?),
+            None => (s, 0),
+        };
+        let (mantissa, scale) = parse_unsigned_decimal(number).ok_or_else(invalid)?;
+
+        let scale = scale as i32 - exponent;
+        if scale >= 0 {
+            Self::new(mantissa, scale as u32)
+        } else {
+            let factor = 10i128.checked_pow((-scale) as u32).ok_or_else(invalid)?;
+            Self::new(mantissa.checked_mul(factor).ok_or_else(invalid)?, 0)
+        }
+    }
+}
+
+impl fmt::Display for ExchangeRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_decimal(self.mantissa, self.scale))
+    }
+}
+
+impl Serialize for ExchangeRate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for ExchangeRate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Converts Naira (in Kobo) to Sats at `rate`.
+pub fn kobo_to_sats(amount: Kobo, rate: &ExchangeRate, rounding: Rounding) -> Result<Sats, AppError> {
+    // sats = kobo / 100 / (mantissa / 10^scale) * 10^8 = kobo * 10^(6 + scale) / mantissa
+    let numerator = (amount.0 as i128)
+        .checked_mul(pow10(SATS_PER_BTC_EXP - KOBO_PER_NAIRA_EXP + rate.scale))
+        .ok_or_else(|| overflow(amount, rate))?;
+    let sats = div_round(numerator, rate.mantissa, rounding);
+    i64::try_from(sats).map(Sats).map_err(|_| overflow(amount, rate))
+}
+
+/// Converts Sats to Naira (in Kobo) at `rate`.
+pub fn sats_to_kobo(amount: Sats, rate: &ExchangeRate, rounding: Rounding) -> Result<Kobo, AppError> {
+    // kobo = sats / 10^8 * (mantissa / 10^scale) * 100 = sats * mantissa / 10^(6 + scale)
+    let numerator = (amount.0 as i128)
+        .checked_mul(rate.mantissa)
+        .ok_or_else(|| overflow(amount, rate))?;
+    let kobo = div_round(
+        numerator,
+        pow10(SATS_PER_BTC_EXP - KOBO_PER_NAIRA_EXP + rate.scale),
+        rounding,
+    );
+    i64::try_from(kobo).map(Kobo).map_err(|_| overflow(amount, rate))
+}
+
+/// Parses a non-negative decimal string into an integer count of `10^-decimals` units,
+/// rejecting anything more precise than the unit allows instead of truncating it.
+pub(crate) fn parse_fixed(s: &str, decimals: u32) -> Result<i64, AppError> {
+    let invalid = || AppError::BadRequest(format!("Invalid amount: '{}'", s));
+    let (mantissa, scale) = parse_unsigned_decimal(s.trim()).ok_or_else(invalid)?;
+    if scale > decimals {
+        return Err(AppError::BadRequest(format!(
+            "Amount '{}' has more than {} decimal places",
+            s, decimals
+        )));
+    }
+    mantissa
+        .checked_mul(pow10(decimals - scale))
+        .and_then(|units| i64::try_from(units).ok())
+        .ok_or_else(invalid)
+}
+
+/// Formats an integer count of `10^-decimals` units as a decimal string, keeping all decimals.
+pub(crate) fn format_fixed(units: i64, decimals: u32) -> String {
+    let sign = if units < 0 { "-" } else { "" };
+    let abs = (units as i128).abs();
+    let divisor = pow10(decimals);
+    if decimals == 0 {
+        return format!("{}{}", sign, abs);
+    }
+    format!(
+        "{}{}.{:0width$}",
+        sign,
+        abs / divisor,
+        abs % divisor,
+        width = decimals as usize
+    )
+}
+
+/// Splits "123.4500" into (1234500, 4). Only digits and at most one '.' are accepted.
+fn parse_unsigned_decimal(s: &str) -> Option<(i128, u32)> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    if !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let scale = u32::try_from(fraction.len()).ok()?;
+    let mantissa = format!("{}{}", whole, fraction).parse::<i128>().ok()?;
+    Some((mantissa, scale))
+}
+
+fn format_decimal(mantissa: i128, scale: u32) -> String {
+    if scale == 0 {
+        return mantissa.to_string();
+    }
+    let divisor = pow10(scale);
+    format!(
+        "{}.{:0width$}",
+        mantissa / divisor,
+        mantissa % divisor,
+        width = scale as usize
+    )
+}
+
+fn div_round(numerator: i128, denominator: i128, rounding: Rounding) -> i128 {
+    let quotient = numerator.div_euclid(denominator);
+    match rounding {
+        Rounding::Floor => quotient,
+        Rounding::Ceiling if numerator.rem_euclid(denominator) != 0 => quotient + 1,
+        Rounding::Ceiling => quotient,
+    }
+}
+
+fn pow10(exp: u32) -> i128 {
+    10i128.pow(exp)
+}
+
+fn overflow(amount: impl fmt::Display, rate: &ExchangeRate) -> AppError {
+    AppError::Internal(format!("Converting {} at rate {} overflows", amount, rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display_rates() {
+        let rate: ExchangeRate = "151000000.50".parse().unwrap();
+        assert_eq!(rate.to_string(), "151000000.5");
+        assert_eq!("1.51e8".parse::<ExchangeRate>().unwrap().to_string(), "151000000");
+        assert_eq!(
+            "149500000.0".parse::<ExchangeRate>().unwrap(),
+            ExchangeRate::from_naira_per_btc(149_500_000).unwrap()
+        );
+
+        assert!("0".parse::<ExchangeRate>().is_err());
+        assert!("-1".parse::<ExchangeRate>().is_err());
+        assert!("abc".parse::<ExchangeRate>().is_err());
+        assert!("1e40".parse::<ExchangeRate>().is_err());
+    }
+
+    #[test]
+    fn test_kobo_to_sats_is_exact_and_rounds_explicitly() {
+        let rate = ExchangeRate::from_naira_per_btc(150_000_000).unwrap();
+
+        // ₦1,500 buys exactly 1,000 sats
+        assert_eq!(kobo_to_sats(Kobo(150_000), &rate, Rounding::Floor).unwrap(), Sats(1_000));
+        assert_eq!(kobo_to_sats(Kobo(150_000), &rate, Rounding::Ceiling).unwrap(), Sats(1_000));
+
+        // ₦1,000 is 666.67 sats
+        assert_eq!(kobo_to_sats(Kobo(100_000), &rate, Rounding::Floor).unwrap(), Sats(666));
+        assert_eq!(kobo_to_sats(Kobo(100_000), &rate, Rounding::Ceiling).unwrap(), Sats(667));
+
+        // ₦18 is exactly 12 sats; the old f64 conversion produced 11
+        assert_eq!(kobo_to_sats(Kobo(1_800), &rate, Rounding::Floor).unwrap(), Sats(12));
+    }
+
+    #[test]
+    fn test_sats_to_kobo_with_fractional_rate() {
+        let rate: ExchangeRate = "151234567.89".parse().unwrap();
+
+        assert_eq!(
+            sats_to_kobo(Sats(100_000_000), &rate, Rounding::Floor).unwrap(),
+            Kobo(15_123_456_789)
+        );
+        assert_eq!(sats_to_kobo(Sats(1), &rate, Rounding::Floor).unwrap(), Kobo(151));
+        assert_eq!(sats_to_kobo(Sats(1), &rate, Rounding::Ceiling).unwrap(), Kobo(152));
+        assert_eq!(sats_to_kobo(Sats(-1), &rate, Rounding::Floor).unwrap(), Kobo(-152));
+    }
+
+    #[test]
+    fn test_ordering_midpoint_and_deviation() {
+        let a: ExchangeRate = "150000000".parse().unwrap();
+        let b: ExchangeRate = "150000001.5".parse().unwrap();
+
+        assert!(a < b);
+        assert_eq!(a.midpoint(&b).to_string(), "150000000.75");
+        assert_eq!(b.deviation_bps(&a), 0);
+        assert_eq!("157500000".parse::<ExchangeRate>().unwrap().deviation_bps(&a), 500);
+    }
+
+    #[test]
+    fn test_adjusting_by_bps() {
+        let rate: ExchangeRate = "150000000".parse().unwrap();
+        assert_eq!(rate.adjusted_by_bps(100).unwrap().to_string(), "151500000");
+        assert_eq!(rate.adjusted_by_bps(-25).unwrap().to_string(), "149625000");
+        assert_eq!(rate.adjusted_by_bps(0).unwrap(), rate);
+        assert_eq!(
+            "0.000000000000000003".parse::<ExchangeRate>().unwrap().adjusted_by_bps(1).unwrap().to_string(),
+            "0.000000000000000003"
+        );
+        assert!(rate.adjusted_by_bps(-10_000).is_err());
+    }
+
+    #[test]
+    fn test_parse_and_format_fixed() {
+        assert_eq!(parse_fixed("10.5", 2).unwrap(), 1050);
+        assert_eq!(parse_fixed("0.00012345", 8).unwrap(), 12345);
+        assert!(parse_fixed("0.001", 2).is_err());
+        assert_eq!(format_fixed(1050, 2), "10.50");
+        assert_eq!(format_fixed(-12345, 8), "-0.00012345");
+    }
+}
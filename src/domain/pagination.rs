@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+/// Position after the last row of a page ordered by `(created_at DESC, id DESC)`.
+/// Unlike an offset, it stays correct while new rows are inserted at the top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    /// Opaque form handed to clients as `next_cursor`.
+    pub fn encode(&self) -> String {
+        hex::encode(format!("{}:{}", self.created_at.timestamp_micros(), self.id))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::BadRequest("Invalid cursor".to_string());
+        let raw = String::from_utf8(hex::decode(cursor.trim()).map_err(|_| invalid())?).map_err(|_| invalid())?;
+        let (micros, id) = raw.split_once(':').ok_or_else(invalid)?;
+        let micros = micros.parse::<i64>().map_err(|_| invalid())?;
+        Ok(Self {
+            created_at: DateTime::<Utc>::from_timestamp_micros(micros).ok_or_else(invalid)?,
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Clamps a requested page size to `1..=MAX_PAGE_SIZE`, defaulting to `DEFAULT_PAGE_SIZE`.
+pub fn page_size(requested: Option<i64>) -> i64 {
+    requested.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor {
+            created_at: Utc.timestamp_opt(1_735_000_000, 123_456_000).unwrap(),
+            id: Uuid::new_v4(),
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+    }
+
+    #[test]
+    fn test_malformed_cursors_are_rejected() {
+        for cursor in ["", "zz", &hex::encode("not-a-cursor"), &hex::encode("123:not-a-uuid")] {
+            assert!(matches!(Cursor::decode(cursor), Err(AppError::BadRequest(_))));
+        }
+    }
+
+    #[test]
+    fn test_page_size_is_clamped() {
+        assert_eq!(page_size(None), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_size(Some(0)), 1);
+        assert_eq!(page_size(Some(10_000)), MAX_PAGE_SIZE);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::error::AppError;
+
+/// Where a wallet's deposits are forwarded over Lightning: a Lightning address (`name@domain`)
+/// or a bech32 LNURL-pay code. Both resolve to a fresh invoice for every payment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayoutDestination(String);
+
+impl PayoutDestination {
+    /// Checks the shape of a destination. Whether it actually pays is only known on the first payout.
+    pub fn parse(raw: &str) -> Result<Self, AppError> {
+        let destination = raw.trim().to_lowercase();
+        if is_lightning_address(&destination) || is_lnurl(&destination) {
+            Ok(Self(destination))
+        } else {
+            Err(AppError::BadRequest(
+                "payout_destination must be a Lightning address (name@domain) or an LNURL".to_string(),
+            ))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+fn is_lightning_address(destination: &str) -> bool {
+    let Some((name, domain)) = destination.split_once('@') else {
+        return false;
+    };
+    let name_ok = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.+".contains(c));
+    let domain_ok = domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && domain.chars().all(|c| c.is_ascii_alphanumeric() || "-.".contains(c));
+    name_ok && domain_ok
+}
+
+fn is_lnurl(destination: &str) -> bool {
+    destination
+        .strip_prefix("lnurl1")
+        .is_some_and(|data| !data.is_empty() && data.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts_lightning_addresses_and_lnurls() {
+        assert_eq!(PayoutDestination::parse(" Ada@Getalby.com ").unwrap().as_str(), "ada@getalby.com");
+        assert!(PayoutDestination::parse("LNURL1DP68GURN8GHJ7UM9WFMXJCM99E3K7MF0V9CXJ0M385EKVCENXC6R2C35XVUKXEFCV5MKVV34X5EKZD3EV56NYD3HXQURZEPEXEJXXEPNXSCRVWFNV9NXZCN9XQ6XYEFHVGCXXCMYXYMNSERXFQ5FNS").is_ok());
+    }
+
+    #[test]
+    fn test_rejects_internal_ids_and_invoices() {
+        assert!(PayoutDestination::parse("breez_0123456789abcdef").is_err());
+        assert!(PayoutDestination::parse("lnbc10u1p3...").is_err());
+        assert!(PayoutDestination::parse("ada@localhost").is_err());
+        assert!(PayoutDestination::parse("@getalby.com").is_err());
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Which way a quoted trade goes. Stored as snake_case TEXT in `quotes.side`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum QuoteSide {
+    /// Naira paid in through Paystack, sats paid out to the wallet.
+    Buy,
+    /// Sats debited from the wallet, Naira paid out to a bank account.
+    Sell,
+}
+
+impl QuoteSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuoteSide::Buy => "buy",
+            QuoteSide::Sell => "sell",
+        }
+    }
+}
+
+impl fmt::Display for QuoteSide {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
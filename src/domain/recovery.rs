@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shamir::SecretData;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+/// Format version of `RecoveryShare`, bumped if the encoding ever changes.
+pub const SHARE_VERSION: u8 = 1;
+/// Fewer than two shares would hand the whole secret to a single helper.
+pub const MIN_THRESHOLD: u8 = 2;
+
+/// One helper's piece of a wallet secret, with enough metadata to refuse mixing
+/// shares from different wallets or different recovery sessions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryShare {
+    pub version: u8,
+    pub recovery_id: Uuid,
+    pub wallet_id: Uuid,
+    /// 1-based share index (the x-coordinate of the share).
+    pub index: u8,
+    pub threshold: u8,
+    pub share_count: u8,
+    /// Hex-encoded Shamir share bytes.
+    pub data: String,
+    /// Ties the reconstructed secret to this recovery so a wrong combination is detected.
+    pub checksum: String,
+}
+
+/// How many helpers hold a share of a wallet's secret, and how many are needed to recover it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryPolicy {
+    pub threshold: u8,
+    pub share_count: u8,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 3,
+            share_count: 5,
+        }
+    }
+}
+
+impl RecoveryPolicy {
+    /// Checks a k-of-n recovery policy.
+    pub fn new(threshold: u8, share_count: u8) -> Result<Self, AppError> {
+        validate_policy(threshold, share_count)?;
+        Ok(Self {
+            threshold,
+            share_count,
+        })
+    }
+
+    /// Checks that there is exactly one distinct helper per share.
+    pub fn check_helpers(&self, helpers: &[String]) -> Result<(), AppError> {
+        let distinct: HashSet<&str> = helpers.iter().map(String::as_str).collect();
+        if distinct.len() != helpers.len() || helpers.len() != self.share_count as usize {
+            return Err(AppError::BadRequest(format!(
+                "Social recovery needs {} distinct helpers, got {}",
+                self.share_count,
+                distinct.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn validate_policy(threshold: u8, share_count: u8) -> Result<(), AppError> {
+    if threshold < MIN_THRESHOLD {
+        return Err(AppError::BadRequest(format!(
+            "Recovery threshold must be at least {}",
+            MIN_THRESHOLD
+        )));
+    }
+    if share_count < threshold {
+        return Err(AppError::BadRequest(format!(
+            "Recovery needs at least as many shares ({}) as the threshold ({})",
+            share_count, threshold
+        )));
+    }
+    Ok(())
+}
+
+/// Splits `secret` into `policy.share_count` shares, any `policy.threshold` of which reconstruct it.
+pub fn split_secret(
+    secret: &[u8],
+    wallet_id: Uuid,
+    recovery_id: Uuid,
+    policy: RecoveryPolicy,
+) -> Result<Vec<RecoveryShare>, AppError> {
+    let RecoveryPolicy {
+        threshold,
+        share_count,
+    } = policy;
+    validate_policy(threshold, share_count)?;
+    if secret.is_empty() {
+        return Err(AppError::Internal(
+            "Cannot split an empty secret".to_string(),
+        ));
+    }
+
+    // The shamir crate works on UTF-8 text, so the secret is split in its hex form
+    let secret_data = SecretData::with_secret(&hex::encode(secret), threshold);
+    let checksum = checksum(recovery_id, secret);
+
+    (1..=share_count)
+        .map(|index| {
+            let share = secret_data.get_share(index).map_err(|e| {
+                AppError::Internal(format!(
+                    "Failed to generate recovery share {}: {:?}",
+                    index, e
+                ))
+            })?;
+            Ok(RecoveryShare {
+                version: SHARE_VERSION,
+                recovery_id,
+                wallet_id,
+                index,
+                threshold,
+                share_count,
+                data: hex::encode(share),
+                checksum: checksum.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Reconstructs the secret from at least `threshold` shares of the same recovery.
+pub fn combine_shares(shares: &[RecoveryShare]) -> Result<Vec<u8>, AppError> {
+    let first = shares
+        .first()
+        .ok_or_else(|| AppError::BadRequest("No recovery shares provided".to_string()))?;
+
+    let mut indices = HashSet::new();
+    for share in shares {
+        if share.version != SHARE_VERSION {
+            return Err(AppError::BadRequest(format!(
+                "Unsupported recovery share version {}",
+                share.version
+            )));
+        }
+        if share.recovery_id != first.recovery_id
+            || share.wallet_id != first.wallet_id
+            || share.threshold != first.threshold
+            || share.share_count != first.share_count
+            || share.checksum != first.checksum
+        {
+            return Err(AppError::BadRequest(
+                "Recovery shares belong to different recoveries and cannot be combined".to_string(),
+            ));
+        }
+        if share.index == 0 || share.index > share.share_count || !indices.insert(share.index) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid or duplicate recovery share index {}",
+                share.index
+            )));
+        }
+    }
+
+    if shares.len() < first.threshold as usize {
+        return Err(AppError::BadRequest(format!(
+            "Need {} recovery shares, only {} provided",
+            first.threshold,
+            shares.len()
+        )));
+    }
+
+    let share_bytes = shares
+        .iter()
+        .map(|share| {
+            let bytes = hex::decode(&share.data).map_err(|e| {
+                AppError::BadRequest(format!("Recovery share is not valid hex: {}", e))
+            })?;
+            // The share data carries its own index as the first byte; it must agree with the metadata
+            if bytes.first() != Some(&share.index) {
+                return Err(AppError::BadRequest(format!(
+                    "Recovery share {} data does not match its index",
+                    share.index
+                )));
+            }
+            Ok(bytes)
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    let mismatch =
+        || AppError::BadRequest("Recovery shares do not reconstruct the wallet secret".to_string());
+    let secret_hex =
+        SecretData::recover_secret(first.threshold, share_bytes).ok_or_else(mismatch)?;
+    let secret = hex::decode(secret_hex).map_err(|_| mismatch())?;
+
+    if checksum(first.recovery_id, &secret) != first.checksum {
+        return Err(mismatch());
+    }
+    Ok(secret)
+}
+
+fn checksum(recovery_id: Uuid, secret: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(recovery_id.as_bytes());
+    hasher.update(secret);
+    hex::encode(&hasher.finalize()[..8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: [u8; 32] = [7u8; 32];
+
+    #[test]
+    fn test_any_threshold_subset_reconstructs() {
+        let wallet_id = Uuid::new_v4();
+        let shares = split_secret(
+            &SECRET,
+            wallet_id,
+            Uuid::new_v4(),
+            RecoveryPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(shares.len(), 5);
+
+        assert_eq!(combine_shares(&shares[..3]).unwrap(), SECRET);
+        assert_eq!(
+            combine_shares(&[shares[4].clone(), shares[1].clone(), shares[3].clone()]).unwrap(),
+            SECRET
+        );
+        assert_eq!(combine_shares(&shares).unwrap(), SECRET);
+        assert!(combine_shares(&shares[..2]).is_err());
+    }
+
+    #[test]
+    fn test_shares_from_different_recoveries_are_rejected() {
+        let wallet_id = Uuid::new_v4();
+        let policy = RecoveryPolicy::new(2, 3).unwrap();
+        let a = split_secret(&SECRET, wallet_id, Uuid::new_v4(), policy).unwrap();
+        let b = split_secret(&SECRET, wallet_id, Uuid::new_v4(), policy).unwrap();
+
+        assert!(combine_shares(&[a[0].clone(), b[1].clone()]).is_err());
+        assert!(combine_shares(&[a[0].clone(), a[0].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_tampered_share_is_detected() {
+        let mut shares = split_secret(
+            &SECRET,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            RecoveryPolicy::new(2, 2).unwrap(),
+        )
+        .unwrap();
+        let mut bytes = hex::decode(&shares[1].data).unwrap();
+        bytes[5] ^= 0x01;
+        shares[1].data = hex::encode(bytes);
+
+        assert!(combine_shares(&shares).is_err());
+    }
+
+    #[test]
+    fn test_policy_validation() {
+        assert!(RecoveryPolicy::new(1, 3).is_err());
+        assert!(RecoveryPolicy::new(4, 3).is_err());
+        assert!(RecoveryPolicy::new(2, 2).is_ok());
+    }
+
+    #[test]
+    fn test_one_distinct_helper_per_share() {
+        let policy = RecoveryPolicy::new(2, 3).unwrap();
+        let helpers = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert!(policy.check_helpers(&helpers(&["a", "b", "c"])).is_ok());
+        assert!(policy.check_helpers(&helpers(&["a", "b"])).is_err());
+        assert!(policy.check_helpers(&helpers(&["a", "b", "a"])).is_err());
+        assert!(policy.check_helpers(&helpers(&["a", "b", "c", "d"])).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use rand::{distributions::Alphanumeric, Rng, RngCore};
+use sha2::{Digest, Sha256};
+use totp_rs::{Algorithm, TOTP};
+
+use crate::error::AppError;
+
+pub const TOTP_ISSUER: &str = "Sabi Wallet";
+pub const TOTP_STEP_SECONDS: u64 = 30;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const TOTP_DIGITS: usize = 6;
+const SECRET_LEN: usize = 20; // 160 bits, as recommended by RFC 4226
+const RECOVERY_CODE_LEN: usize = 10;
+/// Codes from the previous and next step are accepted to absorb clock drift.
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+
+/// Generates a new random TOTP secret.
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; SECRET_LEN];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+fn totp(secret: &[u8], account: &str) -> Result<TOTP, AppError> {
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP_SECONDS,
+        secret.to_vec(),
+        Some(TOTP_ISSUER.to_string()),
+        account.to_string(),
+    )
+    .map_err(|e| AppError::Internal(format!("Invalid TOTP secret: {}", e)))
+}
+
+/// The base32 secret for manual entry and the `otpauth://` URI for QR codes.
+pub fn provisioning(secret: &[u8], account: &str) -> Result<(String, String), AppError> {
+    let totp = totp(secret, account)?;
+    Ok((totp.get_secret_base32(), totp.get_url()))
+}
+
+/// Checks `code` against the steps around `unix_time`. Returns the matching step, which must be
+/// newer than `last_used_step` so a code cannot be used twice.
+pub fn verify_code(
+    secret: &[u8],
+    code: &str,
+    unix_time: u64,
+    last_used_step: Option<u64>,
+) -> Result<Option<u64>, AppError> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let totp = totp(secret, "")?;
+    let current_step = (unix_time / TOTP_STEP_SECONDS) as i64;
+    for offset in -ALLOWED_DRIFT_STEPS..=ALLOWED_DRIFT_STEPS {
+        let step = current_step + offset;
+        if step < 0 || last_used_step.is_some_and(|last| step as u64 <= last) {
+            continue;
+        }
+        if totp.check(code, step as u64 * TOTP_STEP_SECONDS) {
+            return Ok(Some(step as u64));
+        }
+    }
+    Ok(None)
+}
+
+/// Generates single-use recovery codes, formatted `XXXXX-XXXXX`.
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw: String = (&mut rng)
+                .sample_iter(&Alphanumeric)
+                .take(RECOVERY_CODE_LEN)
+                .map(|c| char::from(c).to_ascii_uppercase())
+                .collect();
+            format!("{}-{}", &raw[..RECOVERY_CODE_LEN / 2], &raw[RECOVERY_CODE_LEN / 2..])
+        })
+        .collect()
+}
+
+/// Recovery codes are random, so a plain SHA-256 is enough to store them.
+/// Case, spaces and dashes are ignored so codes can be typed loosely.
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B SHA-1 secret
+    const SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_rfc6238_vector_and_drift() {
+        // At T = 59s the 8-digit code is 94287082, so the 6-digit code is 287082
+        assert_eq!(verify_code(SECRET, "287082", 59, None).unwrap(), Some(1));
+        // One step later is still within drift, two steps is not
+        assert_eq!(verify_code(SECRET, "287082", 59 + 30, None).unwrap(), Some(1));
+        assert_eq!(verify_code(SECRET, "287082", 59 + 60, None).unwrap(), None);
+        assert_eq!(verify_code(SECRET, "28708", 59, None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_code_cannot_be_reused() {
+        assert_eq!(verify_code(SECRET, "287082", 59, Some(1)).unwrap(), None);
+        assert_eq!(verify_code(SECRET, "287082", 59, Some(0)).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes.iter().all(|c| c.len() == RECOVERY_CODE_LEN + 1));
+
+        let code = &codes[0];
+        assert_eq!(
+            hash_recovery_code(code),
+            hash_recovery_code(&code.replace('-', " ").to_lowercase())
+        );
+        assert_ne!(hash_recovery_code(&codes[0]), hash_recovery_code(&codes[1]));
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        let (base32, uri) = provisioning(&generate_secret(), "alice").unwrap();
+        assert_eq!(base32.len(), 32);
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains("issuer=Sabi%20Wallet"));
+    }
+}
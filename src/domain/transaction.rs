@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::error::AppError;
+
+/// What a transaction moves money for. Stored as snake_case TEXT in `transactions.tx_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum TransactionType {
+    /// Naira paid in through Paystack, to be credited as sats.
+    FiatDeposit,
+    /// Sats sent out of a wallet over Lightning.
+    BtcWithdrawal,
+    /// Sats zapped to a Nostr user.
+    NostrSend,
+    /// Sats paid into a wallet over Lightning.
+    LightningReceive,
+}
+
+impl TransactionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionType::FiatDeposit => "fiat_deposit",
+            TransactionType::BtcWithdrawal => "btc_withdrawal",
+            TransactionType::NostrSend => "nostr_send",
+            TransactionType::LightningReceive => "lightning_receive",
+        }
+    }
+
+    /// Which way the node's payment goes for this type, to match Breez notifications against.
+    /// `None` for types whose `external_id` is not a payment hash.
+    pub fn lightning_direction(&self) -> Option<PaymentDirection> {
+        match self {
+            TransactionType::FiatDeposit => None,
+            TransactionType::BtcWithdrawal | TransactionType::NostrSend => Some(PaymentDirection::Outgoing),
+            TransactionType::LightningReceive => Some(PaymentDirection::Incoming),
+        }
+    }
+}
+
+impl fmt::Display for TransactionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TransactionType {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fiat_deposit" => Ok(TransactionType::FiatDeposit),
+            "btc_withdrawal" => Ok(TransactionType::BtcWithdrawal),
+            "nostr_send" => Ok(TransactionType::NostrSend),
+            "lightning_receive" => Ok(TransactionType::LightningReceive),
+            other => Err(AppError::BadRequest(format!("Unknown transaction type '{}'", other))),
+        }
+    }
+}
+
+/// Whether a Lightning payment came into or left the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum PaymentDirection {
+    Incoming,
+    Outgoing,
+}
+
+impl PaymentDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentDirection::Incoming => "incoming",
+            PaymentDirection::Outgoing => "outgoing",
+        }
+    }
+}
+
+impl fmt::Display for PaymentDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Where a transaction is in its lifecycle. Stored as snake_case TEXT in `transactions.status`.
+/// Change it only through `transaction_service::transition`, which enforces `can_transition_to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    /// Recorded, waiting for the payment to settle.
+    Pending,
+    /// Held back for an admin to review before it can settle.
+    AdminHold,
+    /// Settled. Final.
+    Completed,
+    /// The payment did not go through. Can still be refunded.
+    Failed,
+    /// The money was returned to the payer. Final.
+    Refunded,
+}
+
+impl TransactionStatus {
+    pub const ALL: [TransactionStatus; 5] = [
+        TransactionStatus::Pending,
+        TransactionStatus::AdminHold,
+        TransactionStatus::Completed,
+        TransactionStatus::Failed,
+        TransactionStatus::Refunded,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionStatus::Pending => "pending",
+            TransactionStatus::AdminHold => "admin_hold",
+            TransactionStatus::Completed => "completed",
+            TransactionStatus::Failed => "failed",
+            TransactionStatus::Refunded => "refunded",
+        }
+    }
+
+    /// The legal moves. Everything not listed here is rejected, including staying in the same status.
+    pub fn can_transition_to(self, next: TransactionStatus) -> bool {
+        use TransactionStatus::*;
+        matches!(
+            (self, next),
+            (Pending, AdminHold | Completed | Failed | Refunded)
+                | (AdminHold, Completed | Failed | Refunded)
+                | (Failed, Refunded)
+        )
+    }
+
+    /// Whether nothing can happen to the transaction any more.
+    pub fn is_final(self) -> bool {
+        Self::ALL.iter().all(|&next| !self.can_transition_to(next))
+    }
+
+    /// Returns `next` if the move is legal, or a `409` naming both statuses.
+    pub fn transition_to(self, next: TransactionStatus) -> Result<TransactionStatus, AppError> {
+        if !self.can_transition_to(next) {
+            return Err(AppError::Conflict(format!(
+                "Transaction cannot move from {} to {}",
+                self, next
+            )));
+        }
+        Ok(next)
+    }
+}
+
+impl fmt::Display for TransactionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TransactionStatus {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown transaction status '{}'", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TransactionStatus::*;
+
+    #[test]
+    fn test_open_transactions_can_settle_fail_or_be_refunded() {
+        for from in [Pending, AdminHold] {
+            for to in [Completed, Failed, Refunded] {
+                assert_eq!(from.transition_to(to).unwrap(), to);
+            }
+        }
+        assert!(Pending.can_transition_to(AdminHold));
+        assert!(Failed.can_transition_to(Refunded));
+    }
+
+    #[test]
+    fn test_illegal_transitions_are_rejected() {
+        assert!(matches!(Completed.transition_to(Failed), Err(AppError::Conflict(_))));
+        assert!(!Completed.can_transition_to(Refunded));
+        assert!(!Failed.can_transition_to(Completed));
+        assert!(!AdminHold.can_transition_to(Pending));
+        for status in TransactionStatus::ALL {
+            assert!(!status.can_transition_to(status));
+        }
+    }
+
+    #[test]
+    fn test_only_completed_and_refunded_are_final() {
+        let finals: Vec<_> = TransactionStatus::ALL.into_iter().filter(|s| s.is_final()).collect();
+        assert_eq!(finals, vec![Completed, Refunded]);
+    }
+
+    #[test]
+    fn test_round_trip_through_strings() {
+        for status in TransactionStatus::ALL {
+            assert_eq!(status.as_str().parse::<TransactionStatus>().unwrap(), status);
+            assert_eq!(serde_json::to_value(status).unwrap(), status.as_str());
+        }
+        for tx_type in [
+            TransactionType::FiatDeposit,
+            TransactionType::BtcWithdrawal,
+            TransactionType::NostrSend,
+            TransactionType::LightningReceive,
+        ] {
+            assert_eq!(tx_type.as_str().parse::<TransactionType>().unwrap(), tx_type);
+            assert_eq!(serde_json::to_value(tx_type).unwrap(), tx_type.as_str());
+        }
+        assert!("PAID".parse::<TransactionStatus>().is_err());
+        assert!("swap".parse::<TransactionType>().is_err());
+    }
+}
@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use crate::{
+    domain::money::{format_fixed, parse_fixed},
+    error::AppError,
+};
+
 /// Represents monetary amounts in Nigerian Kobo (1 Naira = 100 Kobo).
 /// Stored as a 64-bit integer to avoid floating-point inaccuracies.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -10,12 +15,22 @@ pub struct Kobo(pub i64);
 impl Kobo {
     pub const ZERO: Kobo = Kobo(0);
 
-    pub fn from_naira(naira: f64) -> Self {
-        Kobo((naira * 100.0) as i64)
+    /// Parses a Naira amount such as "10.50". More than two decimal places is an error, not a truncation.
+    pub fn from_naira(naira: &str) -> Result<Self, AppError> {
+        parse_fixed(naira, 2).map(Kobo)
+    }
+
+    /// Formats the amount in Naira with exactly two decimal places.
+    pub fn to_naira(&self) -> String {
+        format_fixed(self.0, 2)
     }
 
-    pub fn to_naira(&self) -> f64 {
-        self.0 as f64 / 100.0
+    pub fn checked_add(self, other: Kobo) -> Option<Kobo> {
+        self.0.checked_add(other.0).map(Kobo)
+    }
+
+    pub fn checked_sub(self, other: Kobo) -> Option<Kobo> {
+        self.0.checked_sub(other.0).map(Kobo)
     }
 }
 
@@ -34,12 +49,22 @@ pub struct Sats(pub i64);
 impl Sats {
     pub const ZERO: Sats = Sats(0);
 
-    pub fn from_btc(btc: f64) -> Self {
-        Sats((btc * 100_000_000.0) as i64)
+    /// Parses a BTC amount such as "0.00012345". More than eight decimal places is an error, not a truncation.
+    pub fn from_btc(btc: &str) -> Result<Self, AppError> {
+        parse_fixed(btc, 8).map(Sats)
+    }
+
+    /// Formats the amount in BTC with exactly eight decimal places.
+    pub fn to_btc(&self) -> String {
+        format_fixed(self.0, 8)
     }
 
-    pub fn to_btc(&self) -> f64 {
-        self.0 as f64 / 100_000_000.0
+    pub fn checked_add(self, other: Sats) -> Option<Sats> {
+        self.0.checked_add(other.0).map(Sats)
+    }
+
+    pub fn checked_sub(self, other: Sats) -> Option<Sats> {
+        self.0.checked_sub(other.0).map(Sats)
     }
 }
 
@@ -55,23 +80,36 @@ mod tests {
 
     #[test]
     fn test_kobo_conversion() {
-        let kobo = Kobo::from_naira(10.50);
+        let kobo = Kobo::from_naira("10.50").unwrap();
         assert_eq!(kobo.0, 1050);
-        assert_eq!(kobo.to_naira(), 10.50);
+        assert_eq!(kobo.to_naira(), "10.50");
 
-        let kobo_zero = Kobo::from_naira(0.0);
+        let kobo_zero = Kobo::from_naira("0").unwrap();
         assert_eq!(kobo_zero.0, 0);
-        assert_eq!(kobo_zero.to_naira(), 0.0);
+        assert_eq!(kobo_zero.to_naira(), "0.00");
+
+        // 0.29 * 100.0 is 28.999999999999996 in f64
+        assert_eq!(Kobo::from_naira("0.29").unwrap(), Kobo(29));
+        assert!(Kobo::from_naira("10.505").is_err());
     }
 
     #[test]
     fn test_sats_conversion() {
-        let sats = Sats::from_btc(0.00012345);
+        let sats = Sats::from_btc("0.00012345").unwrap();
         assert_eq!(sats.0, 12345);
-        assert_eq!(sats.to_btc(), 0.00012345);
+        assert_eq!(sats.to_btc(), "0.00012345");
 
-        let sats_zero = Sats::from_btc(0.0);
+        let sats_zero = Sats::from_btc("0").unwrap();
         assert_eq!(sats_zero.0, 0);
-        assert_eq!(sats_zero.to_btc(), 0.0);
+        assert_eq!(sats_zero.to_btc(), "0.00000000");
+
+        assert!(Sats::from_btc("0.000000001").is_err());
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(Sats(1).checked_add(Sats(2)), Some(Sats(3)));
+        assert_eq!(Sats(i64::MAX).checked_add(Sats(1)), None);
+        assert_eq!(Kobo(i64::MIN).checked_sub(Kobo(1)), None);
     }
 }
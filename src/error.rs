@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -51,6 +51,12 @@ pub enum AppError {
     #[error("Conflict: {0}")]
     Conflict(String),
 
+    #[error("Too Many Requests: {message}")]
+    TooManyRequests { message: String, retry_after_seconds: i64 },
+
+    #[error("Service Unavailable: {0}")]
+    ServiceUnavailable(String),
+
     #[error("Internal Server Error: {0}")]
     Internal(String),
 
@@ -95,6 +101,15 @@ impl IntoResponse for AppError {
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
             AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            AppError::TooManyRequests {
+                message,
+                retry_after_seconds,
+            } => {
+                let body = Json(json!({ "error": message }));
+                let retry_after = [(header::RETRY_AFTER, retry_after_seconds.to_string())];
+                return (StatusCode::TOO_MANY_REQUESTS, retry_after, body).into_response();
+            }
+            AppError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
             AppError::Internal(msg) => {
                 error!("Internal Server Error: {}", msg);
                 (StatusCode::INTERNAL_SERVER_ERROR, msg)
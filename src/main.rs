@@ -1,4 +1,5 @@
 use anyhow::Result;
+use clap::Parser;
 use axum::{extract::Request, http::StatusCode, routing::get, Router};
 use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tower_http::{
@@ -16,22 +17,42 @@ mod app_state;
 mod bitcoin;
 mod cli;
 mod config;
+mod crypto;
 mod database;
 mod domain;
 mod error;
+mod middleware;
 mod nostr;
+mod paystack;
+mod rates;
 mod routes;
 mod services;
 mod utils;
 
 use app_state::AppState;
+use cli::{Cli, Commands};
+use crate::bitcoin::{breez::BreezService, in_memory::InMemoryLightning, lightning::LightningBackend};
 use config::Config;
 use database::AnyPool;
+use domain::types::Sats;
+use rates::oracle::RateOracle;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Load configuration
     dotenvy::dotenv().ok();
+    let cli = Cli::parse();
+
+    match cli.command.unwrap_or(Commands::Serve) {
+        Commands::Serve => serve().await,
+        command => {
+            setup_cli_tracing();
+            cli::run_cli_command(command).await
+        }
+    }
+}
+
+async fn serve() -> Result<()> {
+    // Load configuration
     let config = Config::load()?;
 
     // Initialize tracing
@@ -44,14 +65,38 @@ async fn main() -> Result<()> {
 
     // Initialize Database
     let db_pool: AnyPool = database::init_db_pool(&config).await?;
-    sqlx::migrate!("./migrations").run(&db_pool).await?;
-    info!("Database migrations applied successfully.");
+    database::run_migrations(&db_pool).await?;
 
     // Initialize Redis
     let redis_client = database::init_redis_client(&config)?;
 
+    // Initialize the Lightning backend once; services share it through AppState
+    let lightning = init_lightning_backend(&config).await?;
+
+    // BTC/NGN rate oracle over the configured sources
+    let rate_oracle = init_rate_oracle(&config, redis_client.clone())?;
+
+    // Master keys for decrypting stored nsecs
+    let key_provider = crypto::key_provider::from_config(&config)?;
+
     // Build shared application state
-    let app_state = AppState::new(config.clone(), db_pool, redis_client);
+    let app_state = AppState::new(
+        config.clone(),
+        db_pool,
+        redis_client,
+        lightning,
+        rate_oracle,
+        key_provider,
+    );
+
+    // Background processing of stored webhooks
+    services::webhook_service::spawn_worker(app_state.clone());
+    // Expires release requests nobody approved in time
+    services::release_service::spawn_expiry_worker(app_state.clone());
+    // Pays out deposits over Lightning
+    services::payout_service::spawn_worker(app_state.clone());
+    // Sends SMS queued by committed transactions
+    services::sms_service::spawn_worker(app_state.clone());
 
     let app = create_app(app_state)?;
 
@@ -59,7 +104,8 @@ async fn main() -> Result<()> {
     info!("Listening on {}", addr);
 
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        // Peer addresses feed the per-IP admin login throttling
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await?;
 
     Ok(())
@@ -86,6 +132,35 @@ fn create_app(app_state: Arc<AppState>) -> Result<Router> {
     Ok(app)
 }
 
+async fn init_lightning_backend(config: &Config) -> Result<Arc<dyn LightningBackend>> {
+    match config.lightning_backend.as_str() {
+        "memory" => {
+            info!("Using in-memory Lightning backend.");
+            Ok(Arc::new(InMemoryLightning::new(Sats(1_000_000))))
+        }
+        "breez" => {
+            let breez = BreezService::new(&config.breez_api_key, &config.breez_mnemonic).await?;
+            Ok(Arc::new(breez))
+        }
+        other => Err(anyhow::anyhow!("Unknown LIGHTNING_BACKEND: {}", other)),
+    }
+}
+
+fn init_rate_oracle(config: &Config, redis_client: redis::Client) -> Result<Arc<RateOracle>> {
+    let sources = rates::http::sources_from_names(&config.rate_sources)?;
+    if sources.is_empty() {
+        return Err(anyhow::anyhow!("RATE_SOURCES must list at least one rate source"));
+    }
+    info!("Using BTC/NGN rate sources: {:?}", config.rate_sources);
+
+    Ok(Arc::new(RateOracle::new(
+        sources,
+        redis_client,
+        chrono::Duration::seconds(config.rate_refresh_seconds),
+        chrono::Duration::seconds(config.rate_max_age_seconds),
+    )))
+}
+
 fn setup_tracing(config: &Config) {
     let filter_layer = tracing_subscriber::EnvFilter::builder()
         .with_default_directive(Level::INFO.into())
@@ -97,6 +172,18 @@ fn setup_tracing(config: &Config) {
         .init();
 }
 
+/// Human-readable logs for one-off commands; the server logs JSON.
+fn setup_cli_tracing() {
+    let filter_layer = tracing_subscriber::EnvFilter::builder()
+        .with_default_directive(Level::INFO.into())
+        .from_env_lossy();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter_layer)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
 fn setup_sentry(config: &Config) {
     if !config.sentry_dsn.is_empty() {
         sentry::init((
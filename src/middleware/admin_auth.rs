@@ -0,0 +1,143 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    domain::{admin::AdminRole, audit::AuditActor},
+    error::AppError,
+    services::{
+        admin_service::{self, TokenScope},
+        audit_service, session_service,
+    },
+};
+
+/// The admin behind a request, attached by `authenticate`.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedAdmin {
+    pub admin_id: Uuid,
+    pub username: String,
+    pub role: AdminRole,
+    pub request_id: Option<String>,
+    pub session_id: Uuid,
+    pub token_id: Uuid, // `jti` of the access token presented
+    pub token_expires_at: DateTime<Utc>,
+}
+
+impl AuthenticatedAdmin {
+    /// This admin and request, as recorded in the audit log.
+    pub fn actor(&self) -> AuditActor {
+        AuditActor {
+            admin_id: Some(self.admin_id),
+            username: self.username.clone(),
+            request_id: self.request_id.clone(),
+        }
+    }
+
+    /// Fails with `403 Forbidden` unless the admin holds at least `min_role`.
+    pub fn require(&self, min_role: AdminRole) -> Result<(), AppError> {
+        if self.role < min_role {
+            warn!(
+                "Admin {} ({}) denied: requires role {}",
+                self.username, self.role, min_role
+            );
+            return Err(AppError::Forbidden(format!("Requires the {} role", min_role)));
+        }
+        Ok(())
+    }
+}
+
+/// Validates the bearer token issued at `/admin/login`, loads the admin it belongs to and
+/// attaches them to the request. The admin and their session are re-checked on every request,
+/// so deactivating an account, removing its role or revoking the session takes effect immediately.
+pub async fn authenticate(
+    State(app_state): State<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    match load_admin(&app_state, req.headers()).await {
+        Ok(admin) => {
+            req.extensions_mut().insert(admin);
+            next.run(req).await
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Route layer for endpoints that need at least the operator role. Must run after `authenticate`.
+pub async fn require_operator(req: Request, next: Next) -> Response {
+    require_role(AdminRole::Operator, req, next).await
+}
+
+/// Route layer for endpoints that need the superadmin role. Must run after `authenticate`.
+pub async fn require_superadmin(req: Request, next: Next) -> Response {
+    require_role(AdminRole::Superadmin, req, next).await
+}
+
+async fn require_role(min_role: AdminRole, req: Request, next: Next) -> Response {
+    let allowed = match req.extensions().get::<AuthenticatedAdmin>() {
+        Some(admin) => admin.require(min_role),
+        None => Err(AppError::Unauthorized("Authentication required".to_string())),
+    };
+
+    match allowed {
+        Ok(()) => next.run(req).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn load_admin(app_state: &AppState, headers: &HeaderMap) -> Result<AuthenticatedAdmin, AppError> {
+    let token = bearer_token(headers)
+        .ok_or_else(|| AppError::Unauthorized("Missing bearer token".to_string()))?;
+    let claims = admin_service::decode_admin_token(&app_state.config, token, TokenScope::Session)?;
+    let admin_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+    session_service::ensure_not_revoked(&app_state.db_pool, &claims).await?;
+    let session_id = claims
+        .sid
+        .ok_or_else(|| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+    let token_expires_at = DateTime::<Utc>::from_timestamp(claims.exp as i64, 0)
+        .ok_or_else(|| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+
+    let authorized = admin_service::load_authorized_admin(&app_state.db_pool, admin_id).await?;
+    Ok(AuthenticatedAdmin {
+        admin_id,
+        username: authorized.admin.username,
+        role: authorized.role,
+        request_id: audit_service::request_id(headers),
+        session_id,
+        token_id: claims.jti,
+        token_expires_at,
+    })
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthenticatedAdmin {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthenticatedAdmin>()
+            .cloned()
+            .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))
+    }
+}
@@ -0,0 +1,435 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{OriginalUri, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration as ChronoDuration, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::{app_state::AppState, error::AppError, middleware::admin_auth::AuthenticatedAdmin};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const LEGACY_IDEMPOTENCY_KEY_HEADER: &str = "x-idempotency-key";
+const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const IDEMPOTENCY_TTL_SECONDS: u64 = 86_400; // 24 hours
+const IN_PROGRESS_TTL_SECONDS: u64 = 300; // Claim expires if the handler never finishes
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// What is remembered for an Idempotency-Key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IdempotencyRecord {
+    request_hash: String,
+    completed: bool,
+    status_code: Option<u16>,
+    content_type: Option<String>,
+    body_hex: Option<String>,
+}
+
+enum Claim {
+    Acquired,
+    Existing(IdempotencyRecord),
+}
+
+/// Makes mutating requests carrying an `Idempotency-Key` header safe to retry.
+///
+/// The first request with a key runs normally and its response is stored per route and caller.
+/// Retries with the same key and body get the stored response back; the same key with a
+/// different body, or while the first request is still running, gets `409 Conflict`.
+/// Responses are kept in Redis, falling back to the `idempotency_keys` table if Redis is down.
+pub async fn enforce(
+    State(app_state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match handle(&app_state, req, next).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+async fn handle(app_state: &Arc<AppState>, req: Request, next: Next) -> Result<Response, AppError> {
+    if !is_mutating(req.method()) {
+        return Ok(next.run(req).await);
+    }
+
+    let Some(idempotency_key) = idempotency_key(req.headers()) else {
+        return Ok(next.run(req).await);
+    };
+
+    let (parts, body) = req.into_parts();
+    let body = to_bytes(body, MAX_BODY_BYTES)
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Failed to read request body: {}", e)))?;
+
+    let uri = route_uri(&parts);
+    let path_and_query = uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or_else(|| uri.path());
+    let storage_key = storage_key(
+        parts.method.as_str(),
+        uri.path(),
+        &caller_id(&parts),
+        &idempotency_key,
+    );
+    let request_hash = request_hash(parts.method.as_str(), path_and_query, &body);
+
+    match claim(app_state, &storage_key, &request_hash).await? {
+        Claim::Existing(record) => return replay(record, &request_hash),
+        Claim::Acquired => {}
+    }
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(body)))
+        .await;
+
+    // Server errors are not remembered so the client can retry them
+    if response.status().is_server_error() {
+        release(app_state, &storage_key).await;
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to buffer response body: {}", e)))?;
+
+    let record = IdempotencyRecord {
+        request_hash,
+        completed: true,
+        status_code: Some(parts.status.as_u16()),
+        content_type: parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+        body_hex: Some(hex::encode(&body)),
+    };
+    complete(app_state, &storage_key, &record).await;
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+fn is_mutating(method: &Method) -> bool {
+    matches!(*method, Method::POST | Method::PUT | Method::PATCH | Method::DELETE)
+}
+
+fn idempotency_key(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .or_else(|| headers.get(LEGACY_IDEMPOTENCY_KEY_HEADER))
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+/// The full request URI. Under `Router::nest` the request's own URI has the prefix stripped,
+/// so `/quotes` and `/ussd` would both look like `/`.
+fn route_uri(parts: &Parts) -> Uri {
+    parts
+        .extensions
+        .get::<OriginalUri>()
+        .map(|original| original.0.clone())
+        .unwrap_or_else(|| parts.uri.clone())
+}
+
+/// Keys are scoped to whoever is calling, so two callers can never see each other's responses.
+/// Admins are known by their ID rather than their token, which is replaced every few minutes:
+/// a retry after a token refresh must still find the first response.
+fn caller_id(parts: &Parts) -> String {
+    if let Some(admin) = parts.extensions.get::<AuthenticatedAdmin>() {
+        return format!("admin:{}", admin.admin_id);
+    }
+    match parts.headers.get(header::AUTHORIZATION) {
+        Some(auth) => hex::encode(Sha256::digest(auth.as_bytes())),
+        None => "anonymous".to_string(),
+    }
+}
+
+fn storage_key(method: &str, path: &str, caller: &str, idempotency_key: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [method, path, caller, idempotency_key] {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    format!("idempotency:{}", hex::encode(hasher.finalize()))
+}
+
+fn request_hash(method: &str, path_and_query: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(path_and_query.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+fn replay(record: IdempotencyRecord, request_hash: &str) -> Result<Response, AppError> {
+    if record.request_hash != request_hash {
+        return Err(AppError::Conflict(
+            "Idempotency-Key was already used with a different request".to_string(),
+        ));
+    }
+    if !record.completed {
+        return Err(AppError::Conflict(
+            "A request with this Idempotency-Key is still being processed".to_string(),
+        ));
+    }
+
+    let status = record
+        .status_code
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .unwrap_or(StatusCode::OK);
+    let body = record
+        .body_hex
+        .as_deref()
+        .map(hex::decode)
+        .transpose()
+        .map_err(|e| AppError::Internal(format!("Corrupt idempotency record: {}", e)))?
+        .unwrap_or_default();
+
+    info!("Replaying stored response for Idempotency-Key ({})", status);
+
+    let mut response = (status, body).into_response();
+    if let Some(content_type) = record.content_type.and_then(|ct| HeaderValue::from_str(&ct).ok()) {
+        response.headers_mut().insert(header::CONTENT_TYPE, content_type);
+    }
+    response
+        .headers_mut()
+        .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    Ok(response)
+}
+
+// --- Storage: Redis first, database fallback ---
+
+async fn claim(
+    app_state: &Arc<AppState>,
+    storage_key: &str,
+    request_hash: &str,
+) -> Result<Claim, AppError> {
+    let pending = IdempotencyRecord {
+        request_hash: request_hash.to_string(),
+        completed: false,
+        status_code: None,
+        content_type: None,
+        body_hex: None,
+    };
+
+    match claim_redis(app_state, storage_key, &pending).await {
+        Ok(claim) => Ok(claim),
+        Err(e) => {
+            warn!("Redis unavailable for idempotency, falling back to database: {}", e);
+            claim_db(app_state, storage_key, &pending).await
+        }
+    }
+}
+
+async fn claim_redis(
+    app_state: &Arc<AppState>,
+    storage_key: &str,
+    pending: &IdempotencyRecord,
+) -> Result<Claim, AppError> {
+    let mut con = app_state.redis_client.get_async_connection().await?;
+
+    let acquired: Option<String> = redis::cmd("SET")
+        .arg(storage_key)
+        .arg(serde_json::to_string(pending)?)
+        .arg("NX")
+        .arg("EX")
+        .arg(IN_PROGRESS_TTL_SECONDS)
+        .query_async(&mut con)
+        .await?;
+    if acquired.is_some() {
+        return Ok(Claim::Acquired);
+    }
+
+    let existing: Option<String> = con.get(storage_key).await?;
+    match existing {
+        Some(json) => Ok(Claim::Existing(serde_json::from_str(&json)?)),
+        // Expired between SET and GET; treat as a fresh claim
+        None => Ok(Claim::Acquired),
+    }
+}
+
+async fn claim_db(
+    app_state: &Arc<AppState>,
+    storage_key: &str,
+    pending: &IdempotencyRecord,
+) -> Result<Claim, AppError> {
+    sqlx::query("DELETE FROM idempotency_keys WHERE storage_key = $1 AND expires_at < NOW()")
+        .bind(storage_key)
+        .execute(&app_state.db_pool)
+        .await?;
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO idempotency_keys (storage_key, request_hash, completed, expires_at, created_at)
+        VALUES ($1, $2, FALSE, $3, NOW())
+        ON CONFLICT (storage_key) DO NOTHING
+        "#,
+    )
+    .bind(storage_key)
+    .bind(&pending.request_hash)
+    .bind(Utc::now() + ChronoDuration::seconds(IN_PROGRESS_TTL_SECONDS as i64))
+    .execute(&app_state.db_pool)
+    .await?
+    .rows_affected();
+
+    if inserted == 1 {
+        return Ok(Claim::Acquired);
+    }
+
+    let row: (String, bool, Option<i32>, Option<String>, Option<Vec<u8>>) = sqlx::query_as(
+        "SELECT request_hash, completed, status_code, content_type, response_body FROM idempotency_keys WHERE storage_key = $1",
+    )
+    .bind(storage_key)
+    .fetch_one(&app_state.db_pool)
+    .await?;
+
+    Ok(Claim::Existing(IdempotencyRecord {
+        request_hash: row.0,
+        completed: row.1,
+        status_code: row.2.map(|code| code as u16),
+        content_type: row.3,
+        body_hex: row.4.map(hex::encode),
+    }))
+}
+
+async fn complete(app_state: &Arc<AppState>, storage_key: &str, record: &IdempotencyRecord) {
+    let redis_result: Result<(), AppError> = async {
+        let mut con = app_state.redis_client.get_async_connection().await?;
+        let _: () = con
+            .set_ex(storage_key, serde_json::to_string(record)?, IDEMPOTENCY_TTL_SECONDS)
+            .await?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = redis_result {
+        warn!("Failed to store idempotent response in Redis, using database: {}", e);
+        let body = record.body_hex.as_deref().and_then(|b| hex::decode(b).ok());
+        let db_result = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (storage_key, request_hash, completed, status_code, content_type, response_body, expires_at, created_at)
+            VALUES ($1, $2, TRUE, $3, $4, $5, $6, NOW())
+            ON CONFLICT (storage_key) DO UPDATE
+            SET completed = TRUE, status_code = EXCLUDED.status_code, content_type = EXCLUDED.content_type,
+                response_body = EXCLUDED.response_body, expires_at = EXCLUDED.expires_at
+            "#,
+        )
+        .bind(storage_key)
+        .bind(&record.request_hash)
+        .bind(record.status_code.map(i32::from))
+        .bind(&record.content_type)
+        .bind(body)
+        .bind(Utc::now() + ChronoDuration::seconds(IDEMPOTENCY_TTL_SECONDS as i64))
+        .execute(&app_state.db_pool)
+        .await;
+
+        if let Err(e) = db_result {
+            warn!("Failed to store idempotent response: {}", e);
+        }
+    }
+}
+
+async fn release(app_state: &Arc<AppState>, storage_key: &str) {
+    if let Ok(mut con) = app_state.redis_client.get_async_connection().await {
+        let _: Result<(), _> = con.del(storage_key).await;
+    }
+    let _ = sqlx::query("DELETE FROM idempotency_keys WHERE storage_key = $1")
+        .bind(storage_key)
+        .execute(&app_state.db_pool)
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::admin::AdminRole;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_storage_key_is_scoped_by_route_and_caller() {
+        let key = storage_key("POST", "/quotes", "anonymous", "abc");
+
+        assert_eq!(key, storage_key("POST", "/quotes", "anonymous", "abc"));
+        assert_ne!(key, storage_key("POST", "/wallet/create", "anonymous", "abc"));
+        assert_ne!(key, storage_key("POST", "/quotes", "someone-else", "abc"));
+        assert!(key.starts_with("idempotency:"));
+    }
+
+    #[test]
+    fn test_admin_callers_are_keyed_on_admin_id_not_token() {
+        let admin = AuthenticatedAdmin {
+            admin_id: Uuid::new_v4(),
+            username: "alice".to_string(),
+            role: AdminRole::Operator,
+            request_id: None,
+            session_id: Uuid::new_v4(),
+            token_id: Uuid::new_v4(),
+            token_expires_at: Utc::now(),
+        };
+        let parts = |token: &str, admin: Option<&AuthenticatedAdmin>| {
+            let mut builder = axum::http::Request::builder().header(header::AUTHORIZATION, format!("Bearer {}", token));
+            if let Some(admin) = admin {
+                builder = builder.extension(admin.clone());
+            }
+            builder.body(Body::empty()).unwrap().into_parts().0
+        };
+
+        assert_eq!(caller_id(&parts("old", Some(&admin))), caller_id(&parts("refreshed", Some(&admin))));
+        assert_eq!(caller_id(&parts("old", Some(&admin))), format!("admin:{}", admin.admin_id));
+        assert_ne!(caller_id(&parts("old", None)), caller_id(&parts("refreshed", None)));
+    }
+
+    #[test]
+    fn test_route_uri_keeps_the_nest_prefix() {
+        // What a layer inside `.nest("/quotes", ...)` sees for POST /quotes?side=buy
+        let (parts, _) = axum::http::Request::builder()
+            .method(Method::POST)
+            .uri("/?side=buy")
+            .extension(OriginalUri("/quotes?side=buy".parse().unwrap()))
+            .body(Body::empty())
+            .unwrap()
+            .into_parts();
+
+        let uri = route_uri(&parts);
+        assert_eq!(uri.path(), "/quotes");
+        assert_eq!(uri.path_and_query().unwrap().as_str(), "/quotes?side=buy");
+
+        let (parts, _) = axum::http::Request::builder().uri("/wallet/create").body(Body::empty()).unwrap().into_parts();
+        assert_eq!(route_uri(&parts).path(), "/wallet/create");
+    }
+
+    #[test]
+    fn test_replay_rejects_different_body_and_in_flight_requests() {
+        let hash = request_hash("POST", "/wallet/create", br#"{"user_id":"1"}"#);
+        let other = request_hash("POST", "/wallet/create", br#"{"user_id":"2"}"#);
+        let record = IdempotencyRecord {
+            request_hash: hash.clone(),
+            completed: true,
+            status_code: Some(201),
+            content_type: Some("application/json".to_string()),
+            body_hex: Some(hex::encode(br#"{"success":true}"#)),
+        };
+
+        assert!(matches!(replay(record.clone(), &other), Err(AppError::Conflict(_))));
+        assert!(matches!(
+            replay(IdempotencyRecord { completed: false, ..record.clone() }, &hash),
+            Err(AppError::Conflict(_))
+        ));
+
+        let response = replay(record, &hash).unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers().get(REPLAYED_HEADER).unwrap(), "true");
+    }
+}
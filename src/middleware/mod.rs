@@ -0,0 +1,2 @@
+pub mod admin_auth;
+pub mod idempotency;
@@ -0,0 +1,26 @@
+pub mod webhook;
+
+use crate::domain::types::Kobo;
+
+/// What Paystack charges for a NGN transfer of `amount`: ₦10 up to ₦5,000, ₦25 up to ₦50,000, ₦50 above.
+pub fn transfer_fee(amount: Kobo) -> Kobo {
+    match amount.0 {
+        i64::MIN..=500_000 => Kobo(1_000),
+        500_001..=5_000_000 => Kobo(2_500),
+        _ => Kobo(5_000),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_fee_tiers() {
+        assert_eq!(transfer_fee(Kobo(100_000)), Kobo(1_000));
+        assert_eq!(transfer_fee(Kobo(500_000)), Kobo(1_000));
+        assert_eq!(transfer_fee(Kobo(500_001)), Kobo(2_500));
+        assert_eq!(transfer_fee(Kobo(5_000_000)), Kobo(2_500));
+        assert_eq!(transfer_fee(Kobo(5_000_001)), Kobo(5_000));
+    }
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use validator::Validate;
+
+pub const PROVIDER: &str = "paystack";
+
+/// The part every Paystack event shares: enough to store a delivery and route it by `event`.
+#[derive(Debug, Deserialize)]
+pub struct PaystackEventEnvelope {
+    pub event: String, // e.g., "charge.success"
+    pub data: PaystackEventReference,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaystackEventReference {
+    pub reference: String,
+}
+
+impl PaystackEventEnvelope {
+    /// Paystack has no event ID of its own; the event name plus reference is unique per delivery.
+    pub fn event_id(&self) -> String {
+        format!("{}:{}", self.event, self.data.reference)
+    }
+}
+
+/// A `charge.*` event: Naira paid in by a customer.
+#[derive(Debug, Deserialize, Validate)]
+pub struct PaystackWebhookRequest {
+    // Paystack webhook payload details
+    // Example fields, adjust based on actual Paystack webhook structure
+    pub event: String, // e.g., "charge.success"
+    pub data: PaystackTransactionData,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PaystackTransactionData {
+    pub id: u64,
+    pub domain: String,
+    pub status: String, // e.g., "success"
+    pub reference: String,
+    pub amount: u64, // Amount in kobo
+    pub currency: String, // "NGN"
+    pub customer: PaystackCustomer,
+    pub paid_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub metadata: serde_json::Value, // Whatever the client attached at checkout; may be an empty string
+    // Add other relevant fields
+}
+
+impl PaystackTransactionData {
+    /// The signed quote ID the client attached to the charge, if any.
+    pub fn quote_id(&self) -> Option<&str> {
+        self.metadata.get("quote_id")?.as_str()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PaystackCustomer {
+    pub id: u64,
+    pub email: String,
+    pub phone: Option<String>,
+    // Other customer details
+}
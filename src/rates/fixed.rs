@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::{
+    domain::money::ExchangeRate,
+    error::AppError,
+    rates::source::{RateQuote, RateSource},
+};
+
+/// Returns the same rate every time. For local development and tests where no exchange is reachable.
+pub struct FixedRateSource {
+    naira_per_btc: ExchangeRate,
+}
+
+impl FixedRateSource {
+    pub fn new(naira_per_btc: ExchangeRate) -> Self {
+        Self { naira_per_btc }
+    }
+}
+
+#[async_trait]
+impl RateSource for FixedRateSource {
+    fn name(&self) -> &str {
+        "fixed"
+    }
+
+    async fn fetch(&self) -> Result<RateQuote, AppError> {
+        Ok(RateQuote {
+            source: self.name().to_string(),
+            naira_per_btc: self.naira_per_btc,
+            fetched_at: Utc::now(),
+        })
+    }
+}
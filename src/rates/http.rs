@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::Client;
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    domain::money::ExchangeRate,
+    error::AppError,
+    rates::{
+        fixed::FixedRateSource,
+        source::{RateQuote, RateSource},
+    },
+};
+
+const BINANCE_BASE_URL: &str = "https://api.binance.com";
+const LUNO_BASE_URL: &str = "https://api.luno.com";
+const QUIDAX_BASE_URL: &str = "https://www.quidax.com";
+const COINGECKO_BASE_URL: &str = "https://api.coingecko.com";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const DEV_FIXED_RATE_NAIRA: u64 = 150_000_000; // 1 BTC = 150,000,000 Naira
+
+/// A public JSON ticker endpoint. The price is located with a JSON pointer and may be
+/// a number or a numeric string, which covers the exchanges we read from.
+/// Prices are parsed from their decimal text so no precision is lost to `f64`.
+pub struct HttpRateSource {
+    name: &'static str,
+    url: String,
+    price_pointer: &'static str,
+    client: Client,
+}
+
+impl HttpRateSource {
+    pub fn new(name: &'static str, url: String, price_pointer: &'static str, client: Client) -> Self {
+        Self {
+            name,
+            url,
+            price_pointer,
+            client,
+        }
+    }
+
+    pub fn binance(base_url: &str, client: Client) -> Self {
+        Self::new(
+            "binance",
+            format!("{}/api/v3/ticker/price?symbol=BTCNGN", base_url),
+            "/price",
+            client,
+        )
+    }
+
+    pub fn luno(base_url: &str, client: Client) -> Self {
+        Self::new(
+            "luno",
+            format!("{}/api/1/ticker?pair=XBTNGN", base_url),
+            "/last_trade",
+            client,
+        )
+    }
+
+    pub fn quidax(base_url: &str, client: Client) -> Self {
+        Self::new(
+            "quidax",
+            format!("{}/api/v1/markets/tickers/btcngn", base_url),
+            "/data/ticker/last",
+            client,
+        )
+    }
+
+    pub fn coingecko(base_url: &str, client: Client) -> Self {
+        Self::new(
+            "coingecko",
+            format!("{}/api/v3/simple/price?ids=bitcoin&vs_currencies=ngn", base_url),
+            "/bitcoin/ngn",
+            client,
+        )
+    }
+}
+
+#[async_trait]
+impl RateSource for HttpRateSource {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    async fn fetch(&self) -> Result<RateQuote, AppError> {
+        let body: serde_json::Value = self
+            .client
+            .get(&self.url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let price = body.pointer(self.price_pointer).ok_or_else(|| {
+            AppError::Internal(format!(
+                "Rate source '{}' response has no value at {}",
+                self.name, self.price_pointer
+            ))
+        })?;
+
+        let naira_per_btc = match price {
+            serde_json::Value::Number(n) => n.to_string().parse::<ExchangeRate>().ok(),
+            serde_json::Value::String(s) => s.parse::<ExchangeRate>().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            AppError::Internal(format!(
+                "Rate source '{}' returned an invalid price: {}",
+                self.name, price
+            ))
+        })?;
+
+        Ok(RateQuote {
+            source: self.name.to_string(),
+            naira_per_btc,
+            fetched_at: Utc::now(),
+        })
+    }
+}
+
+/// Builds the configured sources by name (see `RATE_SOURCES`).
+pub fn sources_from_names(names: &[String]) -> Result<Vec<Arc<dyn RateSource>>> {
+    let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+
+    names
+        .iter()
+        .map(|name| -> Result<Arc<dyn RateSource>> {
+            let client = client.clone();
+            let source: Arc<dyn RateSource> = match name.as_str() {
+                "binance" => Arc::new(HttpRateSource::binance(BINANCE_BASE_URL, client)),
+                "luno" => Arc::new(HttpRateSource::luno(LUNO_BASE_URL, client)),
+                "quidax" => Arc::new(HttpRateSource::quidax(QUIDAX_BASE_URL, client)),
+                "coingecko" => Arc::new(HttpRateSource::coingecko(COINGECKO_BASE_URL, client)),
+                "fixed" => Arc::new(FixedRateSource::new(ExchangeRate::from_naira_per_btc(
+                    DEV_FIXED_RATE_NAIRA,
+                )?)),
+                other => return Err(anyhow!("Unknown rate source: {}", other)),
+            };
+            Ok(source)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_reads_string_and_number_prices() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/ticker/price"))
+            .and(query_param("symbol", "BTCNGN"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "symbol": "BTCNGN", "price": "151000000.50" })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/simple/price"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "bitcoin": { "ngn": 149500000 } })),
+            )
+            .mount(&server)
+            .await;
+
+        let binance = HttpRateSource::binance(&server.uri(), Client::new());
+        let quote = binance.fetch().await.unwrap();
+        assert_eq!(quote.source, "binance");
+        assert_eq!(quote.naira_per_btc.to_string(), "151000000.5");
+
+        let coingecko = HttpRateSource::coingecko(&server.uri(), Client::new());
+        assert_eq!(
+            coingecko.fetch().await.unwrap().naira_per_btc,
+            ExchangeRate::from_naira_per_btc(149_500_000).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_error_status_and_bad_prices() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/1/ticker"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/markets/tickers/btcngn"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "data": { "ticker": { "last": "0" } } })),
+            )
+            .mount(&server)
+            .await;
+
+        assert!(HttpRateSource::luno(&server.uri(), Client::new()).fetch().await.is_err());
+        assert!(HttpRateSource::quidax(&server.uri(), Client::new()).fetch().await.is_err());
+    }
+}
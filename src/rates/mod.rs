@@ -0,0 +1,4 @@
+pub mod fixed;
+pub mod http;
+pub mod oracle;
+pub mod source;
@@ -0,0 +1,248 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use redis::{AsyncCommands, Client as RedisClient};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+use crate::{
+    domain::money::ExchangeRate,
+    error::AppError,
+    rates::source::{RateQuote, RateSource},
+};
+
+const BTC_NAIRA_RATE_KEY: &str = "btc:naira_rate";
+/// Quotes further than this from the median are treated as outliers and dropped.
+const MAX_DEVIATION_BPS: i128 = 500; // 5%
+
+/// The rate we quote from, together with where it came from and how old it is.
+/// Cached in Redis as a single value so the timestamp always matches the rate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregatedRate {
+    pub naira_per_btc: ExchangeRate,
+    /// Fetch time of the oldest quote that contributed.
+    pub fetched_at: DateTime<Utc>,
+    pub sources: Vec<String>,
+}
+
+/// Aggregates BTC/NGN prices from several sources and caches the result.
+pub struct RateOracle {
+    sources: Vec<Arc<dyn RateSource>>,
+    redis_client: RedisClient,
+    refresh_after: ChronoDuration,
+    max_age: ChronoDuration,
+}
+
+impl RateOracle {
+    /// `refresh_after` is how long a cached rate is served before re-fetching;
+    /// `max_age` is how old a rate may get before the oracle refuses to quote at all.
+    pub fn new(
+        sources: Vec<Arc<dyn RateSource>>,
+        redis_client: RedisClient,
+        refresh_after: ChronoDuration,
+        max_age: ChronoDuration,
+    ) -> Self {
+        Self {
+            sources,
+            redis_client,
+            refresh_after,
+            max_age,
+        }
+    }
+
+    /// Returns the current BTC/NGN rate, refreshing from the sources when the cached value is due.
+    /// If every source fails, a cached rate is still served until it is older than `max_age`.
+    pub async fn current_rate(&self) -> Result<AggregatedRate, AppError> {
+        let cached = self.read_cache().await;
+
+        if let Some(rate) = &cached {
+            if Utc::now() - rate.fetched_at < self.refresh_after {
+                return Ok(rate.clone());
+            }
+        }
+
+        match self.refresh().await {
+            Ok(rate) => {
+                self.write_cache(&rate).await;
+                Ok(rate)
+            }
+            Err(e) => match cached.filter(|rate| Utc::now() - rate.fetched_at <= self.max_age) {
+                Some(rate) => {
+                    warn!("Rate refresh failed, serving cached rate from {}: {}", rate.fetched_at, e);
+                    Ok(rate)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    async fn refresh(&self) -> Result<AggregatedRate, AppError> {
+        let mut fetches = JoinSet::new();
+        for source in &self.sources {
+            let source = source.clone();
+            fetches.spawn(async move {
+                let name = source.name().to_string();
+                (name, source.fetch().await)
+            });
+        }
+
+        let mut quotes = Vec::with_capacity(self.sources.len());
+        while let Some(joined) = fetches.join_next().await {
+            match joined {
+                Ok((_, Ok(quote))) => quotes.push(quote),
+                Ok((name, Err(e))) => warn!("Rate source '{}' failed: {}", name, e),
+                Err(e) => warn!("Rate source task panicked: {}", e),
+            }
+        }
+
+        let rate = aggregate(&quotes, Utc::now(), self.max_age)?;
+        info!(
+            "Aggregated BTC/NGN rate {} from {:?}",
+            rate.naira_per_btc, rate.sources
+        );
+        Ok(rate)
+    }
+
+    async fn read_cache(&self) -> Option<AggregatedRate> {
+        let result: Result<Option<String>, AppError> = async {
+            let mut conn = self.redis_client.get_async_connection().await?;
+            Ok(conn.get(BTC_NAIRA_RATE_KEY).await?)
+        }
+        .await;
+
+        match result {
+            Ok(cached) => cached.and_then(|json| serde_json::from_str(&json).ok()),
+            Err(e) => {
+                warn!("Failed to read cached BTC/NGN rate: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn write_cache(&self, rate: &AggregatedRate) {
+        let result: Result<(), AppError> = async {
+            let mut conn = self.redis_client.get_async_connection().await?;
+            let ttl = self.max_age.num_seconds().max(1) as u64;
+            let _: () = conn
+                .set_ex(BTC_NAIRA_RATE_KEY, serde_json::to_string(rate)?, ttl)
+                .await?;
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            warn!("Failed to cache BTC/NGN rate: {}", e);
+        }
+    }
+}
+
+/// Combines quotes into one rate: stale quotes are discarded, quotes more than
+/// `MAX_DEVIATION_BPS` from the median are rejected, and the median of the rest is used.
+pub fn aggregate(
+    quotes: &[RateQuote],
+    now: DateTime<Utc>,
+    max_age: ChronoDuration,
+) -> Result<AggregatedRate, AppError> {
+    let fresh: Vec<&RateQuote> = quotes
+        .iter()
+        .filter(|q| now - q.fetched_at <= max_age)
+        .collect();
+
+    if fresh.is_empty() {
+        return Err(AppError::ServiceUnavailable(
+            "No fresh BTC/NGN rate is available from any source".to_string(),
+        ));
+    }
+
+    let center = median(fresh.iter().map(|q| q.naira_per_btc).collect());
+    let kept: Vec<&RateQuote> = fresh
+        .into_iter()
+        .filter(|q| q.naira_per_btc.deviation_bps(&center) <= MAX_DEVIATION_BPS)
+        .collect();
+
+    // Happens when two sources disagree and neither can be trusted over the other
+    if kept.is_empty() {
+        return Err(AppError::ServiceUnavailable(
+            "BTC/NGN rate sources disagree too much to quote".to_string(),
+        ));
+    }
+
+    let mut sources: Vec<String> = kept.iter().map(|q| q.source.clone()).collect();
+    sources.sort();
+
+    Ok(AggregatedRate {
+        naira_per_btc: median(kept.iter().map(|q| q.naira_per_btc).collect()),
+        fetched_at: kept.iter().map(|q| q.fetched_at).min().unwrap_or(now),
+        sources,
+    })
+}
+
+fn median(mut values: Vec<ExchangeRate>) -> ExchangeRate {
+    values.sort();
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        values[mid - 1].midpoint(&values[mid])
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(source: &str, naira_per_btc: u64, age_seconds: i64, now: DateTime<Utc>) -> RateQuote {
+        RateQuote {
+            source: source.to_string(),
+            naira_per_btc: ExchangeRate::from_naira_per_btc(naira_per_btc).unwrap(),
+            fetched_at: now - ChronoDuration::seconds(age_seconds),
+        }
+    }
+
+    #[test]
+    fn test_median_with_outlier_rejected() {
+        let now = Utc::now();
+        let quotes = vec![
+            quote("binance", 150_000_000, 10, now),
+            quote("luno", 152_000_000, 20, now),
+            quote("quidax", 151_000_000, 5, now),
+            quote("broken", 90_000_000, 1, now),
+        ];
+
+        let rate = aggregate(&quotes, now, ChronoDuration::minutes(5)).unwrap();
+
+        assert_eq!(rate.naira_per_btc, ExchangeRate::from_naira_per_btc(151_000_000).unwrap());
+        assert_eq!(rate.sources, vec!["binance", "luno", "quidax"]);
+        assert_eq!(rate.fetched_at, now - ChronoDuration::seconds(20));
+    }
+
+    #[test]
+    fn test_stale_quotes_are_ignored_and_refused_when_all_stale() {
+        let now = Utc::now();
+        let quotes = vec![
+            quote("binance", 150_000_000, 30, now),
+            quote("luno", 100_000_000, 600, now),
+        ];
+
+        let rate = aggregate(&quotes, now, ChronoDuration::minutes(5)).unwrap();
+        assert_eq!(rate.sources, vec!["binance"]);
+
+        let all_stale = vec![quote("binance", 150_000_000, 600, now)];
+        assert!(matches!(
+            aggregate(&all_stale, now, ChronoDuration::minutes(5)),
+            Err(AppError::ServiceUnavailable(_))
+        ));
+        assert!(aggregate(&[], now, ChronoDuration::minutes(5)).is_err());
+    }
+
+    #[test]
+    fn test_two_disagreeing_sources_are_refused() {
+        let now = Utc::now();
+        let quotes = vec![
+            quote("binance", 150_000_000, 1, now),
+            quote("luno", 180_000_000, 1, now),
+        ];
+
+        assert!(aggregate(&quotes, now, ChronoDuration::minutes(5)).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{domain::money::ExchangeRate, error::AppError};
+
+/// A single BTC/NGN price observation from one source.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateQuote {
+    pub source: String,
+    pub naira_per_btc: ExchangeRate,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Somewhere a BTC/NGN price can be read from.
+/// The oracle depends on this trait so exchanges can be added, removed or mocked without touching it.
+#[async_trait]
+pub trait RateSource: Send + Sync {
+    /// Short identifier reported to clients (e.g. "binance").
+    fn name(&self) -> &str;
+
+    /// Fetches the current price of one BTC in Naira.
+    async fn fetch(&self) -> Result<RateQuote, AppError>;
+}
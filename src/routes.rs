@@ -1,9 +1,17 @@
-use axum::{routing::post, Router};
+use axum::{
+    extract::State,
+    middleware::{from_fn, from_fn_with_state},
+    routing::post,
+    Json, Router,
+};
 use std::sync::Arc;
 
 use crate::{
-    api::{admin, recovery, ussd, wallet, webhooks},
+    api::{admin, quotes, recovery, ussd, wallet, webhooks},
     app_state::AppState,
+    bitcoin::lightning::NodeInfo,
+    error::AppError,
+    middleware::{admin_auth, idempotency},
 };
 
 pub fn api_router(app_state: Arc<AppState>) -> Router {
@@ -12,15 +20,18 @@ pub fn api_router(app_state: Arc<AppState>) -> Router {
         .nest("/ussd", ussd_routes(app_state.clone()))
         .nest("/recovery", recovery_routes(app_state.clone()))
         .nest("/wallet", wallet_routes(app_state.clone()))
+        .nest("/quotes", quote_routes(app_state.clone()))
         .nest("/admin", admin_routes(app_state.clone()))
         .route("/rates", axum::routing::get(webhooks::get_rates_handler)) // Assuming get_rates_handler is in webhooks for now
         .route("/health/breez", axum::routing::get(health_check_breez)) // Add health check route
+        .with_state(app_state)
 }
 
 fn webhook_routes(app_state: Arc<AppState>) -> Router {
     Router::new()
         .route("/breez", post(webhooks::breez_webhook_handler))
         .route("/paystack", post(webhooks::paystack_webhook_handler))
+        .route_layer(from_fn_with_state(app_state.clone(), idempotency::enforce))
         .with_state(app_state)
 }
 
@@ -31,17 +42,108 @@ fn ussd_routes(app_state: Arc<AppState>) -> Router {
 }
 
 fn recovery_routes(app_state: Arc<AppState>) -> Router {
+    // Recoveries are started by an operator, under /admin/wallets/:id/recovery
     Router::new()
-        .route("/request", post(recovery::request_recovery_handler))
         .route("/submit", post(recovery::submit_share_handler))
+        .route_layer(from_fn_with_state(app_state.clone(), idempotency::enforce))
+        // Retired public endpoint, kept to answer 410 Gone
+        .route("/request", post(recovery::request_recovery_gone_handler))
         .with_state(app_state)
 }
 
 fn admin_routes(app_state: Arc<AppState>) -> Router {
+    // Everything registered before the auth layer requires a valid admin token
     Router::new()
-        .route("/login", post(admin::login_handler))
         .route("/trades", axum::routing::get(admin::get_trades_handler))
-        .route("/manual-release", post(admin::manual_release_handler))
+        .route(
+            "/manual-release",
+            post(admin::manual_release_handler)
+                .route_layer(from_fn_with_state(app_state.clone(), idempotency::enforce))
+                .route_layer(from_fn(admin_auth::require_operator)),
+        )
+        .route(
+            "/release-requests",
+            axum::routing::get(admin::list_release_requests_handler),
+        )
+        .route(
+            "/release-requests/:id/approve",
+            post(admin::approve_release_handler)
+                .route_layer(from_fn_with_state(app_state.clone(), idempotency::enforce))
+                .route_layer(from_fn(admin_auth::require_operator)),
+        )
+        .route(
+            "/release-requests/:id/reject",
+            post(admin::reject_release_handler).route_layer(from_fn(admin_auth::require_operator)),
+        )
+        .route(
+            "/unmatched-payments",
+            axum::routing::get(admin::list_unmatched_payments_handler),
+        )
+        .route(
+            "/unmatched-payments/:id/attribute",
+            post(admin::attribute_unmatched_payment_handler)
+                .route_layer(from_fn_with_state(app_state.clone(), idempotency::enforce))
+                .route_layer(from_fn(admin_auth::require_operator)),
+        )
+        .route(
+            "/unmatched-payments/:id/refund",
+            post(admin::refund_unmatched_payment_handler)
+                .route_layer(from_fn_with_state(app_state.clone(), idempotency::enforce))
+                .route_layer(from_fn(admin_auth::require_operator)),
+        )
+        .route(
+            "/unmatched-payments/:id/dismiss",
+            post(admin::dismiss_unmatched_payment_handler).route_layer(from_fn(admin_auth::require_operator)),
+        )
+        .route(
+            "/wallets/:id/recovery",
+            post(admin::start_recovery_handler)
+                .route_layer(from_fn_with_state(app_state.clone(), idempotency::enforce))
+                .route_layer(from_fn(admin_auth::require_operator)),
+        )
+        .route(
+            "/webhooks/:id/replay",
+            post(admin::replay_webhook_handler).route_layer(from_fn(admin_auth::require_operator)),
+        )
+        .route(
+            "/audit/verify",
+            axum::routing::get(admin::verify_audit_log_handler)
+                .route_layer(from_fn(admin_auth::require_superadmin)),
+        )
+        .route(
+            "/users",
+            axum::routing::get(admin::list_admin_users_handler)
+                .post(admin::create_admin_user_handler)
+                .route_layer(from_fn(admin_auth::require_superadmin)),
+        )
+        .route(
+            "/users/:id/deactivate",
+            post(admin::deactivate_admin_user_handler)
+                .route_layer(from_fn(admin_auth::require_superadmin)),
+        )
+        .route(
+            "/users/:id/reset-password",
+            post(admin::reset_admin_password_handler)
+                .route_layer(from_fn(admin_auth::require_superadmin)),
+        )
+        .route(
+            "/users/:id/role",
+            axum::routing::put(admin::set_admin_role_handler)
+                .route_layer(from_fn(admin_auth::require_superadmin)),
+        )
+        .route(
+            "/users/:id/revoke-sessions",
+            post(admin::revoke_admin_sessions_handler)
+                .route_layer(from_fn(admin_auth::require_superadmin)),
+        )
+        .route("/logout", post(admin::logout_handler))
+        .route("/totp/enroll", post(admin::totp_enroll_handler))
+        .route("/totp/confirm", post(admin::totp_confirm_handler))
+        .route_layer(from_fn_with_state(app_state.clone(), admin_auth::authenticate))
+        .route("/login", post(admin::login_handler))
+        .route("/login/totp", post(admin::login_totp_handler))
+        .route("/login/password", post(admin::login_password_change_handler))
+        .route("/token/refresh", post(admin::refresh_token_handler))
         .with_state(app_state)
 }
 
@@ -49,12 +151,21 @@ fn wallet_routes(app_state: Arc<AppState>) -> Router {
     Router::new()
         .route("/create", post(wallet::create_wallet_handler))
         .route("/:user_id", axum::routing::get(wallet::get_wallet_handler))
+        .route_layer(from_fn_with_state(app_state.clone(), idempotency::enforce))
+        .with_state(app_state)
+}
+
+fn quote_routes(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/", post(quotes::create_quote_handler))
+        .route_layer(from_fn_with_state(app_state.clone(), idempotency::enforce))
         .with_state(app_state)
 }
 
-// Health check endpoint for Breez node
-#[get("/health/breez")] 
-async fn health_check_breez() -> Result<Json<HealthResponse>, Status> {
-    let status = breez_service.check_status().await;
-    Ok(Json(status))
+// Health check endpoint for the Lightning node
+async fn health_check_breez(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<NodeInfo>, AppError> {
+    let node_info = app_state.lightning.node_info().await?;
+    Ok(Json(node_info))
 }
@@ -4,31 +4,82 @@ use argon2::{
     Argon2,
 };
 use chrono::Utc;
-use jsonwebtoken::{encode, EncodingKey, Header};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use once_cell::sync::Lazy;
 use secrecy::{ExposeSecret, SecretString};
-use std::sync::Arc;
+use sqlx::{Any, Transaction as DbTransaction};
+use std::net::IpAddr;
 use tracing::{error, info};
 use uuid::Uuid;
 
 use crate::{
     app_state::AppState,
+    bitcoin::lightning::PaymentInfo,
     config::Config,
     database::AnyPool,
     domain::{
+        admin::AdminRole,
+        audit::{AuditAction, AuditActor, AuditEvent},
         models::{AdminUser, Transaction},
+        transaction::{TransactionStatus, TransactionType},
         types::Sats,
     },
     error::AppError,
-    bitcoin::breez::BreezService,
+    services::{
+        audit_service, lightning_payment_service, login_throttle_service, payout_service,
+        session_service::{self, SessionTokens},
+        totp_service::{self, SecondFactor},
+        transaction_service,
+    },
 };
 
-const JWT_EXPIRATION_SECONDS: usize = 3600; // 1 hour
+const DEFAULT_ADMIN_USERNAME: &str = "admin";
+const PRE_AUTH_EXPIRATION_SECONDS: usize = 300; // 5 minutes to enter the TOTP code
+const PASSWORD_CHANGE_EXPIRATION_SECONDS: usize = 600; // 10 minutes to choose a new password
+
+/// Verified against when the username does not exist, to keep the timing of both cases alike.
+static DUMMY_PASSWORD_HASH: Lazy<String> = Lazy::new(|| {
+    hash_password(&Uuid::new_v4().to_string()).expect("hashing a random password cannot fail")
+});
+
+/// What a token may be used for. Pre-auth tokens only unlock the TOTP step of login,
+/// password-change tokens only the forced password change that follows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    Session,
+    PreAuth,
+    PasswordChange,
+}
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
-struct Claims {
-    sub: String, // Subject (admin user ID)
-    exp: usize,  // Expiration time
-    iat: usize,  // Issued at
+pub struct Claims {
+    pub sub: String, // Subject (admin user ID)
+    pub exp: usize,  // Expiration time
+    pub iat: usize,  // Issued at
+    pub jti: Uuid,   // Token ID, for the revocation denylist
+    #[serde(default)]
+    pub sid: Option<Uuid>, // Session the token belongs to; set on session tokens only
+    pub scope: TokenScope,
+}
+
+/// Result of the password step of admin login.
+#[derive(Debug)]
+pub enum LoginOutcome {
+    /// Every login step passed; a new session was started.
+    Authenticated(SessionTokens),
+    /// TOTP is enabled; this pre-auth token must be exchanged with a code at `/admin/login/totp`.
+    TotpRequired(String),
+    /// The password was set or reset by someone else; this token must be exchanged
+    /// with a new password at `/admin/login/password` before a session is issued.
+    PasswordChangeRequired(String),
+}
+
+/// An active admin together with their role, as loaded for an authenticated request.
+#[derive(Debug, Clone)]
+pub struct AuthorizedAdmin {
+    pub admin: AdminUser,
+    pub role: AdminRole,
 }
 
 /// Hashes a password using Argon2.
@@ -50,157 +101,432 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
         .is_ok())
 }
 
-/// Authenticates an admin user and returns a JWT token if successful.
+/// Creates the `admin` superadmin from `DEFAULT_ADMIN_PASSWORD` if it does not exist yet.
+/// Returns whether it was created. Run via the `init-admin` command, never from the login path.
+pub async fn ensure_default_admin(db_pool: &AnyPool, config: &Config) -> Result<bool, AppError> {
+    let existing: Option<Uuid> = sqlx::query_scalar("SELECT id FROM admin_users WHERE username = $1")
+        .bind(DEFAULT_ADMIN_USERNAME)
+        .fetch_optional(db_pool)
+        .await?;
+    if existing.is_some() {
+        return Ok(false);
+    }
+
+    let default_password_hash = hash_password(config.default_admin_password.expose_secret())?;
+    let new_admin_id = Uuid::new_v4();
+
+    let mut tx = db_pool.begin().await?;
+    sqlx::query!(
+        "INSERT INTO admin_users (id, username, password_hash, is_active, must_change_password, created_at, updated_at) VALUES ($1, $2, $3, TRUE, TRUE, NOW(), NOW())",
+        new_admin_id,
+        DEFAULT_ADMIN_USERNAME,
+        default_password_hash
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("INSERT INTO admin_roles (admin_user_id, role) VALUES ($1, $2)")
+        .bind(new_admin_id)
+        .bind(AdminRole::Superadmin.as_str())
+        .execute(&mut *tx)
+        .await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(&AuditActor::system(), AuditAction::AdminCreate)
+            .target("admin_user", new_admin_id)
+            .after(&serde_json::json!({
+                "username": DEFAULT_ADMIN_USERNAME,
+                "role": AdminRole::Superadmin,
+            })),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("Default admin user '{}' created with default password.", DEFAULT_ADMIN_USERNAME);
+    Ok(true)
+}
+
+/// Checks an admin's password. Starts a session, or returns a pre-auth token if TOTP is enabled
+/// or a password-change token if the password must be replaced first.
+/// Failures are throttled per username and client IP; see `login_throttle_service`.
 pub async fn authenticate_admin_user(
-    db_pool: AnyPool,
-    config: Config,
+    app_state: &AppState,
     username: &str,
     password: &str,
-) -> Result<String, AppError> {
-    // Check if default admin user exists and create if not
-    let mut admin_user: Option<AdminUser> = sqlx::query_as!(
+    client_ip: IpAddr,
+    request_id: Option<String>,
+) -> Result<LoginOutcome, AppError> {
+    let db_pool = &app_state.db_pool;
+    let config = &app_state.config;
+    login_throttle_service::check(app_state, username, client_ip).await?;
+
+    let admin_user: Option<AdminUser> = sqlx::query_as!(
         AdminUser,
-        "SELECT id, username, password_hash, is_active, created_at, updated_at FROM admin_users WHERE username = $1",
+        "SELECT id, username, password_hash, is_active, must_change_password, created_at, updated_at FROM admin_users WHERE username = $1",
         username
     )
-    .fetch_optional(&db_pool)
+    .fetch_optional(db_pool)
     .await?;
 
-    if admin_user.is_none() && username == "admin" {
-        info!("Default admin user not found, creating one.");
-        let default_password_hash = hash_password(config.default_admin_password.expose_secret())?;
-
-        let new_admin_id = Uuid::new_v4();
-        sqlx::query!(
-            "INSERT INTO admin_users (id, username, password_hash, is_active, created_at, updated_at) VALUES ($1, $2, $3, TRUE, NOW(), NOW())",
-            new_admin_id,
-            "admin",
-            default_password_hash
-        )
-        .execute(&db_pool)
-        .await?;
+    // Unknown usernames still pay for a full Argon2 verification, so timing does not reveal which exist
+    let password_hash = admin_user
+        .as_ref()
+        .map_or(DUMMY_PASSWORD_HASH.as_str(), |admin| admin.password_hash.as_str());
+    let password_ok = verify_password(password, password_hash)?;
 
-        admin_user = Some(AdminUser {
-            id: new_admin_id,
-            username: "admin".to_string(),
-            password_hash: default_password_hash,
-            is_active: true,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-        });
-        info!("Default admin user 'admin' created with default password.");
+    let admin = match admin_user {
+        Some(admin) if password_ok => admin,
+        _ => {
+            login_throttle_service::record_failure(app_state, username, client_ip).await;
+            return Err(AppError::Unauthorized("Invalid username or password".to_string()));
+        }
+    };
+
+    if !admin.is_active {
+        return Err(AppError::Forbidden("Admin account is inactive".to_string()));
+    }
+
+    if totp_service::is_enabled(db_pool, admin.id).await? {
+        let pre_auth_token = issue_token(config, admin.id, TokenScope::PreAuth)?;
+        return Ok(LoginOutcome::TotpRequired(pre_auth_token));
     }
 
-    let admin = admin_user
-        .ok_or_else(|| AppError::Unauthorized("Invalid username or password".to_string()))?;
+    login_throttle_service::record_success(app_state, username, client_ip).await;
+    finish_login(db_pool, config, &admin, None, request_id).await
+}
+
+/// Second step of login: exchanges a pre-auth token and a TOTP or recovery code for a session
+/// (or a password-change token, as in `authenticate_admin_user`).
+/// Wrong codes count towards the same lockout as wrong passwords.
+pub async fn complete_totp_login(
+    app_state: &AppState,
+    pre_auth_token: &str,
+    code: &str,
+    client_ip: IpAddr,
+    request_id: Option<String>,
+) -> Result<LoginOutcome, AppError> {
+    let db_pool = &app_state.db_pool;
+    let config = &app_state.config;
+    let claims = decode_admin_token(config, pre_auth_token, TokenScope::PreAuth)?;
+    let admin_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+
+    let admin = load_active_admin(db_pool, admin_id).await?;
+    login_throttle_service::check(app_state, &admin.username, client_ip).await?;
 
-    if !verify_password(password, &admin.password_hash)? {
-        return Err(AppError::Unauthorized("Invalid username or password".to_string()));
+    let second_factor =
+        match totp_service::verify_second_factor(db_pool, app_state.key_provider.as_ref(), admin_id, code).await {
+            Ok(SecondFactor::Totp) => "totp",
+            Ok(SecondFactor::RecoveryCode) => "recovery_code",
+            Err(e @ AppError::Unauthorized(_)) => {
+                login_throttle_service::record_failure(app_state, &admin.username, client_ip).await;
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        };
+
+    login_throttle_service::record_success(app_state, &admin.username, client_ip).await;
+    finish_login(db_pool, config, &admin, Some(second_factor), request_id).await
+}
+
+/// Last step of a forced password change: exchanges the password-change token and a new
+/// password for a new session. The new password must differ from the one being replaced.
+pub async fn change_password_with_token(
+    db_pool: &AnyPool,
+    config: &Config,
+    password_change_token: &str,
+    new_password: &str,
+    request_id: Option<String>,
+) -> Result<SessionTokens, AppError> {
+    let claims = decode_admin_token(config, password_change_token, TokenScope::PasswordChange)?;
+    let admin_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+
+    let admin = load_active_admin(db_pool, admin_id).await?;
+    if !admin.must_change_password {
+        return Err(AppError::Conflict("No password change is pending for this admin".to_string()));
     }
+    if verify_password(new_password, &admin.password_hash)? {
+        return Err(AppError::BadRequest("The new password must differ from the current one".to_string()));
+    }
+
+    let password_hash = hash_password(new_password)?;
+    let actor = AuditActor {
+        admin_id: Some(admin.id),
+        username: admin.username.clone(),
+        request_id,
+    };
+
+    let mut tx = db_pool.begin().await?;
+    sqlx::query(
+        "UPDATE admin_users SET password_hash = $1, must_change_password = FALSE, updated_at = NOW() WHERE id = $2",
+    )
+    .bind(password_hash)
+    .bind(admin.id)
+    .execute(&mut *tx)
+    .await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(&actor, AuditAction::PasswordChange).target("admin_user", admin.id),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("Admin {} changed their password", admin.username);
+    session_service::start(db_pool, config, admin.id).await
+}
+
+/// Starts a session and records the login, unless the admin must first replace their password.
+async fn finish_login(
+    db_pool: &AnyPool,
+    config: &Config,
+    admin: &AdminUser,
+    second_factor: Option<&str>,
+    request_id: Option<String>,
+) -> Result<LoginOutcome, AppError> {
+    if admin.must_change_password {
+        let token = issue_token(config, admin.id, TokenScope::PasswordChange)?;
+        return Ok(LoginOutcome::PasswordChangeRequired(token));
+    }
+
+    let tokens = session_service::start(db_pool, config, admin.id).await?;
+    let actor = AuditActor {
+        admin_id: Some(admin.id),
+        username: admin.username.clone(),
+        request_id,
+    };
+    audit_service::record_now(
+        db_pool,
+        AuditEvent::new(&actor, AuditAction::Login)
+            .target("admin_user", admin.id)
+            .after(&serde_json::json!({ "second_factor": second_factor })),
+    )
+    .await?;
+
+    Ok(LoginOutcome::Authenticated(tokens))
+}
+
+async fn load_active_admin(db_pool: &AnyPool, admin_id: Uuid) -> Result<AdminUser, AppError> {
+    let admin = sqlx::query_as::<_, AdminUser>(
+        "SELECT id, username, password_hash, is_active, must_change_password, created_at, updated_at FROM admin_users WHERE id = $1",
+    )
+    .bind(admin_id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Admin account no longer exists".to_string()))?;
 
     if !admin.is_active {
         return Err(AppError::Forbidden("Admin account is inactive".to_string()));
     }
+    Ok(admin)
+}
+
+/// Issues a short-lived access JWT for a session. Sessions are started and refreshed by `session_service`.
+pub fn issue_access_token(config: &Config, admin_id: Uuid, session_id: Uuid) -> Result<String, AppError> {
+    let ttl = config.admin_access_token_ttl_seconds as usize;
+    encode_token(config, admin_id, TokenScope::Session, Some(session_id), ttl)
+}
 
-    // Generate JWT token
+/// Issues a token for an intermediate login step.
+fn issue_token(config: &Config, admin_id: Uuid, scope: TokenScope) -> Result<String, AppError> {
+    let ttl = match scope {
+        TokenScope::PreAuth => PRE_AUTH_EXPIRATION_SECONDS,
+        TokenScope::PasswordChange => PASSWORD_CHANGE_EXPIRATION_SECONDS,
+        TokenScope::Session => {
+            return Err(AppError::Internal("Session tokens are issued with a session".to_string()))
+        }
+    };
+    encode_token(config, admin_id, scope, None, ttl)
+}
+
+fn encode_token(
+    config: &Config,
+    admin_id: Uuid,
+    scope: TokenScope,
+    session_id: Option<Uuid>,
+    ttl: usize,
+) -> Result<String, AppError> {
     let now = Utc::now().timestamp() as usize;
     let claims = Claims {
-        sub: admin.id.to_string(),
-        exp: now + JWT_EXPIRATION_SECONDS,
+        sub: admin_id.to_string(),
+        exp: now + ttl,
         iat: now,
+        jti: Uuid::new_v4(),
+        sid: session_id,
+        scope,
     };
-    let token = encode(
+    encode(
         &Header::default(),
         &claims,
         &EncodingKey::from_secret(config.app_secret_key.expose_secret().as_bytes()),
     )
-    .map_err(|e| AppError::Internal(format!("Failed to generate JWT token: {}", e)))?;
-
-    Ok(token)
+    .map_err(|e| AppError::Internal(format!("Failed to generate JWT token: {}", e)))
 }
 
-/// Fetches all transactions for admin review.
-pub async fn fetch_all_transactions(db_pool: AnyPool) -> Result<Vec<Transaction>, AppError> {
-    let transactions = sqlx::query_as!(
-        Transaction,
-        r#"SELECT id, wallet_id, tx_type as "tx_type!", amount_sats as "amount_sats!", fee_sats as "fee_sats!", status as "status!", description, external_id, created_at, updated_at FROM transactions ORDER BY created_at DESC"#
+/// Validates the signature, expiry and scope of a token issued by this service.
+pub fn decode_admin_token(config: &Config, token: &str, scope: TokenScope) -> Result<Claims, AppError> {
+    let claims = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(config.app_secret_key.expose_secret().as_bytes()),
+        &Validation::default(),
     )
-    .fetch_all(&db_pool)
-    .await?;
+    .map(|data| data.claims)
+    .map_err(|_| AppError::Unauthorized("Invalid or expired token".to_string()))?;
 
-    Ok(transactions)
+    if claims.scope != scope {
+        return Err(AppError::Unauthorized("Invalid or expired token".to_string()));
+    }
+    Ok(claims)
 }
 
-/// Manually releases funds for a given transaction.
-/// This would typically involve directly interacting with Breez SDK.
-pub async fn manual_release_funds(
-    app_state: Arc<AppState>,
-    transaction_id_str: &str,
-    amount_sats: Sats,
-    recipient_nostr_pubkey: &str, // This might be a Bitcoin address or other identifier in reality
-    notes: Option<&str>,
-) -> Result<(), AppError> {
-    let transaction_id = Uuid::parse_str(transaction_id_str)
-        .map_err(|e| AppError::BadRequest(format!("Invalid transaction ID: {}", e)))?;
-
-    info!(
-        "Admin manual release: Tx ID {}, Amount {} Sats, Recipient {}",
-        transaction_id, amount_sats.0, recipient_nostr_pubkey
-    );
+/// Loads the admin a token was issued to. Deactivated admins, admins without a role and admins
+/// whose password was reset are refused, so access can be withdrawn without waiting for their tokens to expire.
+pub async fn load_authorized_admin(db_pool: &AnyPool, admin_id: Uuid) -> Result<AuthorizedAdmin, AppError> {
+    let admin = load_active_admin(db_pool, admin_id).await?;
+    if admin.must_change_password {
+        return Err(AppError::Forbidden("Password change required; log in again".to_string()));
+    }
 
-    // TODO: Verify transaction status in DB, ensure it's pending manual release
-    let mut tx = app_state.db_pool.begin().await?;
+    let role: Option<String> = sqlx::query_scalar("SELECT role FROM admin_roles WHERE admin_user_id = $1")
+        .bind(admin_id)
+        .fetch_optional(db_pool)
+        .await?;
+    let role = role
+        .ok_or_else(|| AppError::Forbidden("Admin account has no role assigned".to_string()))?
+        .parse::<AdminRole>()
+        .map_err(|_| AppError::Internal(format!("Admin {} has an unknown role", admin_id)))?;
 
-    let existing_transaction: Option<Transaction> = sqlx::query_as!(
-        Transaction,
-        r#"SELECT id, wallet_id, tx_type as "tx_type!", amount_sats as "amount_sats!", fee_sats as "fee_sats!", status as "status!", description, external_id, created_at, updated_at FROM transactions WHERE id = $1"#,
-        transaction_id
-    )
-    .fetch_optional(&mut *tx)
-    .await?;
+    Ok(AuthorizedAdmin { admin, role })
+}
 
-    let mut transaction = existing_transaction
-        .ok_or_else(|| AppError::NotFound(format!("Transaction {} not found", transaction_id)))?;
+/// Fetches a transaction and checks it can be manually released for `amount_sats`. Queued
+/// payouts of a released deposit are cancelled by the payout worker.
+pub async fn releasable_transaction(
+    tx: &mut DbTransaction<'_, Any>,
+    transaction_id: Uuid,
+    amount_sats: Sats,
+) -> Result<Transaction, AppError> {
+    let transaction = transaction_service::lock(tx, transaction_id).await?;
 
-    if transaction.status != "pending" && transaction.status != "admin_hold" {
+    if !transaction.status.can_transition_to(TransactionStatus::Completed) {
         return Err(AppError::BadRequest(format!(
             "Transaction {} is not in a state for manual release (status: {})",
             transaction_id, transaction.status
         )));
     }
+    check_owed(&transaction, amount_sats)?;
 
-    // Initialize BreezService. This would typically be a singleton in AppState for performance.
-    let breez_service = BreezService::new(
-        app_state.config.breez_api_key.expose_secret(),
-        app_state.config.breez_mnemonic.expose_secret(),
-    ).await?;
+    // Releasing while the payout worker or an approved release is mid-payment could pay the deposit twice
+    let payment_in_flight: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS(SELECT 1 FROM payout_jobs WHERE transaction_id = $1 AND status = 'sending')
+            OR EXISTS(SELECT 1 FROM release_requests WHERE transaction_id = $1 AND status = 'executing')
+        "#,
+    )
+    .bind(transaction_id)
+    .fetch_one(&mut **tx)
+    .await?;
+    if payment_in_flight {
+        return Err(AppError::Conflict(format!(
+            "Transaction {} is being paid out right now; try again shortly",
+            transaction_id
+        )));
+    }
+    Ok(transaction)
+}
 
-    // Perform the actual BTC send using Breez SDK
-    // For manual release, the recipient_nostr_pubkey might represent an invoice, an address, or another form.
-    // Assuming for now it's a simple Bitcoin address for the purpose of this scaffold.
-    let payment_info = breez_service.send_payment(amount_sats, recipient_nostr_pubkey).await?;
+/// Only a deposit is owed a payout, and a release pays exactly what it is owed.
+fn check_owed(transaction: &Transaction, amount_sats: Sats) -> Result<(), AppError> {
+    if transaction.tx_type != TransactionType::FiatDeposit {
+        return Err(AppError::BadRequest(format!(
+            "Transaction {} is a {}; only deposits can be manually released",
+            transaction.id, transaction.tx_type
+        )));
+    }
+    if amount_sats != transaction.amount_sats {
+        return Err(AppError::BadRequest(format!(
+            "Transaction {} is owed {}, not {}",
+            transaction.id, transaction.amount_sats, amount_sats
+        )));
+    }
+    Ok(())
+}
 
-    info!(
-        "Breez SDK payment initiated for manual release. Payment ID: {}",
-        payment_info.payment_hash
-    );
+/// Completes a released transaction once its payment went out. The claim booked the deposit as
+/// sent, so this books the routing fee. The audit row is written last, so the audit log's lock is
+/// held as briefly as possible. Used by `release_service` for small and approved releases alike.
+pub async fn record_release(
+    tx: &mut DbTransaction<'_, Any>,
+    actor: &AuditActor,
+    transaction_id: Uuid,
+    amount_sats: Sats,
+    recipient_nostr_pubkey: &str,
+    notes: Option<&str>,
+    payment_info: &PaymentInfo,
+) -> Result<(), AppError> {
+    let mut transaction = transaction_service::lock(tx, transaction_id).await?;
+    let before = transaction.clone();
 
-    // Update transaction status to 'completed' and record external_id
-    transaction.status = "completed".to_string();
-    transaction.external_id = Some(payment_info.payment_hash);
-    transaction.description = notes.map(|s| s.to_string()); // Update notes if provided
+    // Update transaction status to 'completed' and record external_id.
+    // The admin's notes go to the audit log rather than over the transaction's own description.
+    transaction_service::transition(tx, &mut transaction, TransactionStatus::Completed).await?;
+    transaction.external_id = Some(payment_info.payment_hash.clone());
+    sqlx::query("UPDATE transactions SET external_id = $1 WHERE id = $2")
+        .bind(&transaction.external_id)
+        .bind(transaction.id)
+        .execute(&mut **tx)
+        .await?;
 
-    sqlx::query!(
-        "UPDATE transactions SET status = $1, external_id = $2, description = $3, updated_at = NOW() WHERE id = $4",
-        transaction.status,
-        transaction.external_id,
-        transaction.description,
-        transaction.id
+    payout_service::post_payout(tx, "manual_release_fee", &transaction, payment_info.fee_sats, recipient_nostr_pubkey)
+        .await?;
+    lightning_payment_service::claim_early_notification(tx, &payment_info.payment_hash, &mut transaction).await?;
+
+    audit_service::record(
+        tx,
+        AuditEvent::new(actor, AuditAction::ManualRelease)
+            .target("transaction", transaction.id)
+            .before(&before)
+            .after(&serde_json::json!({
+                "transaction": transaction,
+                "amount_sats": amount_sats,
+                "fee_sats": payment_info.fee_sats,
+                "recipient": recipient_nostr_pubkey,
+                "notes": notes,
+            })),
     )
-    .execute(&mut *tx)
     .await?;
 
-    tx.commit().await?;
-
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(tx_type: TransactionType, amount_sats: i64) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            wallet_id: Uuid::new_v4(),
+            tx_type,
+            amount_sats: Sats(amount_sats),
+            fee_sats: Sats::ZERO,
+            status: TransactionStatus::AdminHold,
+            description: None,
+            external_id: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_release_must_pay_exactly_what_a_deposit_is_owed() {
+        let deposit = transaction(TransactionType::FiatDeposit, 150_000);
+
+        assert!(check_owed(&deposit, Sats(150_000)).is_ok());
+        assert!(matches!(check_owed(&deposit, Sats(150_001)), Err(AppError::BadRequest(_))));
+        assert!(matches!(check_owed(&deposit, Sats(1)), Err(AppError::BadRequest(_))));
+        assert!(matches!(
+            check_owed(&transaction(TransactionType::BtcWithdrawal, 150_000), Sats(150_000)),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+}
@@ -0,0 +1,309 @@
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use sqlx::{Any, FromRow, Transaction as DbTransaction};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    database::AnyPool,
+    domain::{
+        admin::AdminRole,
+        audit::{AuditAction, AuditActor, AuditEvent},
+    },
+    error::AppError,
+    services::{admin_service, audit_service, session_service},
+};
+
+const TEMPORARY_PASSWORD_LENGTH: usize = 20;
+
+const ADMIN_ACCOUNT_QUERY: &str = r#"
+    SELECT u.id, u.username, r.role, u.is_active, u.totp_enabled, u.must_change_password, u.created_at, u.updated_at
+    FROM admin_users u
+    LEFT JOIN admin_roles r ON r.admin_user_id = u.id
+"#;
+
+/// An admin account as shown to other admins. Never includes the password hash or TOTP secret.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct AdminAccount {
+    pub id: Uuid,
+    pub username: String,
+    pub role: Option<String>, // None if the role was removed; such admins cannot log in
+    pub is_active: bool,
+    pub totp_enabled: bool,
+    pub must_change_password: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A random password for a new or reset account. It has to be changed at the next login.
+pub fn generate_temporary_password() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TEMPORARY_PASSWORD_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Creates an active admin with `role`. The password is treated as temporary:
+/// the new admin must replace it on first login.
+pub async fn create(
+    db_pool: &AnyPool,
+    actor: &AuditActor,
+    username: &str,
+    password: &str,
+    role: AdminRole,
+) -> Result<AdminAccount, AppError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(AppError::BadRequest("Username cannot be empty".to_string()));
+    }
+    let password_hash = admin_service::hash_password(password)?;
+    let admin_id = Uuid::new_v4();
+
+    let mut tx = db_pool.begin().await?;
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO admin_users (id, username, password_hash, is_active, must_change_password, created_at, updated_at)
+        VALUES ($1, $2, $3, TRUE, TRUE, NOW(), NOW())
+        ON CONFLICT (username) DO NOTHING
+        "#,
+    )
+    .bind(admin_id)
+    .bind(username)
+    .bind(password_hash)
+    .execute(&mut *tx)
+    .await?;
+    if inserted.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!("Admin '{}' already exists", username)));
+    }
+    sqlx::query("INSERT INTO admin_roles (admin_user_id, role) VALUES ($1, $2)")
+        .bind(admin_id)
+        .bind(role.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+    let account = load_in_tx(&mut tx, admin_id).await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::AdminCreate)
+            .target("admin_user", admin_id)
+            .after(&account),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("Admin '{}' created with role {} by {}", username, role, actor.username);
+    Ok(account)
+}
+
+/// Lists every admin account, active or not, by username.
+pub async fn list(db_pool: &AnyPool) -> Result<Vec<AdminAccount>, AppError> {
+    let accounts = sqlx::query_as::<_, AdminAccount>(&format!("{} ORDER BY u.username", ADMIN_ACCOUNT_QUERY))
+        .fetch_all(db_pool)
+        .await?;
+    Ok(accounts)
+}
+
+/// Looks an admin up by username, for the CLI.
+pub async fn find_by_username(db_pool: &AnyPool, username: &str) -> Result<AdminAccount, AppError> {
+    sqlx::query_as::<_, AdminAccount>(&format!("{} WHERE u.username = $1", ADMIN_ACCOUNT_QUERY))
+        .bind(username)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Admin '{}' not found", username)))
+}
+
+/// Deactivates an admin and revokes their sessions. Their tokens stop working on the next request.
+/// Admins cannot deactivate themselves, and the last active superadmin cannot be deactivated.
+pub async fn deactivate(db_pool: &AnyPool, actor: &AuditActor, admin_id: Uuid) -> Result<AdminAccount, AppError> {
+    check_not_self(actor, admin_id, "deactivate")?;
+
+    let mut tx = db_pool.begin().await?;
+    let before = lock_account(&mut tx, admin_id).await?;
+    if !before.is_active {
+        return Err(AppError::Conflict(format!("Admin '{}' is already inactive", before.username)));
+    }
+    if before.role.as_deref() == Some(AdminRole::Superadmin.as_str()) {
+        ensure_other_superadmin(&mut tx, admin_id).await?;
+    }
+
+    sqlx::query("UPDATE admin_users SET is_active = FALSE, updated_at = NOW() WHERE id = $1")
+        .bind(admin_id)
+        .execute(&mut *tx)
+        .await?;
+    session_service::revoke_all(&mut tx, admin_id, "deactivated").await?;
+    let after = load_in_tx(&mut tx, admin_id).await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::AdminDeactivate)
+            .target("admin_user", admin_id)
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("Admin '{}' deactivated by {}", after.username, actor.username);
+    Ok(after)
+}
+
+/// Replaces an admin's password with `new_password` and forces them to choose their own
+/// at the next login. Their current sessions stop working immediately.
+pub async fn reset_password(
+    db_pool: &AnyPool,
+    actor: &AuditActor,
+    admin_id: Uuid,
+    new_password: &str,
+) -> Result<AdminAccount, AppError> {
+    let password_hash = admin_service::hash_password(new_password)?;
+
+    let mut tx = db_pool.begin().await?;
+    let before = lock_account(&mut tx, admin_id).await?;
+    sqlx::query(
+        "UPDATE admin_users SET password_hash = $1, must_change_password = TRUE, updated_at = NOW() WHERE id = $2",
+    )
+    .bind(password_hash)
+    .bind(admin_id)
+    .execute(&mut *tx)
+    .await?;
+    session_service::revoke_all(&mut tx, admin_id, "password_reset").await?;
+    let after = load_in_tx(&mut tx, admin_id).await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::PasswordReset)
+            .target("admin_user", admin_id)
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("Password of admin '{}' reset by {}", after.username, actor.username);
+    Ok(after)
+}
+
+/// Assigns `role` to an admin. Admins cannot change their own role,
+/// and the last active superadmin cannot be demoted.
+pub async fn set_role(
+    db_pool: &AnyPool,
+    actor: &AuditActor,
+    admin_id: Uuid,
+    role: AdminRole,
+) -> Result<AdminAccount, AppError> {
+    check_not_self(actor, admin_id, "change the role of")?;
+
+    let mut tx = db_pool.begin().await?;
+    let before = lock_account(&mut tx, admin_id).await?;
+    if before.role.as_deref() == Some(role.as_str()) {
+        return Ok(before);
+    }
+    if before.role.as_deref() == Some(AdminRole::Superadmin.as_str()) && before.is_active {
+        ensure_other_superadmin(&mut tx, admin_id).await?;
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO admin_roles (admin_user_id, role) VALUES ($1, $2)
+        ON CONFLICT (admin_user_id) DO UPDATE SET role = EXCLUDED.role, updated_at = NOW()
+        "#,
+    )
+    .bind(admin_id)
+    .bind(role.as_str())
+    .execute(&mut *tx)
+    .await?;
+    let after = load_in_tx(&mut tx, admin_id).await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::RoleChange)
+            .target("admin_user", admin_id)
+            .before(&before)
+            .after(&after),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("Admin '{}' is now {} (changed by {})", after.username, role, actor.username);
+    Ok(after)
+}
+
+async fn lock_account(tx: &mut DbTransaction<'_, Any>, admin_id: Uuid) -> Result<AdminAccount, AppError> {
+    sqlx::query("SELECT id FROM admin_users WHERE id = $1 FOR UPDATE")
+        .bind(admin_id)
+        .execute(&mut **tx)
+        .await?;
+    load_in_tx(tx, admin_id).await
+}
+
+async fn load_in_tx(tx: &mut DbTransaction<'_, Any>, admin_id: Uuid) -> Result<AdminAccount, AppError> {
+    sqlx::query_as::<_, AdminAccount>(&format!("{} WHERE u.id = $1", ADMIN_ACCOUNT_QUERY))
+        .bind(admin_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Admin {} not found", admin_id)))
+}
+
+/// Refuses to remove superadmin access from `admin_id` if nobody else would keep it.
+///
+/// Every superadmin role row is locked first, in a fixed order, so two admins removing
+/// each other's access at once are serialized and the second one counts after the first commits.
+async fn ensure_other_superadmin(tx: &mut DbTransaction<'_, Any>, admin_id: Uuid) -> Result<(), AppError> {
+    sqlx::query("SELECT admin_user_id FROM admin_roles WHERE role = 'superadmin' ORDER BY admin_user_id FOR UPDATE")
+        .execute(&mut **tx)
+        .await?;
+
+    let others: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM admin_roles r
+        JOIN admin_users u ON u.id = r.admin_user_id
+        WHERE r.role = 'superadmin' AND u.is_active AND u.id <> $1
+        "#,
+    )
+    .bind(admin_id)
+    .fetch_one(&mut **tx)
+    .await?;
+    if others == 0 {
+        return Err(AppError::Conflict(
+            "At least one active superadmin must remain".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Admins may not lock themselves out; another superadmin (or the CLI) has to do it.
+fn check_not_self(actor: &AuditActor, admin_id: Uuid, action: &str) -> Result<(), AppError> {
+    if actor.admin_id == Some(admin_id) {
+        return Err(AppError::Forbidden(format!("Admins cannot {} their own account", action)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admins_cannot_target_themselves() {
+        let admin_id = Uuid::new_v4();
+        let actor = AuditActor {
+            admin_id: Some(admin_id),
+            username: "alice".to_string(),
+            request_id: None,
+        };
+
+        assert!(matches!(
+            check_not_self(&actor, admin_id, "deactivate"),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(check_not_self(&actor, Uuid::new_v4(), "deactivate").is_ok());
+        assert!(check_not_self(&AuditActor::cli(), admin_id, "deactivate").is_ok());
+    }
+
+    #[test]
+    fn test_temporary_passwords_are_random_and_long_enough() {
+        let password = generate_temporary_password();
+        assert_eq!(password.len(), TEMPORARY_PASSWORD_LENGTH);
+        assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(password, generate_temporary_password());
+    }
+}
@@ -0,0 +1,61 @@
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport,
+    Message, Tokio1Executor,
+};
+use secrecy::ExposeSecret;
+use tracing::{error, info, warn};
+
+use crate::{config::Config, error::AppError};
+
+/// Emails `ADMIN_EMAIL_RECIPIENT`. Without `SMTP_HOST` the alert is only logged.
+pub async fn send_admin_alert(config: &Config, subject: &str, body: &str) -> Result<(), AppError> {
+    let Some(smtp_host) = config.smtp_host.as_deref() else {
+        warn!("SMTP_HOST is not set; admin alert not emailed: {}", subject);
+        return Ok(());
+    };
+
+    let from = config
+        .alert_email_from
+        .parse::<Mailbox>()
+        .map_err(|e| AppError::Internal(format!("Invalid ALERT_EMAIL_FROM: {}", e)))?;
+    let to = config
+        .admin_email_recipient
+        .parse::<Mailbox>()
+        .map_err(|e| AppError::Internal(format!("Invalid ADMIN_EMAIL_RECIPIENT: {}", e)))?;
+    let email = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(format!("[Sabi Wallet] {}", subject))
+        .body(body.to_string())
+        .map_err(|e| AppError::Internal(format!("Failed to build alert email: {}", e)))?;
+
+    let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp_host)
+        .map_err(|e| AppError::Internal(format!("Invalid SMTP host {}: {}", smtp_host, e)))?;
+    if let Some(port) = config.smtp_port {
+        transport = transport.port(port);
+    }
+    if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+        transport = transport.credentials(Credentials::new(
+            username.expose_secret().clone(),
+            password.expose_secret().clone(),
+        ));
+    }
+
+    transport
+        .build()
+        .send(email)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to send alert email: {}", e)))?;
+
+    info!("Admin alert emailed: {}", subject);
+    Ok(())
+}
+
+/// Sends an admin alert in the background, so a slow mail server never delays the request that raised it.
+pub fn spawn_admin_alert(config: Config, subject: String, body: String) {
+    tokio::spawn(async move {
+        if let Err(e) = send_admin_alert(&config, &subject, &body).await {
+            error!("Failed to send admin alert '{}': {:?}", subject, e);
+        }
+    });
+}
@@ -0,0 +1,147 @@
+use axum::http::HeaderMap;
+use chrono::{SubsecRound, Utc};
+use serde::Serialize;
+use sqlx::{Any, Transaction as DbTransaction};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    database::AnyPool,
+    domain::{
+        audit::{self, AuditEvent, GENESIS_HASH},
+        models::AdminAuditLogEntry,
+    },
+    error::AppError,
+};
+
+/// Serializes appends so two writers can never chain onto the same previous row.
+const AUDIT_LOG_LOCK_ID: i64 = 0x5ab1_a0d1;
+const VERIFY_BATCH_SIZE: i64 = 1000;
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+const AUDIT_LOG_COLUMNS: &str = "seq, id, actor_admin_id, actor_username, action, target_type, target_id, before, after, request_id, prev_hash, hash, created_at";
+
+/// Outcome of checking the whole audit log.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainVerification {
+    pub valid: bool,
+    pub entries_checked: i64,
+    pub broken_at_seq: Option<i64>,
+    pub reason: Option<String>,
+}
+
+/// Appends an event inside the caller's transaction, so it is only recorded if the action commits.
+pub async fn record(tx: &mut DbTransaction<'_, Any>, event: AuditEvent) -> Result<AdminAuditLogEntry, AppError> {
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(AUDIT_LOG_LOCK_ID)
+        .execute(&mut **tx)
+        .await?;
+
+    let last: Option<(i64, String)> =
+        sqlx::query_as("SELECT seq, hash FROM admin_audit_log ORDER BY seq DESC LIMIT 1")
+            .fetch_optional(&mut **tx)
+            .await?;
+    let (prev_seq, prev_hash) = last.unwrap_or_else(|| (0, GENESIS_HASH.to_string()));
+
+    let mut entry = AdminAuditLogEntry {
+        seq: prev_seq + 1,
+        id: Uuid::new_v4(),
+        actor_admin_id: event.actor.admin_id,
+        actor_username: event.actor.username,
+        action: event.action.as_str().to_string(),
+        target_type: event.target_type,
+        target_id: event.target_id,
+        before: event.before,
+        after: event.after,
+        request_id: event.actor.request_id,
+        prev_hash,
+        hash: String::new(),
+        // Postgres keeps microseconds; truncate so the hash matches what is read back
+        created_at: Utc::now().trunc_subsecs(6),
+    };
+    entry.hash = audit::entry_hash(&entry);
+
+    sqlx::query(&format!(
+        "INSERT INTO admin_audit_log ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+        AUDIT_LOG_COLUMNS
+    ))
+    .bind(entry.seq)
+    .bind(entry.id)
+    .bind(entry.actor_admin_id)
+    .bind(&entry.actor_username)
+    .bind(&entry.action)
+    .bind(&entry.target_type)
+    .bind(&entry.target_id)
+    .bind(&entry.before)
+    .bind(&entry.after)
+    .bind(&entry.request_id)
+    .bind(&entry.prev_hash)
+    .bind(&entry.hash)
+    .bind(entry.created_at)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(entry)
+}
+
+/// Appends an event that is not part of a larger database transaction.
+pub async fn record_now(db_pool: &AnyPool, event: AuditEvent) -> Result<AdminAuditLogEntry, AppError> {
+    let mut tx = db_pool.begin().await?;
+    let entry = record(&mut tx, event).await?;
+    tx.commit().await?;
+    Ok(entry)
+}
+
+/// Walks the whole chain in order and reports the first row that fails verification.
+pub async fn verify_chain(db_pool: &AnyPool) -> Result<ChainVerification, AppError> {
+    let mut prev_seq = 0;
+    let mut prev_hash = GENESIS_HASH.to_string();
+
+    loop {
+        let batch = sqlx::query_as::<_, AdminAuditLogEntry>(&format!(
+            "SELECT {} FROM admin_audit_log WHERE seq > $1 ORDER BY seq ASC LIMIT $2",
+            AUDIT_LOG_COLUMNS
+        ))
+        .bind(prev_seq)
+        .bind(VERIFY_BATCH_SIZE)
+        .fetch_all(db_pool)
+        .await?;
+
+        if batch.is_empty() {
+            info!("Admin audit log verified: {} entries", prev_seq);
+            return Ok(ChainVerification {
+                valid: true,
+                entries_checked: prev_seq,
+                broken_at_seq: None,
+                reason: None,
+            });
+        }
+
+        match audit::verify_chain(&batch, prev_seq, prev_hash) {
+            Ok((seq, hash)) => {
+                prev_seq = seq;
+                prev_hash = hash;
+            }
+            Err(broken) => {
+                error!(
+                    "Admin audit log chain broken at seq {}: {}",
+                    broken.seq, broken.reason
+                );
+                return Ok(ChainVerification {
+                    valid: false,
+                    entries_checked: broken.seq - 1,
+                    broken_at_seq: Some(broken.seq),
+                    reason: Some(broken.reason),
+                });
+            }
+        }
+    }
+}
+
+/// The request ID set by the request-id layer, for correlating audit rows with logs.
+pub fn request_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
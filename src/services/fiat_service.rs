@@ -1,134 +1,110 @@
-use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
-use redis::{AsyncCommands, Client as RedisClient};
-use std::sync::Arc;
-use tracing::{error, info};
-use uuid::Uuid;
+use sqlx::{Any, Transaction as DbTransaction};
+use tracing::{error, info, warn};
 
 use crate::{
-    database::AnyPool,
+    app_state::AppState,
     domain::{
-        models::{FiatOnrampWebhook, Transaction, Wallet},
+        money::ExchangeRate,
+        transaction::{TransactionStatus, TransactionType},
         types::{Kobo, Sats},
     },
     error::AppError,
+    paystack::webhook::{PaystackEventEnvelope, PaystackWebhookRequest},
+    services::{
+        alert_service, onboarding_service, payout_service,
+        quote_service::{self, TradeAmount},
+        sms_service, transaction_service,
+    },
     utils::phone_number::NigerianPhoneNumber,
 };
 
-const BTC_NAIRA_RATE_KEY: &str = "btc:naira_rate";
-const BTC_NAIRA_RATE_TTL_SECONDS: usize = 300; // 5 minutes
-
-/// Processes a payment confirmation from Breez SDK.
-/// Marks the corresponding transaction as complete and updates wallet balances.
-pub async fn process_breez_payment(
-    db_pool: AnyPool,
-    payment_hash: String,
-    amount_sats: Sats,
-    fee_sats: Sats,
-    status: String,
+/// Handles a stored Paystack webhook inside the webhook pipeline's database transaction.
+/// `current_rate` was fetched before that transaction began.
+pub async fn handle_paystack_event(
+    tx: &mut DbTransaction<'_, Any>,
+    app_state: &AppState,
+    raw_body: &[u8],
+    current_rate: Result<ExchangeRate, AppError>,
 ) -> Result<(), AppError> {
-    info!(
-        "Processing Breez payment for hash: {}, amount: {} sats, status: {}",
-        payment_hash, amount_sats.0, status
-    );
-
-    // TODO: Look up the original transaction in the DB using payment_hash
-    // Update its status based on the Breez webhook status (e.g., 'completed', 'failed')
-    // If successful, update the user's wallet balance.
-    // This is a placeholder for actual database operations.
-
-    // Example: Find transaction by external_id (payment_hash) and update
-    let mut tx = db_pool.begin().await?;
-
-    let existing_transaction: Option<Transaction> = sqlx::query_as!(
-        Transaction,
-        r#"SELECT id, wallet_id, tx_type as "tx_type!", amount_sats as "amount_sats!", fee_sats as "fee_sats!", status as "status!", description, external_id, created_at, updated_at FROM transactions WHERE external_id = $1"#,
-        payment_hash
-    )
-    .fetch_optional(&mut *tx)
-    .await?;
-
-    if let Some(mut transaction) = existing_transaction {
-        // Prevent reprocessing
-        if transaction.status == "completed" {
-            info!("Transaction {} already completed.", transaction.id);
-            tx.commit().await?;
-            return Ok(());
-        }
-
-        if status == "PAID" {
-            transaction.status = "completed".to_string();
-            // Update transaction status
-            sqlx::query!(
-                "UPDATE transactions SET status = $1, amount_sats = $2, fee_sats = $3, updated_at = NOW() WHERE id = $4",
-                transaction.status,
-                amount_sats.0,
-                fee_sats.0,
-                transaction.id
+    let envelope: PaystackEventEnvelope = serde_json::from_slice(raw_body)?;
+
+    match envelope.event.as_str() {
+        "charge.success" => {
+            let request: PaystackWebhookRequest = serde_json::from_slice(raw_body)?;
+            if request.data.status != "success" {
+                info!(
+                    "Paystack charge {} has status '{}'. Ignoring.",
+                    request.data.reference, request.data.status
+                );
+                return Ok(());
+            }
+            let quote = request.data.quote_id().map(|quote_id| DepositQuote {
+                quote_id: quote_id.to_string(),
+                paid_at: request.data.paid_at.unwrap_or_else(Utc::now),
+            });
+            process_paystack_deposit(
+                tx,
+                app_state,
+                request.data.reference,
+                Kobo(request.data.amount as i64),
+                request.data.customer.phone,
+                request.data.customer.email,
+                quote,
+                current_rate,
             )
-            .execute(&mut *tx)
-            .await?;
-
-            // Update wallet balance (add amount, subtract fee)
-            sqlx::query!(
-                "UPDATE wallets SET balance_sats = balance_sats + $1 - $2, updated_at = NOW() WHERE id = $3",
-                amount_sats.0,
-                fee_sats.0,
-                transaction.wallet_id
-            )
-            .execute(&mut *tx)
-            .await?;
-            info!("Breez payment {} completed. Wallet updated.", payment_hash);
-        } else {
-            transaction.status = "failed".to_string();
-            sqlx::query!(
-                "UPDATE transactions SET status = $1, updated_at = NOW() WHERE id = $2",
-                transaction.status,
-                transaction.id
-            )
-            .execute(&mut *tx)
-            .await?;
-            error!("Breez payment {} failed with status: {}", payment_hash, status);
+            .await
+        }
+        other => {
+            info!("Paystack event '{}' is not eligible for processing. Ignoring.", other);
+            Ok(())
         }
-    } else {
-        error!("No matching transaction found for Breez payment hash: {}", payment_hash);
-        // Potentially create a new transaction with status 'unknown' or 'unmatched'
     }
+}
 
-    tx.commit().await?;
-
-    Ok(())
+/// A deposit's claim to a locked price: the quote the client attached to the charge, and when the customer paid.
+pub struct DepositQuote {
+    pub quote_id: String,
+    pub paid_at: DateTime<Utc>,
 }
 
-/// Processes a Paystack deposit, records the webhook, and initiates a BTC send.
+/// Processes a Paystack deposit: records the BTC transaction and credits it to the payer's wallet,
+/// queueing its payout if the wallet forwards deposits to a payout destination.
+/// Payers without an account get a user and wallet, and an SMS explaining how to reach it by USSD.
+/// A deposit paid before its quote expired is priced at the quote. Otherwise it is re-quoted, and
+/// put on `admin_hold` instead of paid out if that is more than `QUOTE_SLIPPAGE_BPS` worse than the quote.
+/// Runs inside the caller's database transaction so the webhook is marked processed atomically.
 pub async fn process_paystack_deposit(
-    db_pool: AnyPool,
-    redis_client: RedisClient,
+    tx: &mut DbTransaction<'_, Any>,
+    app_state: &AppState,
     reference: String,
     amount_kobo: Kobo,
     phone_number: Option<String>,
     _customer_email: String,
+    quote: Option<DepositQuote>,
+    current_rate: Result<ExchangeRate, AppError>,
 ) -> Result<(), AppError> {
     info!(
         "Processing Paystack deposit for reference: {}, amount: {} Kobo",
         reference, amount_kobo.0
     );
 
-    // 1. Record the incoming webhook for audit and idempotency.
-    let webhook_id = Uuid::new_v4();
-    sqlx::query!(
-        r#"INSERT INTO fiat_onramp_webhooks (id, provider, event_id, payload, processed, created_at)
-        VALUES ($1, $2, $3, $4, $5, NOW())"#,
-        webhook_id,
-        "paystack",
-        reference,
-        serde_json::to_value(&reference).unwrap(), // Placeholder payload, use actual JSON from webhook
-        false
+    // 1. Replays must not credit the same deposit twice.
+    let already_recorded: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM transactions WHERE tx_type = $1 AND external_id = $2)",
     )
-    .execute(&db_pool)
+    .bind(TransactionType::FiatDeposit)
+    .bind(&reference)
+    .fetch_one(&mut **tx)
     .await?;
 
+    if already_recorded {
+        info!("Paystack deposit {} already recorded. Skipping.", reference);
+        return Ok(());
+    }
+
     // 2. Lookup or create user and wallet based on phone number.
+    // The webhook signature was checked on receipt, so the payer is who Paystack says.
     let canonical_phone = NigerianPhoneNumber::new(
         phone_number
             .as_ref()
@@ -136,110 +112,94 @@ pub async fn process_paystack_deposit(
     )
     .map_err(|e| AppError::BadRequest(format!("Invalid phone number from Paystack: {}", e)))?;
 
-    let user_id = sqlx::query_scalar!(
-        "SELECT id FROM users WHERE phone_number = $1",
-        canonical_phone.as_str()
+    let payer = onboarding_service::find_or_provision_wallet(tx, app_state.key_provider.as_ref(), &canonical_phone).await?;
+
+    // 3. Price the deposit. The money is already in, so a quote we cannot use is only logged.
+    let locked_quote = match &quote {
+        Some(quote) => match quote_service::lock(tx, app_state, &quote.quote_id).await {
+            Ok(locked) => Some(locked),
+            Err(e) => {
+                warn!("Ignoring the quote on Paystack deposit {}: {}", reference, e);
+                None
+            }
+        },
+        None => None,
+    };
+    let execution = quote_service::execute(
+        tx,
+        app_state,
+        TradeAmount::Buy(amount_kobo),
+        locked_quote.as_ref(),
+        quote.as_ref().map_or_else(Utc::now, |quote| quote.paid_at),
+        &reference,
+        current_rate,
     )
-    .fetch_optional(&db_pool)
-    .await?
-    .unwrap_or_else(|| {
-        // TODO: Create new user and wallet if not found.
-        // For now, let's error out to make sure we don't proceed without a user.
-        // This is a simplified flow. Real world requires user creation, nostr key gen, breez wallet init.
-        error!("User not found for phone number: {}", canonical_phone.as_str());
-        Uuid::new_v4() // Placeholder
-    });
-
-    let wallet: Wallet = sqlx::query_as!(
-        Wallet,
-        r#"SELECT id, user_id, nostr_npub, breez_wallet_id, balance_sats as "balance_sats!", created_at, updated_at FROM wallets WHERE user_id = $1"#,
-        user_id
-    )
-    .fetch_one(&db_pool)
-    .await
-    .map_err(|e| AppError::Internal(format!("Failed to retrieve wallet for user {}: {}", user_id, e)))?;
-
-    // 3. Get current Naira-to-BTC rate.
-    let (btc_naira_rate, _) = get_cached_btc_naira_rate(redis_client).await?;
-    let btc_amount_sats = Sats((((amount_kobo.0 as f64 / 100.0) / btc_naira_rate) * 100_000_000.0) as i64);
+    .await?;
+    let btc_amount_sats = execution.terms.amount_sats;
 
     info!(
-        "Converted {} Kobo to {} Sats using rate {}",
-        amount_kobo.0, btc_amount_sats, btc_naira_rate
+        "Converted {} Kobo to {} Sats using rate {} ({})",
+        amount_kobo.0,
+        btc_amount_sats,
+        execution.terms.rate,
+        if execution.quote_id.is_some() { "quoted" } else { "current" }
     );
 
-    // 4. Record pending BTC transaction.
-    let transaction_id = Uuid::new_v4();
-    sqlx::query!(
-        r#"INSERT INTO transactions (id, wallet_id, tx_type, amount_sats, fee_sats, status, description, external_id, created_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())"#,
-        transaction_id,
-        wallet.id,
-        "fiat_deposit",
+    // 4. Record the BTC transaction and credit the wallet, unless it needs review.
+    let mut transaction = transaction_service::create(
+        tx,
+        payer.wallet_id,
+        TransactionType::FiatDeposit,
         btc_amount_sats,
-        0, // Fees handled by Breez SDK for the actual send
-        "pending",
-        Some("Paystack Naira deposit for BTC".to_string()),
-        Some(reference.clone())
+        Sats::ZERO, // Fees handled by Breez SDK for the actual send
+        Some("Paystack Naira deposit for BTC"),
+        Some(&reference),
     )
-    .execute(&db_pool)
     .await?;
+    if execution.slippage_exceeded {
+        // The customer paid expecting more sats than the current rate gives; a person decides
+        transaction_service::transition(tx, &mut transaction, TransactionStatus::AdminHold).await?;
+        error!(
+            "Deposit {} put on admin hold: re-quote is more than {} bps worse than its quote",
+            reference, app_state.config.quote_slippage_bps
+        );
+        alert_service::spawn_admin_alert(
+            app_state.config.clone(),
+            "Deposit held: price moved past the quote".to_string(),
+            format!(
+                "Paystack deposit {} of {} could not be priced at its quote, and the current rate {} gives {} \
+                 Sats, more than {} bps worse than quoted. It is on admin hold (transaction {}): release it \
+                 manually or refund it.",
+                reference,
+                amount_kobo,
+                execution.terms.rate,
+                btc_amount_sats.0,
+                app_state.config.quote_slippage_bps,
+                transaction.id
+            ),
+        );
+    } else {
+        payout_service::credit_deposit(tx, &mut transaction).await?;
+    }
+
+    // 5. New users learn about their wallet by SMS, sent once this transaction commits.
+    if payer.provisioned {
+        sms_service::queue(
+            tx,
+            &canonical_phone,
+            &onboarding_service::welcome_message(
+                amount_kobo,
+                !execution.slippage_exceeded,
+                &app_state.config.ussd_service_code,
+            ),
+        )
+        .await?;
+    }
 
-    // 5. Trigger BTC send via Breez SDK (this part would typically be asynchronous/queued)
-    // For now, we'll just log and assume this happens via Breez SDK's payment confirmation webhook
-    // once the funds are available.
     info!(
-        "Fiat deposit processed. Initiating BTC transfer of {} Sats to wallet: {}. Reference: {}",
-        btc_amount_sats, wallet.id, reference
+        "Fiat deposit processed: {} Sats for wallet {} ({}). Reference: {}",
+        btc_amount_sats, payer.wallet_id, transaction.status, reference
     );
 
-    // TODO: Call Breez SDK to create a payment to the user's Breez wallet for `btc_amount_sats`.
-    // The Breez SDK webhook will then confirm this.
-    // breez_sdk::send_payment(...)
-
-    // Update webhook status to processed
-    sqlx::query!(
-        "UPDATE fiat_onramp_webhooks SET processed = TRUE WHERE id = $1",
-        webhook_id
-    )
-    .execute(&db_pool)
-    .await?;
-
     Ok(())
 }
-
-/// Fetches the cached BTC to Naira rate from Redis, or retrieves it from external sources if expired.
-pub async fn get_cached_btc_naira_rate(redis_client: RedisClient) -> Result<(f64, DateTime<Utc>), AppError> {
-    let mut conn = redis_client.get_async_connection().await?;
-
-    let cached_rate_str: Option<String> = conn.get(BTC_NAIRA_RATE_KEY).await?;
-
-    if let Some(rate_str) = cached_rate_str {
-        if let Ok(rate_value) = rate_str.parse::<f64>() {
-            // Redis doesn't store TTL directly, assume if present it's fresh enough or fetch from source
-            // For a real system, you'd store (rate, timestamp) together.
-            info!("Using cached BTC/Naira rate: {}", rate_value);
-            return Ok((rate_value, Utc::now())); // Placeholder for actual timestamp
-        }
-    }
-
-    info!("BTC/Naira rate not cached or expired, fetching from external sources.");
-    // Placeholder: Fetch from a mock external source
-    let external_rate = fetch_btc_naira_from_external_source().await?;
-    let now = Utc::now();
-
-    // Cache the new rate
-    let _: () = conn.set_ex(BTC_NAIRA_RATE_KEY, external_rate.to_string(), BTC_NAIRA_RATE_TTL_SECONDS).await?;
-    info!("Cached new BTC/Naira rate: {}", external_rate);
-
-    Ok((external_rate, now))
-}
-
-/// Mock function to fetch BTC to Naira rate from an external API.
-async fn fetch_btc_naira_from_external_source() -> Result<f64> {
-    // In a real application, this would call multiple exchanges and aggregate.
-    // For now, return a static value.
-    info!("Fetching BTC/Naira rate from mock external source.");
-    tokio::time::sleep(std::time::Duration::from_millis(500)).await; // Simulate API call
-    Ok(150_000_000.0) // Example: 1 BTC = 150,000,000 Naira
-}
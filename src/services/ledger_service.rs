@@ -0,0 +1,151 @@
+use sqlx::{Any, Executor, Transaction as DbTransaction};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        ledger::{AccountRef, JournalEntry},
+        types::Sats,
+    },
+    error::AppError,
+};
+
+/// Creates the ledger account backing a wallet, or returns the existing one.
+pub async fn ensure_wallet_account(
+    tx: &mut DbTransaction<'_, Any>,
+    wallet_id: Uuid,
+) -> Result<Uuid, AppError> {
+    let account_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO ledger_accounts (id, code, kind, wallet_id, created_at)
+        VALUES ($1, $2, 'wallet', $3, NOW())
+        ON CONFLICT (code) DO UPDATE SET code = EXCLUDED.code
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(format!("wallet:{}", wallet_id))
+    .bind(wallet_id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(account_id)
+}
+
+/// Returns a wallet's balance as the sum of its postings.
+pub async fn wallet_balance<'e, E>(executor: E, wallet_id: Uuid) -> Result<Sats, AppError>
+where
+    E: Executor<'e, Database = Any>,
+{
+    let balance: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(SUM(lp.amount_sats), 0)::BIGINT
+        FROM ledger_postings lp
+        JOIN ledger_accounts la ON la.id = lp.account_id
+        WHERE la.wallet_id = $1
+        "#,
+    )
+    .bind(wallet_id)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(Sats(balance))
+}
+
+/// Writes a balanced journal entry inside the caller's database transaction.
+///
+/// Wallet accounts the entry takes sats out of are locked in a stable order and must not end up
+/// negative. System accounts are not locked: they may go negative, and postings only ever insert
+/// rows, so concurrent entries on them do not conflict. Nothing is persisted unless the caller commits `tx`.
+pub async fn post_entry(
+    tx: &mut DbTransaction<'_, Any>,
+    entry: &JournalEntry,
+) -> Result<Uuid, AppError> {
+    entry.validate()?;
+
+    let mut resolved = Vec::with_capacity(entry.postings.len());
+    for posting in &entry.postings {
+        let account_id = resolve_account(tx, posting.account).await?;
+        resolved.push((account_id, posting));
+    }
+
+    // Serializes spends from the same wallet so two entries cannot both pass the balance check.
+    // Locked in wallet ID order so concurrent entries cannot deadlock.
+    let guarded_wallets = entry.credited_wallets();
+    for wallet_id in &guarded_wallets {
+        sqlx::query("SELECT id FROM ledger_accounts WHERE wallet_id = $1 FOR UPDATE")
+            .bind(wallet_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    let entry_id = Uuid::new_v4();
+    sqlx::query(
+        r#"
+        INSERT INTO journal_entries (id, entry_type, transaction_id, description, created_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        "#,
+    )
+    .bind(entry_id)
+    .bind(&entry.entry_type)
+    .bind(entry.transaction_id)
+    .bind(&entry.description)
+    .execute(&mut **tx)
+    .await?;
+
+    for (account_id, posting) in &resolved {
+        sqlx::query(
+            r#"
+            INSERT INTO ledger_postings (id, journal_entry_id, account_id, amount_sats, created_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(entry_id)
+        .bind(account_id)
+        .bind(posting.amount.0)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    for wallet_id in guarded_wallets {
+        let balance = wallet_balance(&mut **tx, wallet_id).await?;
+        if balance.0 < 0 {
+            return Err(AppError::BadRequest(format!(
+                "Insufficient balance in wallet {}",
+                wallet_id
+            )));
+        }
+    }
+
+    info!(
+        "Posted journal entry {} ({}) with {} postings",
+        entry_id,
+        entry.entry_type,
+        resolved.len()
+    );
+
+    Ok(entry_id)
+}
+
+async fn resolve_account(
+    tx: &mut DbTransaction<'_, Any>,
+    account: AccountRef,
+) -> Result<Uuid, AppError> {
+    let account_id: Option<Uuid> = match account {
+        AccountRef::Wallet(wallet_id) => {
+            sqlx::query_scalar("SELECT id FROM ledger_accounts WHERE wallet_id = $1")
+                .bind(wallet_id)
+                .fetch_optional(&mut **tx)
+                .await?
+        }
+        AccountRef::System(code) => {
+            sqlx::query_scalar("SELECT id FROM ledger_accounts WHERE code = $1")
+                .bind(code)
+                .fetch_optional(&mut **tx)
+                .await?
+        }
+    };
+
+    account_id.ok_or_else(|| AppError::NotFound(format!("Ledger account not found: {:?}", account)))
+}
@@ -0,0 +1,691 @@
+use sqlx::{Any, Transaction as DbTransaction};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    bitcoin::breez_webhook::BreezWebhookRequest,
+    database::AnyPool,
+    domain::{
+        audit::{AuditAction, AuditActor, AuditEvent},
+        ledger::{AccountRef, JournalEntry, FEES_ACCOUNT, LIGHTNING_ACCOUNT, SUSPENSE_ACCOUNT, TREASURY_ACCOUNT},
+        models::{Transaction, UnmatchedPayment},
+        transaction::{PaymentDirection, TransactionStatus, TransactionType},
+        types::Sats,
+    },
+    error::AppError,
+    services::{alert_service, audit_service, ledger_service, transaction_service},
+};
+
+const UNMATCHED_PAYMENT_COLUMNS: &str = "id, payment_hash, direction, amount_sats, fee_sats, bolt11, status, wallet_id, transaction_id, refund_payment_hash, resolved_by, resolution_notes, resolved_at, created_at, updated_at";
+
+/// How a payment reported by the node ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentOutcome {
+    Paid,
+    Failed,
+}
+
+/// A payment update from the Lightning node.
+#[derive(Debug, Clone)]
+pub struct PaymentNotification {
+    pub payment_hash: String,
+    pub direction: PaymentDirection,
+    pub outcome: PaymentOutcome,
+    /// Incoming: what reached the node. Outgoing: what the recipient was paid.
+    pub amount_sats: Sats,
+    /// Incoming: what the LSP withheld before the payment reached the node. Outgoing: the routing fee paid on top.
+    pub fee_sats: Sats,
+    pub bolt11: Option<String>,
+}
+
+/// Handles a stored Breez webhook inside the webhook pipeline's database transaction.
+/// Only `PAID` and `FAILED` settle a payment; other statuses are updates to wait past.
+pub async fn handle_breez_event(
+    tx: &mut DbTransaction<'_, Any>,
+    app_state: &AppState,
+    raw_body: &[u8],
+) -> Result<(), AppError> {
+    let request: BreezWebhookRequest = serde_json::from_slice(raw_body)?;
+    let outcome = match request.status.as_str() {
+        "PAID" => PaymentOutcome::Paid,
+        "FAILED" => PaymentOutcome::Failed,
+        other => {
+            info!("Breez payment {} is {}; waiting for it to settle", request.payment_hash, other);
+            return Ok(());
+        }
+    };
+
+    process_notification(
+        tx,
+        app_state,
+        &PaymentNotification {
+            payment_hash: request.payment_hash,
+            direction: request.payment_type.into(),
+            outcome,
+            amount_sats: Sats(request.amount_msat as i64 / 1000), // convert msats to sats
+            fee_sats: Sats(request.fee_msat as i64 / 1000),
+            bolt11: request.bolt11,
+        },
+    )
+    .await
+}
+
+/// Settles the transaction a node notification is for, inside the caller's database transaction.
+/// Payments that match nothing, or that contradict the transaction they name, are parked in
+/// `unmatched_payments` for an admin.
+pub async fn process_notification(
+    tx: &mut DbTransaction<'_, Any>,
+    app_state: &AppState,
+    notification: &PaymentNotification,
+) -> Result<(), AppError> {
+    info!(
+        "Processing {} Lightning payment {}: {} sats, fee {} sats, {:?}",
+        notification.direction,
+        notification.payment_hash,
+        notification.amount_sats.0,
+        notification.fee_sats.0,
+        notification.outcome
+    );
+
+    let parked = match transaction_service::lock_by_external_id(tx, &notification.payment_hash).await? {
+        Some(mut transaction) => settle_matched(tx, &mut transaction, notification).await?,
+        None if is_settled_elsewhere(tx, &notification.payment_hash).await? => {
+            info!(
+                "Payment {} was booked when it was sent; nothing to settle",
+                notification.payment_hash
+            );
+            None
+        }
+        None => park_unmatched(tx, notification).await?,
+    };
+
+    if let Some(unmatched) = parked {
+        alert_service::spawn_admin_alert(
+            app_state.config.clone(),
+            "Unmatched Lightning payment".to_string(),
+            format!(
+                "The node reported an {} payment of {} (fee {}) with hash {} that matches no transaction.\n\
+                 It is waiting in the unmatched payments queue ({}).",
+                unmatched.direction, unmatched.amount_sats, unmatched.fee_sats, unmatched.payment_hash, unmatched.id
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// For code that just sent `payment_hash` for `transaction`: applies a notification that arrived
+/// before the transaction was committed and was parked as unmatched. Call it in the same database
+/// transaction that records the payment hash. Returns whether there was one.
+pub async fn claim_early_notification(
+    tx: &mut DbTransaction<'_, Any>,
+    payment_hash: &str,
+    transaction: &mut Transaction,
+) -> Result<bool, AppError> {
+    let unmatched: Option<UnmatchedPayment> = sqlx::query_as(&format!(
+        "SELECT {} FROM unmatched_payments WHERE payment_hash = $1 AND direction = $2 AND status = 'open' FOR UPDATE",
+        UNMATCHED_PAYMENT_COLUMNS
+    ))
+    .bind(payment_hash)
+    .bind(PaymentDirection::Outgoing)
+    .fetch_optional(&mut **tx)
+    .await?;
+    let Some(unmatched) = unmatched else {
+        return Ok(false);
+    };
+
+    // Payouts and manual releases book their fee when they send; only pending sends wait for the node
+    if transaction.status.can_transition_to(TransactionStatus::Completed) {
+        settle_send(tx, transaction, unmatched.fee_sats).await?;
+    }
+    mark_resolved(
+        tx,
+        unmatched.id,
+        "attributed",
+        None,
+        Some(transaction.wallet_id),
+        Some(transaction.id),
+        Some("Matched once the send was recorded"),
+    )
+    .await?;
+    info!(
+        "Early notification for payment {} applied to transaction {}",
+        payment_hash, transaction.id
+    );
+    Ok(true)
+}
+
+/// Returns the parked payment if the notification could not be applied to `transaction`.
+async fn settle_matched(
+    tx: &mut DbTransaction<'_, Any>,
+    transaction: &mut Transaction,
+    notification: &PaymentNotification,
+) -> Result<Option<UnmatchedPayment>, AppError> {
+    if transaction.status.is_final() {
+        info!(
+            "Transaction {} is already {}; ignoring notification for payment {}",
+            transaction.id, transaction.status, notification.payment_hash
+        );
+        return Ok(None);
+    }
+    if transaction.tx_type.lightning_direction() != Some(notification.direction) {
+        warn!(
+            "Payment {} is {} but transaction {} is a {}",
+            notification.payment_hash, notification.direction, transaction.id, transaction.tx_type
+        );
+        return park_unmatched(tx, notification).await;
+    }
+
+    match notification.outcome {
+        PaymentOutcome::Paid if !transaction.status.can_transition_to(TransactionStatus::Completed) => {
+            error!(
+                "Payment {} was paid but transaction {} is {}",
+                notification.payment_hash, transaction.id, transaction.status
+            );
+            park_unmatched(tx, notification).await
+        }
+        PaymentOutcome::Paid => {
+            match notification.direction {
+                PaymentDirection::Incoming => {
+                    settle_receive(tx, transaction, notification.amount_sats, notification.fee_sats).await?
+                }
+                PaymentDirection::Outgoing => settle_send(tx, transaction, notification.fee_sats).await?,
+            }
+            Ok(None)
+        }
+        PaymentOutcome::Failed if transaction.status == TransactionStatus::Failed => {
+            info!("Transaction {} already failed.", transaction.id);
+            Ok(None)
+        }
+        PaymentOutcome::Failed => {
+            transaction_service::transition(tx, transaction, TransactionStatus::Failed).await?;
+            if notification.direction == PaymentDirection::Outgoing {
+                // Give the sender back what was debited when the payment was sent
+                let entry = JournalEntry::new("lightning_send_reversal")
+                    .for_transaction(transaction.id)
+                    .transfer(
+                        AccountRef::System(LIGHTNING_ACCOUNT),
+                        AccountRef::Wallet(transaction.wallet_id),
+                        transaction.amount_sats,
+                    );
+                ledger_service::post_entry(tx, &entry).await?;
+            }
+            warn!("Lightning payment {} failed.", notification.payment_hash);
+            Ok(None)
+        }
+    }
+}
+
+/// Completes a receive and credits the wallet with what reached the node.
+async fn settle_receive(
+    tx: &mut DbTransaction<'_, Any>,
+    transaction: &mut Transaction,
+    amount_sats: Sats,
+    fee_sats: Sats,
+) -> Result<(), AppError> {
+    transaction_service::transition(tx, transaction, TransactionStatus::Completed).await?;
+    sqlx::query("UPDATE transactions SET amount_sats = $1, fee_sats = $2 WHERE id = $3")
+        .bind(amount_sats.0)
+        .bind(fee_sats.0)
+        .bind(transaction.id)
+        .execute(&mut **tx)
+        .await?;
+
+    let entry = receive_entry(AccountRef::Wallet(transaction.wallet_id), amount_sats, fee_sats)
+        .for_transaction(transaction.id);
+    ledger_service::post_entry(tx, &entry).await?;
+    info!("Lightning receive {} completed. Wallet credited.", transaction.id);
+    Ok(())
+}
+
+/// Completes a send whose amount was debited when it went out, and books the routing fee.
+async fn settle_send(
+    tx: &mut DbTransaction<'_, Any>,
+    transaction: &mut Transaction,
+    fee_sats: Sats,
+) -> Result<(), AppError> {
+    transaction_service::transition(tx, transaction, TransactionStatus::Completed).await?;
+    sqlx::query("UPDATE transactions SET fee_sats = $1 WHERE id = $2")
+        .bind(fee_sats.0)
+        .bind(transaction.id)
+        .execute(&mut **tx)
+        .await?;
+
+    if fee_sats.0 > 0 {
+        let balance = ledger_service::wallet_balance(&mut **tx, transaction.wallet_id).await?;
+        let (from_wallet, from_treasury) = split_send_fee(fee_sats, balance);
+        let entry = JournalEntry::new("lightning_send_fee")
+            .for_transaction(transaction.id)
+            .transfer(
+                AccountRef::Wallet(transaction.wallet_id),
+                AccountRef::System(FEES_ACCOUNT),
+                from_wallet,
+            )
+            .transfer(
+                AccountRef::System(TREASURY_ACCOUNT),
+                AccountRef::System(FEES_ACCOUNT),
+                from_treasury,
+            );
+        ledger_service::post_entry(tx, &entry).await?;
+    }
+    info!("Lightning send {} completed.", transaction.id);
+    Ok(())
+}
+
+/// Records a paid notification nobody claims. Incoming sats are held in the suspense account until
+/// an admin resolves them; outgoing ones are booked only on resolution, since the ledger may already
+/// hold them under a transaction committed after the notification arrived. Returns the new row,
+/// or `None` for failures and repeated notifications.
+async fn park_unmatched(
+    tx: &mut DbTransaction<'_, Any>,
+    notification: &PaymentNotification,
+) -> Result<Option<UnmatchedPayment>, AppError> {
+    if notification.outcome == PaymentOutcome::Failed {
+        warn!(
+            "Failed {} payment {} matches no transaction; nothing to do",
+            notification.direction, notification.payment_hash
+        );
+        return Ok(None);
+    }
+
+    let unmatched: Option<UnmatchedPayment> = sqlx::query_as(&format!(
+        r#"
+        INSERT INTO unmatched_payments (id, payment_hash, direction, amount_sats, fee_sats, bolt11, status, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, 'open', NOW(), NOW())
+        ON CONFLICT (payment_hash) DO NOTHING
+        RETURNING {}
+        "#,
+        UNMATCHED_PAYMENT_COLUMNS
+    ))
+    .bind(Uuid::new_v4())
+    .bind(&notification.payment_hash)
+    .bind(notification.direction)
+    .bind(notification.amount_sats.0)
+    .bind(notification.fee_sats.0)
+    .bind(&notification.bolt11)
+    .fetch_optional(&mut **tx)
+    .await?;
+    let Some(unmatched) = unmatched else {
+        info!("Unmatched payment {} is already parked.", notification.payment_hash);
+        return Ok(None);
+    };
+
+    if unmatched.direction == PaymentDirection::Incoming {
+        let entry = receive_entry(AccountRef::System(SUSPENSE_ACCOUNT), unmatched.amount_sats, unmatched.fee_sats)
+            .with_description(&format!("Unmatched payment {}", unmatched.payment_hash));
+        ledger_service::post_entry(tx, &entry).await?;
+    }
+    warn!(
+        "{} payment {} matches no transaction; parked as unmatched payment {}",
+        unmatched.direction, unmatched.payment_hash, unmatched.id
+    );
+    Ok(Some(unmatched))
+}
+
+/// Sends that were booked without a transaction carrying their hash: deposit payouts and refunds.
+async fn is_settled_elsewhere(tx: &mut DbTransaction<'_, Any>, payment_hash: &str) -> Result<bool, AppError> {
+    let settled: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM payout_jobs WHERE payment_hash = $1)
+            OR EXISTS (SELECT 1 FROM unmatched_payments WHERE refund_payment_hash = $1)
+        "#,
+    )
+    .bind(payment_hash)
+    .fetch_one(&mut **tx)
+    .await?;
+    Ok(settled)
+}
+
+/// Lists unmatched payments, newest first, optionally filtered by status.
+pub async fn list_unmatched(db_pool: &AnyPool, status: Option<&str>) -> Result<Vec<UnmatchedPayment>, AppError> {
+    let payments = sqlx::query_as::<_, UnmatchedPayment>(&format!(
+        "SELECT {} FROM unmatched_payments WHERE ($1::TEXT IS NULL OR status = $1) ORDER BY created_at DESC",
+        UNMATCHED_PAYMENT_COLUMNS
+    ))
+    .bind(status)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(payments)
+}
+
+/// Assigns an unmatched payment to a wallet. An incoming payment is credited to `wallet_id` from
+/// suspense. An outgoing one settles the transaction that has since appeared with its hash, or else
+/// is charged to `wallet_id`. Either way it gets a completed transaction.
+pub async fn attribute(
+    db_pool: &AnyPool,
+    actor: &AuditActor,
+    unmatched_id: Uuid,
+    wallet_id: Option<Uuid>,
+    notes: Option<&str>,
+) -> Result<UnmatchedPayment, AppError> {
+    let mut tx = db_pool.begin().await?;
+    let unmatched = lock_open(&mut tx, unmatched_id).await?;
+
+    let transaction = match transaction_service::lock_by_external_id(&mut tx, &unmatched.payment_hash).await? {
+        Some(mut transaction) if unmatched.direction == PaymentDirection::Outgoing => {
+            if !transaction.status.is_final() {
+                settle_send(&mut tx, &mut transaction, unmatched.fee_sats).await?;
+            }
+            transaction
+        }
+        Some(transaction) => {
+            return Err(AppError::Conflict(format!(
+                "Payment {} already belongs to transaction {}",
+                unmatched.payment_hash, transaction.id
+            )))
+        }
+        None => {
+            let wallet_id = wallet_id.ok_or_else(|| {
+                AppError::BadRequest("A wallet_id is required to attribute this payment".to_string())
+            })?;
+            book_to_wallet(&mut tx, &unmatched, wallet_id).await?
+        }
+    };
+
+    let resolved = mark_resolved(
+        &mut tx,
+        unmatched.id,
+        "attributed",
+        actor.admin_id,
+        Some(transaction.wallet_id),
+        Some(transaction.id),
+        notes,
+    )
+    .await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::UnmatchedAttribute)
+            .target("unmatched_payment", unmatched.id)
+            .before(&unmatched)
+            .after(&resolved),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Unmatched payment {} attributed to wallet {} as transaction {}",
+        unmatched.id, transaction.wallet_id, transaction.id
+    );
+    Ok(resolved)
+}
+
+/// Creates the completed transaction for a payment attributed to `wallet_id` and moves the money.
+async fn book_to_wallet(
+    tx: &mut DbTransaction<'_, Any>,
+    unmatched: &UnmatchedPayment,
+    wallet_id: Uuid,
+) -> Result<Transaction, AppError> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM wallets WHERE id = $1)")
+        .bind(wallet_id)
+        .fetch_one(&mut **tx)
+        .await?;
+    if !exists {
+        return Err(AppError::NotFound(format!("Wallet {} not found", wallet_id)));
+    }
+
+    let (tx_type, description) = match unmatched.direction {
+        PaymentDirection::Incoming => (TransactionType::LightningReceive, "Attributed unmatched receive"),
+        PaymentDirection::Outgoing => (TransactionType::BtcWithdrawal, "Attributed unmatched send"),
+    };
+    let mut transaction = transaction_service::create(
+        tx,
+        wallet_id,
+        tx_type,
+        unmatched.amount_sats,
+        unmatched.fee_sats,
+        Some(description),
+        Some(&unmatched.payment_hash),
+    )
+    .await?;
+    transaction_service::transition(tx, &mut transaction, TransactionStatus::Completed).await?;
+
+    let entry = match unmatched.direction {
+        // The LSP fee was booked when the payment was parked
+        PaymentDirection::Incoming => JournalEntry::new("unmatched_attribute").transfer(
+            AccountRef::System(SUSPENSE_ACCOUNT),
+            AccountRef::Wallet(wallet_id),
+            unmatched.amount_sats,
+        ),
+        // The ledger rejects the entry if the wallet cannot cover the send
+        PaymentDirection::Outgoing => JournalEntry::new("unmatched_attribute")
+            .transfer(
+                AccountRef::Wallet(wallet_id),
+                AccountRef::System(LIGHTNING_ACCOUNT),
+                unmatched.amount_sats,
+            )
+            .transfer(
+                AccountRef::Wallet(wallet_id),
+                AccountRef::System(FEES_ACCOUNT),
+                unmatched.fee_sats,
+            ),
+    }
+    .for_transaction(transaction.id);
+    ledger_service::post_entry(tx, &entry).await?;
+    Ok(transaction)
+}
+
+/// Sends an unmatched incoming payment back to `recipient` out of suspense. Sabi pays the routing fee.
+///
+/// The payment is marked 'refunding' and committed before sending, so no other refund, attribution or
+/// dismissal can touch it while the payment is in flight outside a database transaction. The outcome
+/// is recorded afterwards: 'refunded' once the payment went out, back to 'open' if it did not.
+pub async fn refund(
+    app_state: &AppState,
+    actor: &AuditActor,
+    unmatched_id: Uuid,
+    recipient: &str,
+    notes: Option<&str>,
+) -> Result<UnmatchedPayment, AppError> {
+    // 1. Claim the refund
+    let mut tx = app_state.db_pool.begin().await?;
+    let unmatched = lock_open(&mut tx, unmatched_id).await?;
+    if unmatched.direction != PaymentDirection::Incoming {
+        return Err(AppError::Conflict("Only incoming payments can be refunded".to_string()));
+    }
+    set_status(&mut tx, unmatched.id, "refunding").await?;
+    tx.commit().await?;
+
+    // 2. Send; a crash from here on leaves the payment 'refunding' for an admin to check against the node
+    let sent = app_state
+        .lightning
+        .send_payment(unmatched.amount_sats, recipient)
+        .await;
+
+    // 3. Record the outcome
+    let mut tx = app_state.db_pool.begin().await?;
+    lock(&mut tx, unmatched.id).await?;
+    let payment = match sent {
+        Ok(payment) => payment,
+        Err(e) => {
+            set_status(&mut tx, unmatched.id, "open").await?;
+            tx.commit().await?;
+            warn!("Refund of unmatched payment {} to {} failed: {}", unmatched.id, recipient, e);
+            return Err(e);
+        }
+    };
+
+    let entry = JournalEntry::new("unmatched_refund")
+        .with_description(&format!("Refund of unmatched payment {} to {}", unmatched.payment_hash, recipient))
+        .transfer(
+            AccountRef::System(SUSPENSE_ACCOUNT),
+            AccountRef::System(LIGHTNING_ACCOUNT),
+            unmatched.amount_sats,
+        )
+        .transfer(
+            AccountRef::System(TREASURY_ACCOUNT),
+            AccountRef::System(FEES_ACCOUNT),
+            payment.fee_sats,
+        );
+    ledger_service::post_entry(&mut tx, &entry).await?;
+
+    sqlx::query("UPDATE unmatched_payments SET refund_payment_hash = $2 WHERE id = $1")
+        .bind(unmatched.id)
+        .bind(&payment.payment_hash)
+        .execute(&mut *tx)
+        .await?;
+    let resolved = mark_resolved(&mut tx, unmatched.id, "refunded", actor.admin_id, None, None, notes).await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::UnmatchedRefund)
+            .target("unmatched_payment", unmatched.id)
+            .before(&unmatched)
+            .after(&resolved),
+    )
+    .await?;
+
+    if let Err(e) = tx.commit().await {
+        // The payment went out, so it stays 'refunding' rather than being refunded again
+        error!(
+            "Unmatched payment {} was refunded as {} but recording it failed: {:?}",
+            unmatched.id, payment.payment_hash, e
+        );
+        return Err(e.into());
+    }
+
+    info!(
+        "Unmatched payment {} refunded to {} (payment {})",
+        unmatched.id, recipient, payment.payment_hash
+    );
+    Ok(resolved)
+}
+
+/// Closes an unmatched payment without attributing or refunding it. Incoming sats move from
+/// suspense to the treasury; outgoing payments are left unbooked, for sends already accounted for.
+pub async fn dismiss(
+    db_pool: &AnyPool,
+    actor: &AuditActor,
+    unmatched_id: Uuid,
+    notes: &str,
+) -> Result<UnmatchedPayment, AppError> {
+    let mut tx = db_pool.begin().await?;
+    let unmatched = lock_open(&mut tx, unmatched_id).await?;
+
+    if unmatched.direction == PaymentDirection::Incoming {
+        let entry = JournalEntry::new("unmatched_dismiss")
+            .with_description(&format!("Dismissed unmatched payment {}", unmatched.payment_hash))
+            .transfer(
+                AccountRef::System(SUSPENSE_ACCOUNT),
+                AccountRef::System(TREASURY_ACCOUNT),
+                unmatched.amount_sats,
+            );
+        ledger_service::post_entry(&mut tx, &entry).await?;
+    }
+
+    let resolved = mark_resolved(&mut tx, unmatched.id, "dismissed", actor.admin_id, None, None, Some(notes)).await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::UnmatchedDismiss)
+            .target("unmatched_payment", unmatched.id)
+            .before(&unmatched)
+            .after(&resolved),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("Unmatched payment {} dismissed: {}", unmatched.id, notes);
+    Ok(resolved)
+}
+
+/// Loads an unmatched payment for resolution. `409` if it was already resolved.
+async fn lock(tx: &mut DbTransaction<'_, Any>, unmatched_id: Uuid) -> Result<UnmatchedPayment, AppError> {
+    sqlx::query_as(&format!(
+        "SELECT {} FROM unmatched_payments WHERE id = $1 FOR UPDATE",
+        UNMATCHED_PAYMENT_COLUMNS
+    ))
+    .bind(unmatched_id)
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Unmatched payment {} not found", unmatched_id)))
+}
+
+async fn lock_open(tx: &mut DbTransaction<'_, Any>, unmatched_id: Uuid) -> Result<UnmatchedPayment, AppError> {
+    let unmatched = lock(tx, unmatched_id).await?;
+    if unmatched.status != "open" {
+        return Err(AppError::Conflict(format!(
+            "Unmatched payment {} is already {}",
+            unmatched_id, unmatched.status
+        )));
+    }
+    Ok(unmatched)
+}
+
+async fn set_status(tx: &mut DbTransaction<'_, Any>, unmatched_id: Uuid, status: &str) -> Result<(), AppError> {
+    sqlx::query("UPDATE unmatched_payments SET status = $2, updated_at = NOW() WHERE id = $1")
+        .bind(unmatched_id)
+        .bind(status)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+async fn mark_resolved(
+    tx: &mut DbTransaction<'_, Any>,
+    unmatched_id: Uuid,
+    status: &str,
+    resolved_by: Option<Uuid>,
+    wallet_id: Option<Uuid>,
+    transaction_id: Option<Uuid>,
+    notes: Option<&str>,
+) -> Result<UnmatchedPayment, AppError> {
+    let resolved = sqlx::query_as::<_, UnmatchedPayment>(&format!(
+        r#"
+        UPDATE unmatched_payments
+        SET status = $2, resolved_by = $3, wallet_id = $4, transaction_id = $5, resolution_notes = $6,
+            resolved_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        UNMATCHED_PAYMENT_COLUMNS
+    ))
+    .bind(unmatched_id)
+    .bind(status)
+    .bind(resolved_by)
+    .bind(wallet_id)
+    .bind(transaction_id)
+    .bind(notes)
+    .fetch_one(&mut **tx)
+    .await?;
+    Ok(resolved)
+}
+
+/// Books an incoming payment to `account`: the payer sent `amount + fee`, the LSP kept the fee.
+fn receive_entry(account: AccountRef, amount_sats: Sats, fee_sats: Sats) -> JournalEntry {
+    JournalEntry::new("lightning_receive")
+        .post(AccountRef::System(LIGHTNING_ACCOUNT), Sats(-(amount_sats.0 + fee_sats.0)))
+        .post(account, amount_sats)
+        .post(AccountRef::System(FEES_ACCOUNT), fee_sats)
+}
+
+/// Splits a send's routing fee into what the sender's remaining balance covers and what the
+/// treasury absorbs. Returns `(from_wallet, from_treasury)`.
+fn split_send_fee(fee_sats: Sats, wallet_balance: Sats) -> (Sats, Sats) {
+    let from_wallet = fee_sats.0.min(wallet_balance.0.max(0));
+    (Sats(from_wallet), Sats(fee_sats.0 - from_wallet))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_fee_is_charged_to_the_wallet_first() {
+        assert_eq!(split_send_fee(Sats(10), Sats(500)), (Sats(10), Sats(0)));
+        assert_eq!(split_send_fee(Sats(10), Sats(4)), (Sats(4), Sats(6)));
+        assert_eq!(split_send_fee(Sats(10), Sats(0)), (Sats(0), Sats(10)));
+        assert_eq!(split_send_fee(Sats(0), Sats(500)), (Sats(0), Sats(0)));
+    }
+
+    #[test]
+    fn test_receive_credits_what_reached_the_node() {
+        let wallet_id = Uuid::new_v4();
+        let entry = receive_entry(AccountRef::Wallet(wallet_id), Sats(1_000), Sats(25));
+        entry.validate().unwrap();
+        let credited: i64 = entry
+            .postings
+            .iter()
+            .filter(|p| p.account == AccountRef::Wallet(wallet_id))
+            .map(|p| p.amount.0)
+            .sum();
+        assert_eq!(credited, 1_000);
+    }
+}
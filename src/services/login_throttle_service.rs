@@ -0,0 +1,175 @@
+use redis::AsyncCommands;
+use std::net::IpAddr;
+use tracing::{error, warn};
+
+use crate::{app_state::AppState, error::AppError, services::alert_service};
+
+const KEY_PREFIX: &str = "admin_login";
+
+/// One identity failed logins are counted against.
+struct Subject {
+    kind: &'static str, // 'username' | 'ip'
+    value: String,
+    max_failures: i64,
+}
+
+impl Subject {
+    fn failures_key(&self) -> String {
+        format!("{}:failures:{}:{}", KEY_PREFIX, self.kind, self.value)
+    }
+
+    fn blocked_key(&self) -> String {
+        format!("{}:blocked:{}:{}", KEY_PREFIX, self.kind, self.value)
+    }
+}
+
+fn subjects(app_state: &AppState, username: &str, client_ip: IpAddr) -> [Subject; 2] {
+    [
+        Subject {
+            kind: "username",
+            value: username.to_string(),
+            max_failures: app_state.config.admin_login_max_failures,
+        },
+        Subject {
+            kind: "ip",
+            value: client_ip.to_string(),
+            max_failures: app_state.config.admin_login_max_failures_per_ip,
+        },
+    ]
+}
+
+/// Refuses the attempt with `429` while the username or the client IP is backing off or locked out.
+/// Usernames are counted whether or not they exist, so the response never reveals which do.
+/// If Redis is unreachable, login stays available without throttling and the error is logged.
+pub async fn check(app_state: &AppState, username: &str, client_ip: IpAddr) -> Result<(), AppError> {
+    let remaining = match blocked_for(app_state, username, client_ip).await {
+        Ok(remaining) => remaining,
+        Err(e) => {
+            error!("Admin login throttling unavailable: {:?}", e);
+            return Ok(());
+        }
+    };
+
+    if remaining > 0 {
+        warn!("Admin login for '{}' from {} refused: blocked for {}s", username, client_ip, remaining);
+        return Err(AppError::TooManyRequests {
+            message: format!("Too many failed login attempts. Try again in {} seconds.", remaining),
+            retry_after_seconds: remaining,
+        });
+    }
+    Ok(())
+}
+
+/// Counts a failed password or second-factor attempt and starts the backoff. Emails an alert
+/// to `ADMIN_EMAIL_RECIPIENT` when the username or IP reaches its lockout threshold.
+pub async fn record_failure(app_state: &AppState, username: &str, client_ip: IpAddr) {
+    let lockout_seconds = app_state.config.admin_login_lockout_seconds;
+
+    for subject in subjects(app_state, username, client_ip) {
+        let failures = match count_failure(app_state, &subject, lockout_seconds).await {
+            Ok(failures) => failures,
+            Err(e) => {
+                error!("Failed to record failed admin login: {:?}", e);
+                return;
+            }
+        };
+
+        // Only the attempt that crosses the threshold alerts; further attempts just extend the lockout
+        if failures == subject.max_failures {
+            warn!(
+                "Admin login locked out for {} '{}' after {} failures",
+                subject.kind, subject.value, failures
+            );
+            alert_service::spawn_admin_alert(
+                app_state.config.clone(),
+                format!("Admin login locked out ({})", subject.kind),
+                format!(
+                    "{} failed admin login attempts for {} '{}' (latest from {} as '{}').\n\
+                     Further attempts are refused for {} seconds.",
+                    failures, subject.kind, subject.value, client_ip, username, lockout_seconds
+                ),
+            );
+        }
+    }
+}
+
+/// Clears the username's failures after a complete login. The IP's count is left alone,
+/// so one valid account cannot be used to reset guessing against others.
+pub async fn record_success(app_state: &AppState, username: &str, client_ip: IpAddr) {
+    let [username_subject, _] = subjects(app_state, username, client_ip);
+    let result: Result<(), AppError> = async {
+        let mut con = app_state.redis_client.get_async_connection().await?;
+        let _: () = con
+            .del(&[username_subject.failures_key(), username_subject.blocked_key()])
+            .await?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        error!("Failed to reset admin login failures: {:?}", e);
+    }
+}
+
+/// Seconds until both the username and the IP may try again; 0 if neither is blocked.
+async fn blocked_for(app_state: &AppState, username: &str, client_ip: IpAddr) -> Result<i64, AppError> {
+    let mut con = app_state.redis_client.get_async_connection().await?;
+    let mut remaining = 0;
+    for subject in subjects(app_state, username, client_ip) {
+        // TTL is -2 for a missing key, so an unblocked subject contributes nothing
+        let ttl: i64 = con.ttl(subject.blocked_key()).await?;
+        remaining = remaining.max(ttl);
+    }
+    Ok(remaining)
+}
+
+async fn count_failure(app_state: &AppState, subject: &Subject, lockout_seconds: i64) -> Result<i64, AppError> {
+    let mut con = app_state.redis_client.get_async_connection().await?;
+    let failures_key = subject.failures_key();
+
+    // The count expires after a lockout period without failures
+    let (failures,): (i64,) = redis::pipe()
+        .atomic()
+        .incr(&failures_key, 1)
+        .expire(&failures_key, lockout_seconds)
+        .ignore()
+        .query_async(&mut con)
+        .await?;
+
+    let delay = backoff_seconds(failures, subject.max_failures, lockout_seconds);
+    if delay > 0 {
+        let _: () = con.set_ex(subject.blocked_key(), failures, delay as u64).await?;
+    }
+    Ok(failures)
+}
+
+/// How long a subject must wait after its `failures`-th consecutive failure: doubling from
+/// one second, then the full lockout once `max_failures` is reached.
+fn backoff_seconds(failures: i64, max_failures: i64, lockout_seconds: i64) -> i64 {
+    if failures <= 0 {
+        return 0;
+    }
+    if failures >= max_failures {
+        return lockout_seconds;
+    }
+    let exponent = (failures - 1).min(30) as u32;
+    2_i64.pow(exponent).min(lockout_seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_until_lockout() {
+        let delays: Vec<i64> = (1..=6).map(|failures| backoff_seconds(failures, 5, 900)).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 900, 900]);
+    }
+
+    #[test]
+    fn test_backoff_never_exceeds_lockout() {
+        assert_eq!(backoff_seconds(0, 5, 900), 0);
+        assert_eq!(backoff_seconds(40, 100, 900), 900);
+        assert_eq!(backoff_seconds(3, 5, 2), 2);
+    }
+}
@@ -1,6 +1,23 @@
 pub mod admin_service;
+pub mod admin_user_service;
+pub mod alert_service;
+pub mod audit_service;
 pub mod fiat_service;
+pub mod lightning_payment_service;
+pub mod ledger_service;
+pub mod login_throttle_service;
 pub mod nostr_service;
+pub mod nsec_service;
+pub mod onboarding_service;
+pub mod payout_service;
+pub mod quote_service;
 pub mod recovery_service;
+pub mod release_service;
+pub mod session_service;
+pub mod sms_service;
+pub mod totp_service;
+pub mod trade_service;
+pub mod transaction_service;
 pub mod ussd_service;
 pub mod wallet_service;
+pub mod webhook_service;
@@ -0,0 +1,221 @@
+use secrecy::SecretString;
+use sqlx::{Any, Transaction as DbTransaction};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    crypto::{
+        envelope::{self, Envelope},
+        key_provider::KeyProvider,
+    },
+    database::AnyPool,
+    domain::models::EncryptedNostrNsec,
+    error::AppError,
+};
+
+const NSEC_COLUMNS: &str =
+    "id, wallet_id, legacy_nsec, key_version, wrapped_data_key, nonce, ciphertext, created_at, updated_at";
+
+/// Counts from a master key rotation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RotationSummary {
+    /// Envelopes whose data key was re-wrapped under the current master key.
+    pub rewrapped: usize,
+    /// Legacy plaintext rows that were encrypted for the first time.
+    pub encrypted_legacy: usize,
+}
+
+/// Encrypts and stores a wallet's nsec, replacing any previous one.
+pub async fn store_nsec(
+    db_pool: &AnyPool,
+    key_provider: &dyn KeyProvider,
+    wallet_id: Uuid,
+    nsec: &str,
+) -> Result<(), AppError> {
+    let mut tx = db_pool.begin().await?;
+    store_nsec_in_tx(&mut tx, key_provider, wallet_id, nsec).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Like `store_nsec`, inside the caller's transaction, for wallets created in that same transaction.
+pub async fn store_nsec_in_tx(
+    tx: &mut DbTransaction<'_, Any>,
+    key_provider: &dyn KeyProvider,
+    wallet_id: Uuid,
+    nsec: &str,
+) -> Result<(), AppError> {
+    let sealed = envelope::seal(key_provider, nsec.as_bytes(), wallet_id.as_bytes()).await?;
+
+    sqlx::query("DELETE FROM encrypted_nostr_nsecs WHERE wallet_id = $1")
+        .bind(wallet_id)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO encrypted_nostr_nsecs (id, wallet_id, key_version, wrapped_data_key, nonce, ciphertext, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, NOW(), NOW())
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(wallet_id)
+    .bind(sealed.key_version as i32)
+    .bind(sealed.wrapped_key)
+    .bind(sealed.nonce)
+    .bind(sealed.ciphertext)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Loads and decrypts a wallet's nsec.
+pub async fn load_nsec(
+    db_pool: &AnyPool,
+    key_provider: &dyn KeyProvider,
+    wallet_id: Uuid,
+) -> Result<SecretString, AppError> {
+    let record = sqlx::query_as::<_, EncryptedNostrNsec>(&format!(
+        "SELECT {} FROM encrypted_nostr_nsecs WHERE wallet_id = $1",
+        NSEC_COLUMNS
+    ))
+    .bind(wallet_id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Encrypted nsec not found for wallet {}", wallet_id)))?;
+
+    if let Some(legacy) = record.legacy_nsec {
+        warn!(
+            "Nsec for wallet {} is still stored in plaintext; run `rotate-master-key` to encrypt it",
+            wallet_id
+        );
+        return Ok(SecretString::new(legacy));
+    }
+
+    let plaintext = envelope::open(key_provider, &envelope_of(&record)?, wallet_id.as_bytes()).await?;
+    let nsec = String::from_utf8(plaintext)
+        .map_err(|_| AppError::Internal(format!("Decrypted nsec for wallet {} is not UTF-8", wallet_id)))?;
+    Ok(SecretString::new(nsec))
+}
+
+/// Moves every stored nsec onto the provider's current master key: envelopes under an older
+/// version are re-wrapped, and legacy plaintext rows are encrypted and their plaintext cleared.
+/// Each row is updated in its own transaction, so an interrupted run can simply be repeated.
+pub async fn reencrypt_all(
+    db_pool: &AnyPool,
+    key_provider: &dyn KeyProvider,
+) -> Result<RotationSummary, AppError> {
+    let current_version = key_provider.current_version() as i32;
+    let ids: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM encrypted_nostr_nsecs WHERE key_version IS NULL OR key_version <> $1",
+    )
+    .bind(current_version)
+    .fetch_all(db_pool)
+    .await?;
+
+    let mut summary = RotationSummary::default();
+    for id in ids {
+        let mut tx = db_pool.begin().await?;
+        let record = sqlx::query_as::<_, EncryptedNostrNsec>(&format!(
+            "SELECT {} FROM encrypted_nostr_nsecs WHERE id = $1 FOR UPDATE",
+            NSEC_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // Deleted or already rotated since the IDs were listed
+        let Some(record) = record else { continue };
+        if record.key_version == Some(current_version) {
+            continue;
+        }
+
+        let sealed = reseal(key_provider, &record).await?;
+        if record.legacy_nsec.is_some() {
+            summary.encrypted_legacy += 1;
+        } else {
+            summary.rewrapped += 1;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE encrypted_nostr_nsecs
+            SET legacy_nsec = NULL, key_version = $1, wrapped_data_key = $2, nonce = $3, ciphertext = $4, updated_at = NOW()
+            WHERE id = $5
+            "#,
+        )
+        .bind(sealed.key_version as i32)
+        .bind(sealed.wrapped_key)
+        .bind(sealed.nonce)
+        .bind(sealed.ciphertext)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+    }
+
+    info!(
+        "Master key rotation to version {}: {} re-wrapped, {} legacy rows encrypted",
+        current_version, summary.rewrapped, summary.encrypted_legacy
+    );
+    Ok(summary)
+}
+
+/// The record's nsec sealed under the current master key.
+async fn reseal(key_provider: &dyn KeyProvider, record: &EncryptedNostrNsec) -> Result<Envelope, AppError> {
+    match &record.legacy_nsec {
+        Some(legacy) => envelope::seal(key_provider, legacy.as_bytes(), record.wallet_id.as_bytes()).await,
+        None => envelope::rewrap(key_provider, &envelope_of(record)?).await,
+    }
+}
+
+fn envelope_of(record: &EncryptedNostrNsec) -> Result<Envelope, AppError> {
+    match (&record.key_version, &record.wrapped_data_key, &record.nonce, &record.ciphertext) {
+        (Some(version), Some(wrapped_key), Some(nonce), Some(ciphertext)) => Ok(Envelope {
+            key_version: u32::try_from(*version)
+                .map_err(|_| AppError::Internal(format!("Invalid key version on nsec {}", record.id)))?,
+            wrapped_key: wrapped_key.clone(),
+            nonce: nonce.clone(),
+            ciphertext: ciphertext.clone(),
+        }),
+        _ => Err(AppError::Internal(format!("Nsec {} has an incomplete envelope", record.id))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::key_provider::test_provider;
+    use chrono::Utc;
+
+    fn record(wallet_id: Uuid, legacy_nsec: Option<&str>, sealed: Option<Envelope>) -> EncryptedNostrNsec {
+        EncryptedNostrNsec {
+            id: Uuid::new_v4(),
+            wallet_id,
+            legacy_nsec: legacy_nsec.map(str::to_string),
+            key_version: sealed.as_ref().map(|e| e.key_version as i32),
+            wrapped_data_key: sealed.as_ref().map(|e| e.wrapped_key.clone()),
+            nonce: sealed.as_ref().map(|e| e.nonce.clone()),
+            ciphertext: sealed.map(|e| e.ciphertext),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reseal_moves_enveloped_and_legacy_nsecs_to_current_key() {
+        let wallet_id = Uuid::new_v4();
+        let old = test_provider(&[1], 1);
+        let rotated = test_provider(&[1, 2], 2);
+
+        let sealed = envelope::seal(&old, b"nsec1secret", wallet_id.as_bytes()).await.unwrap();
+        let rewrapped = reseal(&rotated, &record(wallet_id, None, Some(sealed))).await.unwrap();
+        assert_eq!(rewrapped.key_version, 2);
+        assert_eq!(envelope::open(&rotated, &rewrapped, wallet_id.as_bytes()).await.unwrap(), b"nsec1secret");
+
+        let encrypted = reseal(&rotated, &record(wallet_id, Some("nsec1legacy"), None)).await.unwrap();
+        assert_eq!(encrypted.key_version, 2);
+        assert_eq!(envelope::open(&rotated, &encrypted, wallet_id.as_bytes()).await.unwrap(), b"nsec1legacy");
+    }
+}
@@ -0,0 +1,117 @@
+use nostr_sdk::nostr::{nips::nip19::ToBech32, Keys};
+use sqlx::{Any, Transaction as DbTransaction};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    crypto::key_provider::KeyProvider,
+    domain::{recovery::RecoveryPolicy, types::Kobo},
+    error::AppError,
+    services::{nsec_service, wallet_service::WalletService},
+    utils::phone_number::NigerianPhoneNumber,
+};
+
+/// The wallet a payment for a phone number goes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayerWallet {
+    pub user_id: Uuid,
+    pub wallet_id: Uuid,
+    pub provisioned: bool, // True if the wallet was created for this payment
+}
+
+/// Finds the wallet of the user with `phone_number`, or creates the user, their Nostr keys and
+/// a wallet inside the caller's transaction. A rollback leaves no half-provisioned account.
+pub async fn find_or_provision_wallet(
+    tx: &mut DbTransaction<'_, Any>,
+    key_provider: &dyn KeyProvider,
+    phone_number: &NigerianPhoneNumber,
+) -> Result<PayerWallet, AppError> {
+    // A concurrent signup with the same number wins; we then use its user
+    sqlx::query(
+        "INSERT INTO users (id, phone_number, created_at, updated_at) VALUES ($1, $2, NOW(), NOW()) ON CONFLICT (phone_number) DO NOTHING",
+    )
+    .bind(Uuid::new_v4())
+    .bind(phone_number.as_str())
+    .execute(&mut **tx)
+    .await?;
+
+    let (user_id, wallet_id): (Uuid, Option<Uuid>) = sqlx::query_as(
+        r#"
+        SELECT u.id, w.id FROM users u
+        LEFT JOIN wallets w ON w.user_id = u.id
+        WHERE u.phone_number = $1
+        ORDER BY w.created_at
+        LIMIT 1
+        FOR UPDATE OF u
+        "#,
+    )
+    .bind(phone_number.as_str())
+    .fetch_one(&mut **tx)
+    .await?;
+
+    if let Some(wallet_id) = wallet_id {
+        return Ok(PayerWallet {
+            user_id,
+            wallet_id,
+            provisioned: false,
+        });
+    }
+
+    let keys = Keys::generate();
+    let npub = keys
+        .public_key()
+        .to_bech32()
+        .map_err(|e| AppError::Internal(format!("Failed to encode npub: {}", e)))?;
+    let nsec = keys
+        .secret_key()
+        .map_err(|e| AppError::Internal(format!("Generated Nostr keys have no secret key: {}", e)))?
+        .to_bech32()
+        .map_err(|e| AppError::Internal(format!("Failed to encode nsec: {}", e)))?;
+
+    // USSD users have no device to hold a seed or pick recovery helpers; they can set up a backup later.
+    // Nor do they have a payout destination, so their deposits stay in the wallet for USSD to spend.
+    let wallet =
+        WalletService::create_wallet_in_tx(tx, user_id, &npub, "none", RecoveryPolicy::default(), None).await?;
+    nsec_service::store_nsec_in_tx(tx, key_provider, wallet.id, &nsec).await?;
+
+    info!("Provisioned user {} and wallet {} for a first-time payer", user_id, wallet.id);
+    Ok(PayerWallet {
+        user_id,
+        wallet_id: wallet.id,
+        provisioned: true,
+    })
+}
+
+/// The SMS telling a provisioned user where their money is. Fits in a single 160-character SMS.
+/// Only a `credited` deposit is in the wallet's balance; a held one waits for an admin.
+pub fn welcome_message(deposit: Kobo, credited: bool, ussd_service_code: &str) -> String {
+    if credited {
+        format!(
+            "Sabi Wallet: we received your NGN {} deposit and opened a Bitcoin wallet for this number. Dial {} to check your balance or send.",
+            deposit.to_naira(),
+            ussd_service_code
+        )
+    } else {
+        format!(
+            "Sabi Wallet: we received your NGN {} deposit and opened a Bitcoin wallet for this number. The price moved, so our team will review it and contact you.",
+            deposit.to_naira()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_welcome_message_fits_one_sms() {
+        let message = welcome_message(Kobo(9_999_999_999), true, "*384*12345#");
+        assert!(message.contains("NGN 99999999.99"));
+        assert!(message.contains("*384*12345#"));
+        assert!(message.len() <= 160, "{} characters", message.len());
+
+        let held = welcome_message(Kobo(9_999_999_999), false, "*384*12345#");
+        assert!(!held.contains("balance"));
+        assert!(held.len() <= 160, "{} characters", held.len());
+    }
+}
@@ -0,0 +1,516 @@
+use chrono::Duration as ChronoDuration;
+use redis::{AsyncCommands, Client as RedisClient};
+use sqlx::{Any, Transaction as DbTransaction};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        ledger::{AccountRef, JournalEntry, FEES_ACCOUNT, LIGHTNING_ACCOUNT, TREASURY_ACCOUNT},
+        models::{PayoutJob, Transaction},
+        transaction::TransactionStatus,
+        types::Sats,
+    },
+    error::AppError,
+    services::{alert_service, ledger_service, lightning_payment_service, transaction_service},
+};
+
+/// Pushed to after commits that may have queued payouts; the worker blocks on it between polls.
+const WAKE_KEY: &str = "payout_jobs:wake";
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const BASE_BACKOFF_SECONDS: i64 = 30;
+const MAX_BACKOFF_SECONDS: i64 = 3600; // 1 hour
+/// A job still 'sending' after this long was interrupted mid-payment, e.g. by a restart.
+const SENDING_TIMEOUT_MINUTES: i64 = 10;
+
+const PAYOUT_JOB_COLUMNS: &str =
+    "id, transaction_id, status, attempts, next_attempt_at, last_error, payment_hash, created_at, updated_at";
+
+/// Credits an accepted deposit to its wallet inside the caller's transaction: Sabi sells the sats
+/// from its float. If the wallet has a payout destination, the deposit stays pending and its payout
+/// is queued; otherwise it is complete. Returns whether a payout was queued.
+pub async fn credit_deposit(
+    tx: &mut DbTransaction<'_, Any>,
+    transaction: &mut Transaction,
+) -> Result<bool, AppError> {
+    let entry = JournalEntry::new("deposit_credit")
+        .for_transaction(transaction.id)
+        .transfer(
+            AccountRef::System(TREASURY_ACCOUNT),
+            AccountRef::Wallet(transaction.wallet_id),
+            transaction.amount_sats,
+        );
+    ledger_service::post_entry(tx, &entry).await?;
+
+    if payout_destination(tx, transaction.wallet_id).await?.is_some() {
+        enqueue(tx, transaction.id).await?;
+        Ok(true)
+    } else {
+        transaction_service::transition(tx, transaction, TransactionStatus::Completed).await?;
+        Ok(false)
+    }
+}
+
+/// Queues the Lightning payout of a pending deposit inside the caller's transaction.
+/// Call `wake` once that transaction has committed.
+pub async fn enqueue(tx: &mut DbTransaction<'_, Any>, transaction_id: Uuid) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO payout_jobs (id, transaction_id, status, next_attempt_at, created_at, updated_at)
+        VALUES ($1, $2, 'queued', NOW(), NOW(), NOW())
+        ON CONFLICT (transaction_id) DO NOTHING
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(transaction_id)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Wakes a payout worker. Best effort: without Redis the job is still picked up at the next poll.
+pub async fn wake(redis_client: &RedisClient) {
+    let result: Result<(), AppError> = async {
+        let mut con = redis_client.get_async_connection().await?;
+        // One pending wakeup is as good as many
+        let _: () = redis::pipe()
+            .atomic()
+            .lpush(WAKE_KEY, 1)
+            .ltrim(WAKE_KEY, 0, 0)
+            .query_async(&mut con)
+            .await?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        warn!("Failed to wake the payout worker: {:?}", e);
+    }
+}
+
+/// Starts the background worker that pays out queued deposits. It runs until the process exits;
+/// jobs live in the database, so nothing is lost by a restart.
+pub fn spawn_worker(app_state: Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        info!("Payout worker started.");
+        loop {
+            if let Err(e) = hold_interrupted(&app_state).await {
+                error!("Payout worker failed to check for interrupted payouts: {:?}", e);
+            }
+            loop {
+                match process_next(&app_state).await {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(e) => {
+                        error!("Payout worker failed: {:?}", e);
+                        break;
+                    }
+                }
+            }
+            wait_for_work(&app_state.redis_client).await;
+        }
+    })
+}
+
+/// Claims and pays out the next due job. Returns false if none was due.
+pub async fn process_next(app_state: &AppState) -> Result<bool, AppError> {
+    let Some(claim) = claim_next(app_state).await? else {
+        return Ok(false);
+    };
+    let PayoutClaim { job, transaction, destination, debit } = claim;
+
+    // The claim is committed, so a crash from here on leaves the job 'sending' for `hold_interrupted`
+    match app_state
+        .lightning
+        .send_payment(transaction.amount_sats, &destination)
+        .await
+    {
+        Ok(payment) => {
+            let mut tx = app_state.db_pool.begin().await?;
+            let mut transaction = transaction_service::lock(&mut tx, transaction.id).await?;
+            transaction_service::transition(&mut tx, &mut transaction, TransactionStatus::Completed).await?;
+            sqlx::query("UPDATE transactions SET fee_sats = $1 WHERE id = $2")
+                .bind(payment.fee_sats.0)
+                .bind(transaction.id)
+                .execute(&mut *tx)
+                .await?;
+
+            // The claim booked the sats as sent, so only the fee is left
+            post_payout(&mut tx, "deposit_payout_fee", &transaction, payment.fee_sats, &destination).await?;
+
+            sqlx::query(
+                "UPDATE payout_jobs SET status = 'succeeded', payment_hash = $2, last_error = NULL, updated_at = NOW() WHERE id = $1",
+            )
+            .bind(job.id)
+            .bind(&payment.payment_hash)
+            .execute(&mut *tx)
+            .await?;
+            lightning_payment_service::claim_early_notification(&mut tx, &payment.payment_hash, &mut transaction)
+                .await?;
+            tx.commit().await?;
+
+            info!(
+                "Paid out {} for deposit {} (payment {})",
+                transaction.amount_sats, transaction.id, payment.payment_hash
+            );
+        }
+        Err(e) if job.attempts >= app_state.config.payout_max_attempts => {
+            let reason = format!("Gave up after {} attempts: {}", job.attempts, e);
+            hold(app_state, &job, &reason, Some(&debit)).await?;
+        }
+        Err(e) => {
+            // Nothing went out, so the deposit goes back to the wallet until the next attempt
+            let next_attempt_in = backoff_for(job.attempts);
+            let mut tx = app_state.db_pool.begin().await?;
+            post_if_any(&mut tx, &debit.reversed("deposit_payout_reversal")).await?;
+            sqlx::query(
+                "UPDATE payout_jobs SET status = 'queued', last_error = $2, next_attempt_at = NOW() + $3 * INTERVAL '1 second', updated_at = NOW() WHERE id = $1",
+            )
+            .bind(job.id)
+            .bind(e.to_string())
+            .bind(next_attempt_in.num_seconds())
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            warn!(
+                "Payout for deposit {} failed (attempt {}), retrying in {}s: {}",
+                transaction.id,
+                job.attempts,
+                next_attempt_in.num_seconds(),
+                e
+            );
+        }
+    }
+    Ok(true)
+}
+
+/// What the ledger holds for a deposit: sats credited to its wallet and not yet paid out,
+/// and sats booked as sent over Lightning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepositBooking {
+    pub in_wallet: Sats,
+    pub sent: Sats,
+}
+
+/// Sums the postings booked for a deposit so far. Call with its transaction row locked.
+pub async fn booking(
+    tx: &mut DbTransaction<'_, Any>,
+    transaction: &Transaction,
+) -> Result<DepositBooking, AppError> {
+    let (in_wallet, sent): (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            COALESCE(SUM(CASE WHEN la.wallet_id = $2 THEN lp.amount_sats ELSE 0 END), 0)::BIGINT,
+            COALESCE(SUM(CASE WHEN la.code = $3 THEN lp.amount_sats ELSE 0 END), 0)::BIGINT
+        FROM journal_entries je
+        JOIN ledger_postings lp ON lp.journal_entry_id = je.id
+        JOIN ledger_accounts la ON la.id = lp.account_id
+        WHERE je.transaction_id = $1
+        "#,
+    )
+    .bind(transaction.id)
+    .bind(transaction.wallet_id)
+    .bind(LIGHTNING_ACCOUNT)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(DepositBooking {
+        in_wallet: Sats(in_wallet),
+        sent: Sats(sent),
+    })
+}
+
+/// Books a deposit paid out over Lightning, by the worker or by an admin's manual release.
+/// Only what is not yet booked as sent is booked: out of the wallet as far as the deposit was
+/// credited there, and out of the treasury for a deposit that was held before it was credited.
+/// The treasury also absorbs the routing fee.
+pub fn payout_entry(
+    entry_type: &str,
+    transaction: &Transaction,
+    booking: DepositBooking,
+    fee_sats: Sats,
+) -> JournalEntry {
+    let unsent = (transaction.amount_sats.0 - booking.sent.0).max(0);
+    let from_wallet = unsent.min(booking.in_wallet.0.max(0));
+
+    JournalEntry::new(entry_type)
+        .for_transaction(transaction.id)
+        .transfer(
+            AccountRef::Wallet(transaction.wallet_id),
+            AccountRef::System(LIGHTNING_ACCOUNT),
+            Sats(from_wallet),
+        )
+        .transfer(
+            AccountRef::System(TREASURY_ACCOUNT),
+            AccountRef::System(LIGHTNING_ACCOUNT),
+            Sats(unsent - from_wallet),
+        )
+        .transfer(
+            AccountRef::System(TREASURY_ACCOUNT),
+            AccountRef::System(FEES_ACCOUNT),
+            fee_sats,
+        )
+}
+
+/// Posts `payout_entry` for the deposit's current booking and returns it, so a payment booked before
+/// sending can be reversed if it does not go out. Fails if the wallet no longer holds the sats.
+pub async fn post_payout(
+    tx: &mut DbTransaction<'_, Any>,
+    entry_type: &str,
+    transaction: &Transaction,
+    fee_sats: Sats,
+    recipient: &str,
+) -> Result<JournalEntry, AppError> {
+    let booking = booking(tx, transaction).await?;
+    let entry = payout_entry(entry_type, transaction, booking, fee_sats)
+        .with_description(&format!("Payout to {}", recipient));
+    post_if_any(tx, &entry).await?;
+    Ok(entry)
+}
+
+/// Posts an entry unless there was nothing to book, e.g. the fee of a free payment.
+pub async fn post_if_any(tx: &mut DbTransaction<'_, Any>, entry: &JournalEntry) -> Result<(), AppError> {
+    if !entry.postings.is_empty() {
+        ledger_service::post_entry(tx, entry).await?;
+    }
+    Ok(())
+}
+
+/// A job claimed for sending, with the deposit's sats already booked out of its wallet.
+struct PayoutClaim {
+    job: PayoutJob,
+    transaction: Transaction,
+    destination: String,
+    debit: JournalEntry,
+}
+
+/// Marks the next due job 'sending', books the deposit out of its wallet and commits, so no other
+/// worker picks it up and the user cannot spend the sats while they are in flight.
+/// Jobs whose transaction is no longer pending (released or refunded by an admin), or is being paid
+/// by an approved release, are cancelled instead. Jobs that cannot be paid are held without sending:
+/// the wallet has no payout destination, or no longer holds the deposit.
+async fn claim_next(app_state: &AppState) -> Result<Option<PayoutClaim>, AppError> {
+    loop {
+        let mut tx = app_state.db_pool.begin().await?;
+        let job: Option<PayoutJob> = sqlx::query_as(&format!(
+            r#"
+            UPDATE payout_jobs SET status = 'sending', attempts = attempts + 1, updated_at = NOW()
+            WHERE id = (
+                SELECT id FROM payout_jobs
+                WHERE status = 'queued' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING {}
+            "#,
+            PAYOUT_JOB_COLUMNS
+        ))
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(job) = job else {
+            return Ok(None);
+        };
+
+        let transaction = transaction_service::lock(&mut tx, job.transaction_id).await?;
+        let release_executing: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM release_requests WHERE transaction_id = $1 AND status = 'executing')",
+        )
+        .bind(transaction.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if transaction.status != TransactionStatus::Pending || release_executing {
+            sqlx::query("UPDATE payout_jobs SET status = 'cancelled', updated_at = NOW() WHERE id = $1")
+                .bind(job.id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            info!(
+                "Payout for deposit {} cancelled: transaction is {}{}",
+                transaction.id,
+                transaction.status,
+                if release_executing { " and an approved release is paying it" } else { "" }
+            );
+            continue;
+        }
+
+        let Some(destination) = payout_destination(&mut tx, transaction.wallet_id).await? else {
+            tx.rollback().await?;
+            hold(app_state, &job, "The wallet has no payout destination", None).await?;
+            continue;
+        };
+        let debit = match post_payout(&mut tx, "deposit_payout", &transaction, Sats::ZERO, &destination).await {
+            Ok(debit) => debit,
+            Err(AppError::BadRequest(reason)) => {
+                // The user spent the deposit before it was forwarded
+                tx.rollback().await?;
+                hold(app_state, &job, &reason, None).await?;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        tx.commit().await?;
+        return Ok(Some(PayoutClaim {
+            job,
+            transaction,
+            destination,
+            debit,
+        }));
+    }
+}
+
+async fn payout_destination(tx: &mut DbTransaction<'_, Any>, wallet_id: Uuid) -> Result<Option<String>, AppError> {
+    let destination: Option<String> = sqlx::query_scalar("SELECT payout_destination FROM wallets WHERE id = $1")
+        .bind(wallet_id)
+        .fetch_one(&mut **tx)
+        .await?;
+    Ok(destination)
+}
+
+/// Puts the deposit on `admin_hold`, where it shows up for manual release, and alerts the admins.
+/// `reversal` returns a claimed deposit to its wallet when its payment did not go out.
+async fn hold(
+    app_state: &AppState,
+    job: &PayoutJob,
+    reason: &str,
+    reversal: Option<&JournalEntry>,
+) -> Result<(), AppError> {
+    let mut tx = app_state.db_pool.begin().await?;
+    let mut transaction = transaction_service::lock(&mut tx, job.transaction_id).await?;
+    if let Some(debit) = reversal {
+        post_if_any(&mut tx, &debit.reversed("deposit_payout_reversal")).await?;
+    }
+    if transaction.status == TransactionStatus::Pending {
+        transaction_service::transition(&mut tx, &mut transaction, TransactionStatus::AdminHold).await?;
+    }
+    sqlx::query("UPDATE payout_jobs SET status = 'held', last_error = $2, updated_at = NOW() WHERE id = $1")
+        .bind(job.id)
+        .bind(reason)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    error!("Payout for deposit {} put on admin hold: {}", transaction.id, reason);
+    alert_service::spawn_admin_alert(
+        app_state.config.clone(),
+        "Deposit payout needs manual release".to_string(),
+        format!(
+            "The Lightning payout of {} for deposit {} (wallet {}) did not go through and is on admin hold.\n\
+             {}\nCheck the node for a payment before releasing it manually.",
+            transaction.amount_sats, transaction.id, transaction.wallet_id, reason
+        ),
+    );
+    Ok(())
+}
+
+/// Holds jobs left 'sending' by a crash or restart. Whether their payment went out is unknown,
+/// so retrying could pay twice; an admin has to check and release them. Their sats stay booked as
+/// sent, so a release only books what is still unsent.
+async fn hold_interrupted(app_state: &AppState) -> Result<(), AppError> {
+    let stale: Vec<PayoutJob> = sqlx::query_as(&format!(
+        "SELECT {} FROM payout_jobs WHERE status = 'sending' AND updated_at < NOW() - $1 * INTERVAL '1 minute'",
+        PAYOUT_JOB_COLUMNS
+    ))
+    .bind(SENDING_TIMEOUT_MINUTES)
+    .fetch_all(&app_state.db_pool)
+    .await?;
+
+    for job in stale {
+        hold(app_state, &job, "Interrupted while sending; the payment may or may not have gone out", None).await?;
+    }
+    Ok(())
+}
+
+/// Blocks until `wake` is called or the poll interval passes.
+async fn wait_for_work(redis_client: &RedisClient) {
+    let result: Result<(), AppError> = async {
+        let mut con = redis_client.get_async_connection().await?;
+        let _: Option<(String, String)> = con.blpop(WAKE_KEY, POLL_INTERVAL.as_secs_f64()).await?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        warn!("Payout worker cannot wait on Redis, polling instead: {:?}", e);
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Exponential backoff: 30s, 60s, 120s, ... capped at one hour.
+fn backoff_for(attempts: i32) -> ChronoDuration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    let seconds = BASE_BACKOFF_SECONDS
+        .saturating_mul(2i64.saturating_pow(exponent))
+        .min(MAX_BACKOFF_SECONDS);
+    ChronoDuration::seconds(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::transaction::TransactionType;
+    use chrono::Utc;
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        assert_eq!(backoff_for(1), ChronoDuration::seconds(30));
+        assert_eq!(backoff_for(3), ChronoDuration::seconds(120));
+        assert_eq!(backoff_for(100), ChronoDuration::seconds(MAX_BACKOFF_SECONDS));
+    }
+
+    fn deposit(amount_sats: i64) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            wallet_id: Uuid::new_v4(),
+            tx_type: TransactionType::FiatDeposit,
+            amount_sats: Sats(amount_sats),
+            fee_sats: Sats::ZERO,
+            status: TransactionStatus::Pending,
+            description: None,
+            external_id: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn sum(entry: &JournalEntry, account: AccountRef) -> i64 {
+        entry.postings.iter().filter(|p| p.account == account).map(|p| p.amount.0).sum()
+    }
+
+    #[test]
+    fn test_credited_deposit_is_paid_out_of_its_wallet() {
+        let transaction = deposit(10_000);
+        let credited = DepositBooking { in_wallet: Sats(10_000), sent: Sats::ZERO };
+        let entry = payout_entry("deposit_payout", &transaction, credited, Sats::ZERO);
+
+        assert!(entry.validate().is_ok());
+        assert_eq!(entry.credited_wallets(), vec![transaction.wallet_id]);
+        assert_eq!(sum(&entry, AccountRef::Wallet(transaction.wallet_id)), -10_000);
+        assert_eq!(sum(&entry, AccountRef::System(TREASURY_ACCOUNT)), 0);
+    }
+
+    #[test]
+    fn test_held_deposit_is_paid_from_treasury() {
+        let transaction = deposit(10_000);
+        let uncredited = DepositBooking { in_wallet: Sats::ZERO, sent: Sats::ZERO };
+        let entry = payout_entry("manual_release", &transaction, uncredited, Sats(12));
+
+        assert!(entry.validate().is_ok());
+        assert!(entry.credited_wallets().is_empty());
+        assert_eq!(sum(&entry, AccountRef::System(TREASURY_ACCOUNT)), -10_012);
+    }
+
+    #[test]
+    fn test_only_the_fee_is_booked_once_the_deposit_is_booked_as_sent() {
+        let transaction = deposit(10_000);
+        let sent = DepositBooking { in_wallet: Sats::ZERO, sent: Sats(10_000) };
+
+        let entry = payout_entry("deposit_payout_fee", &transaction, sent, Sats(12));
+        assert_eq!(sum(&entry, AccountRef::System(FEES_ACCOUNT)), 12);
+        assert_eq!(sum(&entry, AccountRef::System(LIGHTNING_ACCOUNT)), 0);
+
+        assert!(payout_entry("deposit_payout_fee", &transaction, sent, Sats::ZERO).postings.is_empty());
+    }
+}
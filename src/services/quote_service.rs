@@ -0,0 +1,406 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use secrecy::ExposeSecret;
+use serde::Serialize;
+use sqlx::{Any, Transaction as DbTransaction};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        models::Quote,
+        money::{self, ExchangeRate, Rounding},
+        quote::QuoteSide,
+        types::{Kobo, Sats},
+    },
+    error::AppError,
+    paystack,
+    utils::signature::{sign_hmac_sha256, verify_hmac_sha256},
+};
+
+/// Smallest sell payout worth a bank transfer: ₦100.
+pub const MIN_PAYOUT: Kobo = Kobo(10_000);
+
+const QUOTE_COLUMNS: &str = "id, side, market_rate, rate, spread_bps, amount_kobo, fee_kobo, amount_sats, expires_at, used_at, used_reference, created_at";
+
+/// What a client wants priced: the Naira it will pay in, or the sats it will sell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeAmount {
+    Buy(Kobo),
+    Sell(Sats),
+}
+
+impl TradeAmount {
+    pub fn side(&self) -> QuoteSide {
+        match self {
+            TradeAmount::Buy(_) => QuoteSide::Buy,
+            TradeAmount::Sell(_) => QuoteSide::Sell,
+        }
+    }
+}
+
+/// The terms of a trade at one rate.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuoteTerms {
+    pub side: QuoteSide,
+    pub market_rate: ExchangeRate,
+    pub rate: ExchangeRate, // The market rate with the spread taken off, in Sabi's favour
+    pub spread_bps: i64,
+    pub amount_kobo: Kobo, // Buy: paid in. Sell: what the sats are worth at `rate`
+    pub fee_kobo: Kobo,
+    pub net_kobo: Kobo, // After the fee. Buy: converted to sats. Sell: paid to the bank
+    pub amount_sats: Sats, // Buy: paid out. Sell: sold
+}
+
+/// How a trade was priced.
+#[derive(Debug, Clone)]
+pub struct Execution {
+    pub terms: QuoteTerms,
+    pub quote_id: Option<Uuid>, // The quote honoured, if any
+    /// The client had a quote that could not be honoured, and the re-quote is worse for them
+    /// than it by more than `QUOTE_SLIPPAGE_BPS`.
+    pub slippage_exceeded: bool,
+}
+
+/// Prices `amount` at `market_rate` less `spread_bps`. Amounts paid out are rounded down;
+/// a sale passes Paystack's transfer fee on.
+pub fn price(amount: TradeAmount, market_rate: &ExchangeRate, spread_bps: i64) -> Result<QuoteTerms, AppError> {
+    match amount {
+        TradeAmount::Buy(amount_kobo) => {
+            if amount_kobo.0 <= 0 {
+                return Err(AppError::BadRequest("amount_kobo must be positive".to_string()));
+            }
+            // More Naira per BTC means fewer sats for the buyer
+            let rate = market_rate.adjusted_by_bps(spread_bps)?;
+            let amount_sats = money::kobo_to_sats(amount_kobo, &rate, Rounding::Floor)?;
+            if amount_sats.0 <= 0 {
+                return Err(AppError::BadRequest(format!("{} buys less than one sat", amount_kobo)));
+            }
+            Ok(QuoteTerms {
+                side: QuoteSide::Buy,
+                market_rate: *market_rate,
+                rate,
+                spread_bps,
+                amount_kobo,
+                fee_kobo: Kobo::ZERO,
+                net_kobo: amount_kobo,
+                amount_sats,
+            })
+        }
+        TradeAmount::Sell(amount_sats) => {
+            if amount_sats.0 <= 0 {
+                return Err(AppError::BadRequest("amount_sats must be positive".to_string()));
+            }
+            let rate = market_rate.adjusted_by_bps(-spread_bps)?;
+            let amount_kobo = money::sats_to_kobo(amount_sats, &rate, Rounding::Floor)?;
+            let fee_kobo = paystack::transfer_fee(amount_kobo);
+            let net_kobo = amount_kobo.checked_sub(fee_kobo).unwrap_or(Kobo::ZERO);
+            if net_kobo < MIN_PAYOUT {
+                return Err(AppError::BadRequest(format!(
+                    "{} sats pays out less than the ₦{} minimum after the ₦{} transfer fee",
+                    amount_sats.0,
+                    MIN_PAYOUT.to_naira(),
+                    fee_kobo.to_naira()
+                )));
+            }
+            Ok(QuoteTerms {
+                side: QuoteSide::Sell,
+                market_rate: *market_rate,
+                rate,
+                spread_bps,
+                amount_kobo,
+                fee_kobo,
+                net_kobo,
+                amount_sats,
+            })
+        }
+    }
+}
+
+/// Whether `fresh` is worse for the client than `quoted` by more than `tolerance_bps`.
+/// Buyers lose when the rate rises, sellers when it falls; moves in their favour never count.
+pub fn slippage_exceeded(side: QuoteSide, quoted: &ExchangeRate, fresh: &ExchangeRate, tolerance_bps: i64) -> bool {
+    let worse = match side {
+        QuoteSide::Buy => fresh > quoted,
+        QuoteSide::Sell => fresh < quoted,
+    };
+    worse && fresh.deviation_bps(quoted) > tolerance_bps as i128
+}
+
+/// The quote ID handed to clients: `{uuid}.{hex HMAC-SHA256}` keyed with `APP_SECRET_KEY`,
+/// so IDs cannot be guessed or enumerated.
+pub fn sign_quote_id(secret: &[u8], quote_id: Uuid) -> String {
+    format!("{}.{}", quote_id, sign_hmac_sha256(secret, signed_payload(quote_id).as_bytes()))
+}
+
+/// Checks a signed quote ID and returns the quote's UUID.
+pub fn verify_quote_id(secret: &[u8], signed_id: &str) -> Result<Uuid, AppError> {
+    let invalid = || AppError::BadRequest("Invalid quote_id".to_string());
+    let (id, signature) = signed_id.trim().split_once('.').ok_or_else(invalid)?;
+    let quote_id = Uuid::parse_str(id).map_err(|_| invalid())?;
+    if !verify_hmac_sha256(secret, signed_payload(quote_id).as_bytes(), signature) {
+        return Err(invalid());
+    }
+    Ok(quote_id)
+}
+
+fn signed_payload(quote_id: Uuid) -> String {
+    format!("quote:{}", quote_id)
+}
+
+/// Locks a price for `QUOTE_TTL_SECONDS`. Returns the quote and its signed ID.
+/// Only buys are quoted: nothing redeems a sell quote until the off-ramp is back.
+pub async fn create(app_state: &AppState, amount: TradeAmount) -> Result<(Quote, String), AppError> {
+    if amount.side() == QuoteSide::Sell {
+        return Err(AppError::BadRequest("Sell quotes are not available".to_string()));
+    }
+    let market_rate = app_state.rate_oracle.current_rate().await?.naira_per_btc;
+    let terms = price(amount, &market_rate, app_state.config.quote_spread_bps)?;
+    let expires_at = Utc::now() + ChronoDuration::seconds(app_state.config.quote_ttl_seconds);
+
+    let quote: Quote = sqlx::query_as(&format!(
+        r#"
+        INSERT INTO quotes (id, side, market_rate, rate, spread_bps, amount_kobo, fee_kobo, amount_sats, expires_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
+        RETURNING {}
+        "#,
+        QUOTE_COLUMNS
+    ))
+    .bind(Uuid::new_v4())
+    .bind(terms.side)
+    .bind(terms.market_rate.to_string())
+    .bind(terms.rate.to_string())
+    .bind(terms.spread_bps)
+    .bind(terms.amount_kobo.0)
+    .bind(terms.fee_kobo.0)
+    .bind(terms.amount_sats.0)
+    .bind(expires_at)
+    .fetch_one(&app_state.db_pool)
+    .await?;
+
+    info!(
+        "Quoted {} of {} sats for {} at {} (expires {})",
+        quote.side, terms.amount_sats.0, terms.amount_kobo, terms.rate, quote.expires_at
+    );
+    let signed_id = sign_quote_id(app_state.config.app_secret_key.expose_secret().as_bytes(), quote.id);
+    Ok((quote, signed_id))
+}
+
+/// Loads a quote by its signed ID and locks it until the caller's transaction ends.
+pub async fn lock(tx: &mut DbTransaction<'_, Any>, app_state: &AppState, signed_id: &str) -> Result<Quote, AppError> {
+    let quote_id = verify_quote_id(app_state.config.app_secret_key.expose_secret().as_bytes(), signed_id)?;
+    sqlx::query_as(&format!("SELECT {} FROM quotes WHERE id = $1 FOR UPDATE", QUOTE_COLUMNS))
+        .bind(quote_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Quote {} not found", quote_id)))
+}
+
+/// Prices a trade made at `traded_at`, inside the caller's transaction.
+///
+/// `quote` is honoured, and marked used by `reference`, if it is for this side and amount,
+/// unused, and `traded_at` is before it expired. Otherwise the trade is re-quoted at the current
+/// rate, and `slippage_exceeded` tells the caller whether that is too far from what the client saw.
+/// `current_rate` must be fetched before the caller's transaction began, so no lock is held
+/// while a rate source answers; its error is only returned if a re-quote needs it.
+pub async fn execute(
+    tx: &mut DbTransaction<'_, Any>,
+    app_state: &AppState,
+    amount: TradeAmount,
+    quote: Option<&Quote>,
+    traded_at: DateTime<Utc>,
+    reference: &str,
+    current_rate: Result<ExchangeRate, AppError>,
+) -> Result<Execution, AppError> {
+    if let Some(quote) = quote {
+        match unusable_reason(quote, amount, traded_at) {
+            None => {
+                sqlx::query("UPDATE quotes SET used_at = NOW(), used_reference = $2 WHERE id = $1")
+                    .bind(quote.id)
+                    .bind(reference)
+                    .execute(&mut **tx)
+                    .await?;
+                info!("Honouring quote {} for {}", quote.id, reference);
+                return Ok(Execution {
+                    terms: terms_of(quote)?,
+                    quote_id: Some(quote.id),
+                    slippage_exceeded: false,
+                });
+            }
+            Some(reason) => info!("Quote {} cannot be honoured for {}: {}; re-quoting", quote.id, reference, reason),
+        }
+    }
+
+    let market_rate = current_rate?;
+    let terms = price(amount, &market_rate, app_state.config.quote_spread_bps)?;
+
+    let slippage_exceeded = match quote {
+        Some(quote) if quote.side == amount.side() => {
+            let quoted_rate: ExchangeRate = quote.rate.parse()?;
+            let exceeded = slippage_exceeded(
+                quote.side,
+                &quoted_rate,
+                &terms.rate,
+                app_state.config.quote_slippage_bps,
+            );
+            if exceeded {
+                warn!(
+                    "Re-quote for {} at {} is more than {} bps worse than quote {} at {}",
+                    reference, terms.rate, app_state.config.quote_slippage_bps, quote.id, quoted_rate
+                );
+            }
+            exceeded
+        }
+        _ => false,
+    };
+
+    Ok(Execution {
+        terms,
+        quote_id: None,
+        slippage_exceeded,
+    })
+}
+
+/// Why `quote` cannot be honoured for `amount` at `traded_at`, or `None` if it can.
+fn unusable_reason(quote: &Quote, amount: TradeAmount, traded_at: DateTime<Utc>) -> Option<&'static str> {
+    let same_amount = match amount {
+        TradeAmount::Buy(amount_kobo) => quote.amount_kobo == amount_kobo,
+        TradeAmount::Sell(amount_sats) => quote.amount_sats == amount_sats,
+    };
+    if quote.side != amount.side() {
+        Some("it is for the other side")
+    } else if !same_amount {
+        Some("it is for a different amount")
+    } else if quote.used_at.is_some() {
+        Some("it was already used")
+    } else if traded_at > quote.expires_at {
+        Some("it expired")
+    } else {
+        None
+    }
+}
+
+fn terms_of(quote: &Quote) -> Result<QuoteTerms, AppError> {
+    let net_kobo = quote
+        .amount_kobo
+        .checked_sub(quote.fee_kobo)
+        .ok_or_else(|| AppError::Internal(format!("Quote {} has an invalid fee", quote.id)))?;
+    Ok(QuoteTerms {
+        side: quote.side,
+        market_rate: quote.market_rate.parse()?,
+        rate: quote.rate.parse()?,
+        spread_bps: quote.spread_bps,
+        amount_kobo: quote.amount_kobo,
+        fee_kobo: quote.fee_kobo,
+        net_kobo,
+        amount_sats: quote.amount_sats,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(naira_per_btc: u64) -> ExchangeRate {
+        ExchangeRate::from_naira_per_btc(naira_per_btc).unwrap()
+    }
+
+    #[test]
+    fn test_sale_is_priced_down_and_pays_the_transfer_fee() {
+        // 100,000 sats = ₦150,000 exactly; the top fee tier applies
+        let terms = price(TradeAmount::Sell(Sats(100_000)), &rate(150_000_000), 0).unwrap();
+        assert_eq!(terms.amount_kobo, Kobo(15_000_000));
+        assert_eq!(terms.fee_kobo, Kobo(5_000));
+        assert_eq!(terms.net_kobo, Kobo(14_995_000));
+
+        // 1 sat = 150 Kobo; 333 sats = 49,950 Kobo, never rounded up
+        let terms = price(TradeAmount::Sell(Sats(333)), &rate(150_000_000), 0).unwrap();
+        assert_eq!(terms.amount_kobo, Kobo(49_950));
+        assert_eq!(terms.net_kobo, Kobo(48_950));
+    }
+
+    #[test]
+    fn test_sales_below_the_minimum_payout_are_refused() {
+        // ₦105 worth leaves ₦95 after the ₦10 fee
+        assert!(matches!(
+            price(TradeAmount::Sell(Sats(70)), &rate(150_000_000), 0),
+            Err(AppError::BadRequest(_))
+        ));
+        assert!(price(TradeAmount::Sell(Sats(74)), &rate(150_000_000), 0).is_ok());
+        assert!(price(TradeAmount::Sell(Sats(0)), &rate(150_000_000), 0).is_err());
+    }
+
+    #[test]
+    fn test_spread_is_taken_off_both_sides() {
+        let market = rate(150_000_000);
+
+        // Buyers pay 1% more Naira per BTC: ₦15,000 buys 9,900 sats instead of 10,000
+        let buy = price(TradeAmount::Buy(Kobo(1_500_000)), &market, 100).unwrap();
+        assert_eq!(buy.rate, rate(151_500_000));
+        assert_eq!(buy.amount_sats, Sats(9_900));
+        assert_eq!(buy.fee_kobo, Kobo::ZERO);
+
+        // Sellers get 1% fewer: 100,000 sats is worth ₦148,500
+        let sell = price(TradeAmount::Sell(Sats(100_000)), &market, 100).unwrap();
+        assert_eq!(sell.rate, rate(148_500_000));
+        assert_eq!(sell.amount_kobo, Kobo(14_850_000));
+    }
+
+    #[test]
+    fn test_only_adverse_moves_beyond_the_tolerance_count_as_slippage() {
+        let quoted = rate(150_000_000);
+
+        assert!(!slippage_exceeded(QuoteSide::Buy, &quoted, &rate(151_500_000), 100));
+        assert!(slippage_exceeded(QuoteSide::Buy, &quoted, &rate(151_515_000), 100));
+        assert!(!slippage_exceeded(QuoteSide::Buy, &quoted, &rate(140_000_000), 100));
+
+        assert!(!slippage_exceeded(QuoteSide::Sell, &quoted, &rate(148_500_000), 100));
+        assert!(slippage_exceeded(QuoteSide::Sell, &quoted, &rate(148_485_000), 100));
+        assert!(!slippage_exceeded(QuoteSide::Sell, &quoted, &rate(160_000_000), 100));
+    }
+
+    #[test]
+    fn test_quote_ids_are_signed() {
+        let id = Uuid::new_v4();
+        let signed = sign_quote_id(b"app-secret", id);
+
+        assert_eq!(verify_quote_id(b"app-secret", &signed).unwrap(), id);
+        assert!(verify_quote_id(b"other-secret", &signed).is_err());
+        assert!(verify_quote_id(b"app-secret", &id.to_string()).is_err());
+
+        let forged = format!("{}.{}", Uuid::new_v4(), signed.split_once('.').unwrap().1);
+        assert!(verify_quote_id(b"app-secret", &forged).is_err());
+    }
+
+    #[test]
+    fn test_quotes_are_honoured_once_before_expiry_for_their_own_trade() {
+        let now = Utc::now();
+        let quote = Quote {
+            id: Uuid::new_v4(),
+            side: QuoteSide::Buy,
+            market_rate: "150000000".to_string(),
+            rate: "150000000".to_string(),
+            spread_bps: 0,
+            amount_kobo: Kobo(1_500_000),
+            fee_kobo: Kobo::ZERO,
+            amount_sats: Sats(10_000),
+            expires_at: now,
+            used_at: None,
+            used_reference: None,
+            created_at: now - ChronoDuration::seconds(300),
+        };
+        let buy = TradeAmount::Buy(Kobo(1_500_000));
+
+        assert_eq!(unusable_reason(&quote, buy, now), None);
+        assert!(unusable_reason(&quote, buy, now + ChronoDuration::seconds(1)).is_some());
+        assert!(unusable_reason(&quote, TradeAmount::Buy(Kobo(1_500_100)), now).is_some());
+        assert!(unusable_reason(&quote, TradeAmount::Sell(Sats(10_000)), now).is_some());
+
+        let used = Quote {
+            used_at: Some(now),
+            ..quote.clone()
+        };
+        assert!(unusable_reason(&used, buy, now).is_some());
+        assert_eq!(terms_of(&quote).unwrap().net_kobo, Kobo(1_500_000));
+    }
+}
@@ -1,95 +1,186 @@
-use anyhow::{anyhow, Result};
+use redis::AsyncCommands;
 use secrecy::ExposeSecret;
-use tracing::info;
-use uuid::Uuid;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Any, Transaction as DbTransaction};
 use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
 
 use crate::{
     app_state::AppState,
-    domain::models::EncryptedNostrNsec,
+    domain::{
+        audit::{AuditAction, AuditActor, AuditEvent},
+        recovery::{self, RecoveryPolicy, RecoveryShare},
+    },
     error::AppError,
     nostr::client::NostrClient,
+    services::{audit_service, nsec_service},
 };
+use nostr_sdk::key::PublicKey;
 use nostr_sdk::nostr::key::FromSkStr;
-use nostr_sdk::nostr::nips::nip04::decrypt;
+use nostr_sdk::nostr::nips::nip19::ToBech32;
 use nostr_sdk::nostr::prelude::SecretKey;
-use nostr_sdk::key::PublicKey;
 
+const RECOVERY_SESSION_TTL_SECONDS: u64 = 7 * 24 * 3600; // 7 days
 
-/// Initiates the social recovery process by sending encrypted Shamir shares
-/// to designated helpers via Nostr DMs.
-/// The `wallet_id` is the ID of the wallet that needs recovery.
-/// `helper_npubs` are the Nostr public keys of the designated helpers.
-pub async fn initiate_recovery_request(
-    app_state: Arc<AppState>,
-    wallet_id_str: &str,
+/// An in-flight recovery of a wallet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecoverySession {
+    recovery_id: Uuid,
+    policy: RecoveryPolicy,
+    device_npub: String, // The owner's new device, which receives the rebuilt key
+}
+
+/// Progress of a recovery after a share is submitted.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryProgress {
+    pub shares_collected: usize,
+    pub threshold: u8,
+    /// Enough valid shares came back to rebuild the wallet key, and it was sent to the owner's new device.
+    pub key_delivered: bool,
+}
+
+/// Records the helpers chosen when a wallet's social backup is set up, one per share in its policy.
+/// Recovery only ever sends shares to, and accepts shares from, these helpers.
+pub async fn store_helpers(
+    tx: &mut DbTransaction<'_, Any>,
+    wallet_id: Uuid,
+    policy: RecoveryPolicy,
     helper_npubs: &[String],
 ) -> Result<(), AppError> {
-    info!("Initiating recovery request for wallet: {}", wallet_id_str);
+    policy.check_helpers(helper_npubs)?;
+    parse_helpers(helper_npubs)?;
 
-    let wallet_id = Uuid::parse_str(wallet_id_str)
-        .map_err(|e| AppError::BadRequest(format!("Invalid wallet ID: {}", e)))?;
+    for (i, npub) in helper_npubs.iter().enumerate() {
+        // Helper `i` holds share index `i + 1`
+        sqlx::query(
+            "INSERT INTO wallet_recovery_helpers (wallet_id, share_index, helper_npub, created_at) VALUES ($1, $2, $3, NOW())",
+        )
+        .bind(wallet_id)
+        .bind(i as i16 + 1)
+        .bind(npub)
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Initiates the social recovery process by sending encrypted Shamir shares
+/// to the wallet's helpers via Nostr DMs.
+/// Only an operator can start a recovery, after verifying the wallet's owner out of band;
+/// `notes` says how, and goes into the audit log. The rebuilt key goes only to `device_npub`,
+/// the Nostr key of the owner's new device, confirmed with the owner during that verification.
+/// Returns the new recovery's ID.
+pub async fn initiate_recovery_request(
+    app_state: &AppState,
+    actor: &AuditActor,
+    wallet_id: Uuid,
+    device_npub: &str,
+    notes: &str,
+) -> Result<Uuid, AppError> {
+    info!("Initiating recovery request for wallet: {}", wallet_id);
+
+    PublicKey::from_bech32(device_npub)
+        .map_err(|e| AppError::BadRequest(format!("Invalid device npub '{}': {}", device_npub, e)))?;
+
+    // 1. Load the wallet's k-of-n policy and the helpers stored when its backup was set up.
+    let policy = wallet_recovery_policy(app_state, wallet_id).await?;
+    let helper_npubs = wallet_helpers(app_state, wallet_id).await?;
+    if helper_npubs.len() != policy.share_count as usize {
+        return Err(AppError::Conflict(format!(
+            "Wallet {} has no social recovery helpers on file",
+            wallet_id
+        )));
+    }
+    let helper_pubkeys = parse_helpers(&helper_npubs)?;
 
-    // 1. Retrieve the encrypted nsec from the database for the given wallet.
-    let encrypted_nsec_record: EncryptedNostrNsec = sqlx::query_as::<_, EncryptedNostrNsec>(
-        "SELECT id, wallet_id, encrypted_nsec, created_at FROM encrypted_nostr_nsecs WHERE wallet_id = $1"
+    // 2. Retrieve and decrypt the wallet's nsec.
+    let nsec = nsec_service::load_nsec(
+        &app_state.db_pool,
+        app_state.key_provider.as_ref(),
+        wallet_id,
     )
-    .bind(wallet_id)
-    .fetch_one(&app_state.db_pool)
-    .await
-    .map_err(|e| AppError::NotFound(format!("Encrypted nsec not found for wallet {}: {}", wallet_id, e)))?;
-
-    // TODO: Decrypt the nsec using a key derived from a master secret or KMS.
-    // For now, let's assume `encrypted_nsec_record.encrypted_nsec` is the actual nsec for simplicity,
-    // but in production, this MUST be a proper decryption.
-    let nsec_secret_key = SecretKey::from_sk_str(&encrypted_nsec_record.encrypted_nsec)
+    .await?;
+    let wallet_secret_key = SecretKey::from_sk_str(nsec.expose_secret())
         .map_err(|e| AppError::Internal(format!("Failed to parse nsec for recovery: {}", e)))?;
-    let recovery_coordinator_keys = nostr_sdk::Keys::new(nsec_secret_key);
-
-    // 2. Split the nsec into Shamir shares (e.g., 3-of-5).
-    // This is a placeholder for `shamir` crate usage.
-    // TODO: Implement shamir secret sharing when library is available
-    let shares = vec![];
-    if shares.is_empty() {
-        return Err(AppError::Internal("Shamir sharing not yet implemented".to_string()));
-    }
 
-    // 3. For each helper, encrypt a share with their public key and send via Nostr DM.
-    let nostr_client = NostrClient::new(&app_state.config.nostr_relays)?;
+    // 3. Split the nsec bytes into shares tagged with a fresh recovery ID.
+    let recovery_id = Uuid::new_v4();
+    let shares = recovery::split_secret(
+        &wallet_secret_key.secret_bytes(),
+        wallet_id,
+        recovery_id,
+        policy,
+    )?;
+
+    // Starting a new recovery invalidates shares collected for any previous one.
+    let session = RecoverySession {
+        recovery_id,
+        policy,
+        device_npub: device_npub.to_string(),
+    };
+    let mut con = app_state.redis_client.get_async_connection().await?;
+    let _: () = con.del(shares_key(wallet_id)).await?;
+    let _: () = con
+        .set_ex(
+            session_key(wallet_id),
+            serde_json::to_string(&session)?,
+            RECOVERY_SESSION_TTL_SECONDS,
+        )
+        .await?;
 
-    for (i, npub_str) in helper_npubs.iter().enumerate() {
-        let helper_pubkey = PublicKey::from_bech32(npub_str)
-            .map_err(|e| AppError::BadRequest(format!("Invalid helper npub '{}': {}", npub_str, e)))?;
+    // 4. For each helper, encrypt their share from the coordinator key and send it as a NIP-04 DM.
+    let coordinator_keys = coordinator_keys(app_state)?;
+    let coordinator_secret = coordinator_keys
+        .secret_key()
+        .map_err(|e| AppError::Internal(format!("Coordinator keys have no secret key: {}", e)))?;
+    let nostr_client = NostrClient::new(&app_state.config.nostr_relays)?;
 
-        // Encrypt the share using NIP-04 (shared secret encryption)
+    for ((share, helper_pubkey), npub_str) in shares.iter().zip(helper_pubkeys).zip(&helper_npubs) {
         let encrypted_share = nostr_sdk::nostr::nips::nip04::encrypt(
-            recovery_coordinator_keys.secret_key().unwrap(),
+            &coordinator_secret,
             &helper_pubkey,
-            &hex::encode(&shares[i]), // Send the i-th share
+            serde_json::to_string(share)?,
         )
-        .map_err(|e| AppError::Internal(format!("Failed to encrypt share for helper {}: {}", npub_str, e)))?;
+        .map_err(|e| {
+            AppError::Internal(format!(
+                "Failed to encrypt share for helper {}: {}",
+                npub_str, e
+            ))
+        })?;
 
-        // Send the encrypted share as a NIP-04 DM
-        nostr_client.send_dm(
-            &recovery_coordinator_keys, // Pass the Keys object
-            helper_pubkey,
-            &encrypted_share,
-        ).await?;
+        nostr_client
+            .send_dm(&coordinator_keys, helper_pubkey, &encrypted_share)
+            .await?;
 
-        info!("Sent share {} to helper {}", i + 1, npub_str);
+        info!(
+            "Sent share {} of recovery {} to helper {}",
+            share.index, recovery_id, npub_str
+        );
     }
 
-    Ok(())
+    audit_service::record_now(
+        &app_state.db_pool,
+        AuditEvent::new(actor, AuditAction::RecoveryStart)
+            .target("wallet", wallet_id)
+            .after(&json!({ "recovery_id": recovery_id, "policy": policy, "device_npub": device_npub, "notes": notes })),
+    )
+    .await?;
+
+    Ok(recovery_id)
 }
 
 /// Receives an encrypted share from a helper and stores it temporarily in Redis.
-/// When enough shares are collected, it attempts to reconstruct the original nsec.
+/// When enough shares are collected, it reconstructs the original nsec and sends it to the
+/// device named when the recovery started, as a NIP-04 DM from the coordinator key.
+/// If that send fails the shares are kept, so the next submission retries it.
 pub async fn submit_recovery_share(
     app_state: Arc<AppState>,
     wallet_id_str: &str,
     encrypted_share: &str,
     helper_pubkey_str: &str,
-) -> Result<(), AppError> {
+) -> Result<RecoveryProgress, AppError> {
     info!(
         "Received share for wallet: {} from helper: {}",
         wallet_id_str, helper_pubkey_str
@@ -100,57 +191,200 @@ pub async fn submit_recovery_share(
     let helper_pubkey = PublicKey::from_bech32(helper_pubkey_str)
         .map_err(|e| AppError::BadRequest(format!("Invalid helper pubkey: {}", e)))?;
 
-    // 1. Retrieve the recovery coordinator's nsec (from config/KMS) to decrypt the share.
-    let coordinator_nsec_secret = app_state.config.sabi_nostr_nsec.expose_secret();
-    let coordinator_keys = nostr_sdk::Keys::new(SecretKey::from_sk_str(coordinator_nsec_secret)
-        .map_err(|e| AppError::Internal(format!("Failed to parse coordinator nsec: {}", e)))?);
-
-    // 2. Decrypt the share using NIP-04.
-    let decrypted_share_hex = nostr_sdk::nostr::nips::nip04::decrypt(
-        coordinator_keys.secret_key().unwrap(),
+    // 1. Decrypt the share with the recovery coordinator's key (helpers encrypt to it with NIP-04).
+    let coordinator_keys = coordinator_keys(&app_state)?;
+    let coordinator_secret = coordinator_keys
+        .secret_key()
+        .map_err(|e| AppError::Internal(format!("Coordinator keys have no secret key: {}", e)))?;
+    let decrypted_share = nostr_sdk::nostr::nips::nip04::decrypt(
+        &coordinator_secret,
         &helper_pubkey,
         encrypted_share,
     )
     .map_err(|e| AppError::BadRequest(format!("Failed to decrypt share: {}", e)))?;
 
-    let decrypted_share = hex::decode(&decrypted_share_hex)
-        .map_err(|e| AppError::Internal(format!("Failed to hex-decode decrypted share: {}", e)))?;
+    let share: RecoveryShare = serde_json::from_str(&decrypted_share)
+        .map_err(|e| AppError::BadRequest(format!("Malformed recovery share: {}", e)))?;
 
-    // 3. Store the decrypted share temporarily in Redis (or a secure in-memory store)
-    // Key: `recovery:wallet_id:shares`, Value: Hash map of `helper_pubkey` -> `share_data`
-    let redis_key = format!("recovery:{}:shares", wallet_id);
+    // 2. The share must belong to this wallet's current recovery and to the helper it was sent to.
     let mut con = app_state.redis_client.get_async_connection().await?;
-    let _: () = con.hset(&redis_key, helper_pubkey_str, decrypted_share_hex).await?;
-    info!("Stored share for wallet {} from helper {}", wallet_id, helper_pubkey_str);
+    let session_json: Option<String> = con.get(session_key(wallet_id)).await?;
+    let session: RecoverySession = session_json
+        .map(|json| serde_json::from_str(&json))
+        .transpose()?
+        .ok_or_else(|| {
+            AppError::NotFound(format!("No recovery in progress for wallet {}", wallet_id))
+        })?;
 
-    // 4. Check if enough shares are collected.
-    let collected_shares: Vec<String> = con.hvals(&redis_key).await?;
-
-    if collected_shares.len() >= 3 { // Assuming 3-of-5 scheme
-        info!(
-            "Enough shares ({}) collected for wallet {}. Attempting reconstruction.",
-            collected_shares.len(),
-            wallet_id
-        );
+    if share.wallet_id != wallet_id || share.recovery_id != session.recovery_id {
+        return Err(AppError::BadRequest(
+            "Share does not belong to this wallet's current recovery".to_string(),
+        ));
+    }
+    let expected_helper: Option<String> = sqlx::query_scalar(
+        "SELECT helper_npub FROM wallet_recovery_helpers WHERE wallet_id = $1 AND share_index = $2",
+    )
+    .bind(wallet_id)
+    .bind(share.index as i16)
+    .fetch_optional(&app_state.db_pool)
+    .await?;
+    let expected_pubkey = expected_helper.and_then(|npub| PublicKey::from_bech32(&npub).ok());
+    if expected_pubkey != Some(helper_pubkey) {
+        return Err(AppError::Forbidden(format!(
+            "Share {} was not issued to helper {}",
+            share.index, helper_pubkey_str
+        )));
+    }
 
-        let shares_bytes: Result<Vec<Vec<u8>>> = collected_shares
-            .into_iter()
-            .map(|s| hex::decode(s).map_err(|e| anyhow!("Failed to decode hex share: {}", e)))
-            .collect();
+    // 3. Store the share in Redis, keyed by helper so resubmissions overwrite rather than count twice.
+    let redis_key = shares_key(wallet_id);
+    let _: () = con
+        .hset(
+            &redis_key,
+            helper_pubkey_str,
+            serde_json::to_string(&share)?,
+        )
+        .await?;
+    let _: () = con
+        .expire(&redis_key, RECOVERY_SESSION_TTL_SECONDS as i64)
+        .await?;
+    info!(
+        "Stored share {} for wallet {} from helper {}",
+        share.index, wallet_id, helper_pubkey_str
+    );
 
-        let shares_bytes = shares_bytes.map_err(|e| AppError::Internal(e.to_string()))?;
+    // 4. Reconstruct once the threshold is reached.
+    let collected: Vec<String> = con.hvals(&redis_key).await?;
+    let threshold = session.policy.threshold;
+    let mut progress = RecoveryProgress {
+        shares_collected: collected.len(),
+        threshold,
+        key_delivered: false,
+    };
 
-        // TODO: Implement shamir reconstruction when library is available
-        // For now, return early as reconstruction is not implemented
-        return Err(AppError::Internal("Shamir reconstruction not yet implemented".to_string()));
-    } else {
+    if collected.len() < threshold as usize {
         info!(
             "Collected {} shares for wallet {}. Need {} more.",
-            collected_shares.len(),
+            collected.len(),
             wallet_id,
-            3 - collected_shares.len()
+            threshold as usize - collected.len()
         );
+        return Ok(progress);
     }
 
-    Ok(())
+    info!(
+        "Enough shares ({}) collected for wallet {}. Attempting reconstruction.",
+        collected.len(),
+        wallet_id
+    );
+
+    let shares = collected
+        .iter()
+        .map(|json| serde_json::from_str::<RecoveryShare>(json))
+        .collect::<Result<Vec<_>, _>>()?;
+    // The shares' checksum ties the rebuilt secret to this recovery, so a bad combination fails here
+    let secret_bytes = recovery::combine_shares(&shares).map_err(|e| {
+        warn!(
+            "Recovery reconstruction failed for wallet {}: {}",
+            wallet_id, e
+        );
+        e
+    })?;
+    let nsec = SecretKey::from_slice(&secret_bytes)
+        .map_err(|e| AppError::Internal(format!("Rebuilt key for wallet {} is invalid: {}", wallet_id, e)))?
+        .to_bech32()
+        .map_err(|e| AppError::Internal(format!("Failed to encode nsec: {}", e)))?;
+
+    // 5. Deliver the key to the owner's new device. Only that device can decrypt the DM.
+    let device_pubkey = PublicKey::from_bech32(&session.device_npub)
+        .map_err(|e| AppError::Internal(format!("Recovery session has an invalid device npub: {}", e)))?;
+    let delivery = json!({
+        "wallet_id": wallet_id,
+        "recovery_id": session.recovery_id,
+        "nsec": nsec,
+    });
+    let event_id = NostrClient::new(&app_state.config.nostr_relays)?
+        .send_dm(&coordinator_keys, device_pubkey, &delivery.to_string())
+        .await?;
+
+    let _: () = con.del(&[session_key(wallet_id), redis_key]).await?;
+    info!(
+        "Recovery {} for wallet {}: key sent to device {}.",
+        session.recovery_id, wallet_id, session.device_npub
+    );
+    audit_service::record_now(
+        &app_state.db_pool,
+        AuditEvent::new(&AuditActor::system(), AuditAction::RecoveryComplete)
+            .target("wallet", wallet_id)
+            .after(&json!({
+                "recovery_id": session.recovery_id,
+                "device_npub": session.device_npub,
+                "event_id": event_id.to_hex(),
+            })),
+    )
+    .await?;
+
+    progress.key_delivered = true;
+    Ok(progress)
+}
+
+/// Reads the wallet's k-of-n recovery policy.
+async fn wallet_recovery_policy(
+    app_state: &AppState,
+    wallet_id: Uuid,
+) -> Result<RecoveryPolicy, AppError> {
+    let (threshold, share_count): (i16, i16) = sqlx::query_as(
+        "SELECT recovery_threshold, recovery_share_count FROM wallets WHERE id = $1",
+    )
+    .bind(wallet_id)
+    .fetch_optional(&app_state.db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Wallet {} not found", wallet_id)))?;
+
+    let to_u8 = |value: i16| {
+        u8::try_from(value).map_err(|_| {
+            AppError::Internal(format!(
+                "Wallet {} has an invalid recovery policy",
+                wallet_id
+            ))
+        })
+    };
+    RecoveryPolicy::new(to_u8(threshold)?, to_u8(share_count)?)
+}
+
+/// The wallet's helper npubs in share order.
+async fn wallet_helpers(app_state: &AppState, wallet_id: Uuid) -> Result<Vec<String>, AppError> {
+    let helpers = sqlx::query_scalar(
+        "SELECT helper_npub FROM wallet_recovery_helpers WHERE wallet_id = $1 ORDER BY share_index",
+    )
+    .bind(wallet_id)
+    .fetch_all(&app_state.db_pool)
+    .await?;
+    Ok(helpers)
+}
+
+fn parse_helpers(helper_npubs: &[String]) -> Result<Vec<PublicKey>, AppError> {
+    helper_npubs
+        .iter()
+        .map(|npub_str| {
+            PublicKey::from_bech32(npub_str).map_err(|e| {
+                AppError::BadRequest(format!("Invalid helper npub '{}': {}", npub_str, e))
+            })
+        })
+        .collect()
+}
+
+fn coordinator_keys(app_state: &AppState) -> Result<nostr_sdk::Keys, AppError> {
+    let coordinator_nsec_secret = app_state.config.sabi_nostr_nsec.expose_secret();
+    let secret_key = SecretKey::from_sk_str(coordinator_nsec_secret)
+        .map_err(|e| AppError::Internal(format!("Failed to parse coordinator nsec: {}", e)))?;
+    Ok(nostr_sdk::Keys::new(secret_key))
+}
+
+fn session_key(wallet_id: Uuid) -> String {
+    format!("recovery:{}:session", wallet_id)
+}
+
+fn shares_key(wallet_id: Uuid) -> String {
+    format!("recovery:{}:shares", wallet_id)
 }
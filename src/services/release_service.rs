@@ -0,0 +1,476 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use sqlx::{Any, Transaction as DbTransaction};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    database::AnyPool,
+    domain::{
+        audit::{AuditAction, AuditActor, AuditEvent},
+        ledger::JournalEntry,
+        models::{ReleaseRequest, Transaction},
+        transaction::TransactionStatus,
+        types::Sats,
+    },
+    error::AppError,
+    services::{admin_service, alert_service, audit_service, payout_service, transaction_service},
+};
+
+const EXPIRY_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+const RELEASE_REQUEST_COLUMNS: &str = "id, transaction_id, amount_sats, recipient, notes, status, requested_by, decided_by, decision_reason, failure_reason, expires_at, decided_at, created_at, updated_at";
+
+/// What happened to a manual release request.
+#[derive(Debug)]
+pub enum ReleaseOutcome {
+    /// At or below the approval threshold: the payment was sent.
+    Released(ReleaseRequest),
+    /// Above the threshold: waiting for a second admin.
+    PendingApproval(ReleaseRequest),
+}
+
+/// Releases funds directly when the amount is within `RELEASE_APPROVAL_THRESHOLD_SATS`,
+/// otherwise records a pending request for a different admin to approve.
+/// A direct release is recorded as a request that needs no approval and is sent like an approved one.
+pub async fn request_release(
+    app_state: Arc<AppState>,
+    actor: &AuditActor,
+    transaction_id_str: &str,
+    amount_sats: Sats,
+    recipient: &str,
+    notes: Option<&str>,
+) -> Result<ReleaseOutcome, AppError> {
+    if amount_sats.0 <= 0 {
+        return Err(AppError::BadRequest("Release amount must be positive".to_string()));
+    }
+    let transaction_id = Uuid::parse_str(transaction_id_str)
+        .map_err(|e| AppError::BadRequest(format!("Invalid transaction ID: {}", e)))?;
+    let requested_by = actor
+        .admin_id
+        .ok_or_else(|| AppError::Forbidden("Release requests need an admin".to_string()))?;
+    info!(
+        "Admin manual release: Tx ID {}, Amount {} Sats, Recipient {}",
+        transaction_id, amount_sats.0, recipient
+    );
+
+    let mut tx = app_state.db_pool.begin().await?;
+    let transaction = admin_service::releasable_transaction(&mut tx, transaction_id, amount_sats).await?;
+
+    if amount_sats.0 <= app_state.config.release_approval_threshold_sats {
+        let executing = sqlx::query_as::<_, ReleaseRequest>(&format!(
+            r#"
+            INSERT INTO release_requests (id, transaction_id, amount_sats, recipient, notes, status, requested_by, expires_at, decided_at, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, 'executing', $6, NOW(), NOW(), NOW(), NOW())
+            RETURNING {}
+            "#,
+            RELEASE_REQUEST_COLUMNS
+        ))
+        .bind(Uuid::new_v4())
+        .bind(transaction_id)
+        .bind(amount_sats.0)
+        .bind(recipient)
+        .bind(notes)
+        .bind(requested_by)
+        .fetch_one(&mut *tx)
+        .await?;
+        let debit = claim(&mut tx, &transaction, recipient).await?;
+        tx.commit().await?;
+
+        let released = execute(&app_state, actor, executing, debit).await?;
+        return Ok(ReleaseOutcome::Released(released));
+    }
+
+    let expires_at = Utc::now() + ChronoDuration::seconds(app_state.config.release_request_ttl_seconds);
+
+    let request = sqlx::query_as::<_, ReleaseRequest>(&format!(
+        r#"
+        INSERT INTO release_requests (id, transaction_id, amount_sats, recipient, notes, status, requested_by, expires_at, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, 'pending', $6, $7, NOW(), NOW())
+        ON CONFLICT (transaction_id) WHERE status = 'pending' DO NOTHING
+        RETURNING {}
+        "#,
+        RELEASE_REQUEST_COLUMNS
+    ))
+    .bind(Uuid::new_v4())
+    .bind(transaction_id)
+    .bind(amount_sats.0)
+    .bind(recipient)
+    .bind(notes)
+    .bind(requested_by)
+    .bind(expires_at)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::Conflict(format!(
+            "Transaction {} already has a pending release request",
+            transaction_id
+        ))
+    })?;
+
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::ReleaseRequested)
+            .target("release_request", request.id)
+            .after(&request),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!(
+        "Release request {} for {} sats on transaction {} awaits approval",
+        request.id, amount_sats.0, transaction_id
+    );
+    Ok(ReleaseOutcome::PendingApproval(request))
+}
+
+/// Approves a pending request and sends the payment. The approver must not be the requester.
+pub async fn approve(
+    app_state: Arc<AppState>,
+    actor: &AuditActor,
+    request_id: Uuid,
+) -> Result<ReleaseRequest, AppError> {
+    // 1. Approve and claim the release
+    let mut tx = app_state.db_pool.begin().await?;
+    let request = lock_request(&mut tx, request_id).await?;
+    let approver = check_decidable(&request, actor, Utc::now())?;
+    let transaction =
+        admin_service::releasable_transaction(&mut tx, request.transaction_id, request.amount_sats).await?;
+
+    let executing = sqlx::query_as::<_, ReleaseRequest>(&format!(
+        "UPDATE release_requests SET status = 'executing', decided_by = $1, decided_at = NOW(), updated_at = NOW() WHERE id = $2 RETURNING {}",
+        RELEASE_REQUEST_COLUMNS
+    ))
+    .bind(approver)
+    .bind(request_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    let debit = claim(&mut tx, &transaction, &request.recipient).await?;
+
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::ReleaseApproved)
+            .target("release_request", request_id)
+            .before(&request)
+            .after(&executing),
+    )
+    .await?;
+    tx.commit().await?;
+
+    let approved = execute(&app_state, actor, executing, debit).await?;
+    info!("Release request {} approved and executed", request_id);
+    Ok(approved)
+}
+
+/// Books the deposit as sent before its release goes out, so the user cannot spend a credited
+/// deposit while it is in flight. Returns the entry, to reverse if the payment fails.
+async fn claim(
+    tx: &mut DbTransaction<'_, Any>,
+    transaction: &Transaction,
+    recipient: &str,
+) -> Result<JournalEntry, AppError> {
+    payout_service::post_payout(tx, "manual_release", transaction, Sats::ZERO, recipient).await
+}
+
+/// Sends the payment of a request committed as 'executing' with its deposit claimed, which keeps any
+/// other release or payout of the transaction away while the payment is outside a database transaction.
+/// The outcome is recorded afterwards: 'approved' once the payment went out, 'failed' otherwise.
+async fn execute(
+    app_state: &AppState,
+    actor: &AuditActor,
+    executing: ReleaseRequest,
+    debit: JournalEntry,
+) -> Result<ReleaseRequest, AppError> {
+    // A crash from here on leaves the request 'executing' for an admin to check against the node
+    let sent = app_state
+        .lightning
+        .send_payment(executing.amount_sats, &executing.recipient)
+        .await;
+
+    let mut tx = app_state.db_pool.begin().await?;
+    lock_request(&mut tx, executing.id).await?;
+    let payment_info = match sent {
+        Ok(payment_info) => payment_info,
+        Err(e) => {
+            let failed = fail(&mut tx, actor, &executing, &debit, &e.to_string()).await?;
+            tx.commit().await?;
+            error!("Release request {} failed to send: {}", executing.id, e);
+            alert_service::spawn_admin_alert(
+                app_state.config.clone(),
+                "Manual release failed".to_string(),
+                format!(
+                    "The release of {} for transaction {} to {} failed and was not sent: {}\n\
+                     The transaction is on admin hold and can be released again.",
+                    failed.amount_sats, failed.transaction_id, failed.recipient, e
+                ),
+            );
+            return Err(e);
+        }
+    };
+    info!(
+        "Lightning payment sent for release request {}. Payment ID: {}",
+        executing.id, payment_info.payment_hash
+    );
+
+    let approved = sqlx::query_as::<_, ReleaseRequest>(&format!(
+        "UPDATE release_requests SET status = 'approved', updated_at = NOW() WHERE id = $1 RETURNING {}",
+        RELEASE_REQUEST_COLUMNS
+    ))
+    .bind(executing.id)
+    .fetch_one(&mut *tx)
+    .await?;
+    admin_service::record_release(
+        &mut tx,
+        actor,
+        executing.transaction_id,
+        executing.amount_sats,
+        &executing.recipient,
+        executing.notes.as_deref(),
+        &payment_info,
+    )
+    .await?;
+
+    if let Err(e) = tx.commit().await {
+        // The payment went out, so the request stays 'executing' rather than being released again
+        error!(
+            "Release request {} was paid as {} but recording it failed: {:?}",
+            executing.id, payment_info.payment_hash, e
+        );
+        return Err(e.into());
+    }
+    Ok(approved)
+}
+
+/// Marks an executing request 'failed' and returns its claimed deposit to where it was booked.
+/// A deposit that was still pending goes on admin hold, since its queued payout was cancelled
+/// while the release was executing.
+async fn fail(
+    tx: &mut DbTransaction<'_, Any>,
+    actor: &AuditActor,
+    executing: &ReleaseRequest,
+    debit: &JournalEntry,
+    reason: &str,
+) -> Result<ReleaseRequest, AppError> {
+    let mut transaction = transaction_service::lock(tx, executing.transaction_id).await?;
+    payout_service::post_if_any(tx, &debit.reversed("manual_release_reversal")).await?;
+    if transaction.status == TransactionStatus::Pending {
+        transaction_service::transition(tx, &mut transaction, TransactionStatus::AdminHold).await?;
+    }
+
+    let failed = sqlx::query_as::<_, ReleaseRequest>(&format!(
+        "UPDATE release_requests SET status = 'failed', failure_reason = $1, updated_at = NOW() WHERE id = $2 RETURNING {}",
+        RELEASE_REQUEST_COLUMNS
+    ))
+    .bind(reason)
+    .bind(executing.id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    audit_service::record(
+        tx,
+        AuditEvent::new(actor, AuditAction::ReleaseFailed)
+            .target("release_request", executing.id)
+            .before(executing)
+            .after(&failed),
+    )
+    .await?;
+    Ok(failed)
+}
+
+/// Rejects a pending request. Like approval, this needs an admin other than the requester.
+pub async fn reject(
+    db_pool: &AnyPool,
+    actor: &AuditActor,
+    request_id: Uuid,
+    reason: &str,
+) -> Result<ReleaseRequest, AppError> {
+    let mut tx = db_pool.begin().await?;
+    let request = lock_request(&mut tx, request_id).await?;
+    let decider = check_decidable(&request, actor, Utc::now())?;
+
+    let rejected = sqlx::query_as::<_, ReleaseRequest>(&format!(
+        "UPDATE release_requests SET status = 'rejected', decided_by = $1, decision_reason = $2, decided_at = NOW(), updated_at = NOW() WHERE id = $3 RETURNING {}",
+        RELEASE_REQUEST_COLUMNS
+    ))
+    .bind(decider)
+    .bind(reason)
+    .bind(request_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::ReleaseRejected)
+            .target("release_request", request_id)
+            .before(&request)
+            .after(&rejected),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("Release request {} rejected: {}", request_id, reason);
+    Ok(rejected)
+}
+
+/// Lists release requests, newest first, optionally filtered by status.
+pub async fn list(db_pool: &AnyPool, status: Option<&str>) -> Result<Vec<ReleaseRequest>, AppError> {
+    let requests = sqlx::query_as::<_, ReleaseRequest>(&format!(
+        "SELECT {} FROM release_requests WHERE ($1::TEXT IS NULL OR status = $1) ORDER BY created_at DESC",
+        RELEASE_REQUEST_COLUMNS
+    ))
+    .bind(status)
+    .fetch_all(db_pool)
+    .await?;
+    Ok(requests)
+}
+
+/// Marks every pending request past its expiry as expired. Returns how many were expired.
+pub async fn expire_stale(db_pool: &AnyPool) -> Result<usize, AppError> {
+    let mut tx = db_pool.begin().await?;
+    let expired = sqlx::query_as::<_, ReleaseRequest>(&format!(
+        "UPDATE release_requests SET status = 'expired', decided_at = NOW(), updated_at = NOW() WHERE status = 'pending' AND expires_at <= NOW() RETURNING {}",
+        RELEASE_REQUEST_COLUMNS
+    ))
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for request in &expired {
+        audit_service::record(
+            &mut tx,
+            AuditEvent::new(&AuditActor::system(), AuditAction::ReleaseExpired)
+                .target("release_request", request.id)
+                .after(request),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    if !expired.is_empty() {
+        info!("Expired {} unapproved release requests", expired.len());
+    }
+    Ok(expired.len())
+}
+
+/// Periodically expires unapproved release requests.
+pub fn spawn_expiry_worker(app_state: Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        info!("Release request expiry worker started.");
+        loop {
+            if let Err(e) = expire_stale(&app_state.db_pool).await {
+                error!("Failed to expire release requests: {:?}", e);
+            }
+            tokio::time::sleep(EXPIRY_POLL_INTERVAL).await;
+        }
+    })
+}
+
+async fn lock_request(
+    tx: &mut DbTransaction<'_, Any>,
+    request_id: Uuid,
+) -> Result<ReleaseRequest, AppError> {
+    sqlx::query_as::<_, ReleaseRequest>(&format!(
+        "SELECT {} FROM release_requests WHERE id = $1 FOR UPDATE",
+        RELEASE_REQUEST_COLUMNS
+    ))
+    .bind(request_id)
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Release request {} not found", request_id)))
+}
+
+/// Checks that `actor` may approve or reject `request` at `now`, returning the deciding admin's ID.
+fn check_decidable(
+    request: &ReleaseRequest,
+    actor: &AuditActor,
+    now: DateTime<Utc>,
+) -> Result<Uuid, AppError> {
+    let decider = actor
+        .admin_id
+        .ok_or_else(|| AppError::Forbidden("Release decisions need an admin".to_string()))?;
+    if request.status != "pending" {
+        return Err(AppError::Conflict(format!(
+            "Release request {} is already {}",
+            request.id, request.status
+        )));
+    }
+    if request.expires_at <= now {
+        return Err(AppError::Conflict(format!(
+            "Release request {} has expired",
+            request.id
+        )));
+    }
+    if request.requested_by == decider {
+        return Err(AppError::Forbidden(
+            "A release must be approved or rejected by a different admin than the one who requested it"
+                .to_string(),
+        ));
+    }
+    Ok(decider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(requested_by: Uuid, status: &str, expires_at: DateTime<Utc>) -> ReleaseRequest {
+        ReleaseRequest {
+            id: Uuid::new_v4(),
+            transaction_id: Uuid::new_v4(),
+            amount_sats: Sats(500_000),
+            recipient: "lnbc1...".to_string(),
+            notes: None,
+            status: status.to_string(),
+            requested_by,
+            decided_by: None,
+            decision_reason: None,
+            failure_reason: None,
+            expires_at,
+            decided_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn admin(admin_id: Uuid) -> AuditActor {
+        AuditActor {
+            admin_id: Some(admin_id),
+            username: "admin".to_string(),
+            request_id: None,
+        }
+    }
+
+    #[test]
+    fn test_second_admin_can_decide_pending_request() {
+        let now = Utc::now();
+        let approver = Uuid::new_v4();
+        let pending = request(Uuid::new_v4(), "pending", now + ChronoDuration::hours(1));
+
+        assert_eq!(check_decidable(&pending, &admin(approver), now).unwrap(), approver);
+    }
+
+    #[test]
+    fn test_requester_cannot_approve_own_request() {
+        let now = Utc::now();
+        let requester = Uuid::new_v4();
+        let pending = request(requester, "pending", now + ChronoDuration::hours(1));
+
+        assert!(matches!(
+            check_decidable(&pending, &admin(requester), now),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(check_decidable(&pending, &AuditActor::system(), now).is_err());
+    }
+
+    #[test]
+    fn test_expired_or_decided_requests_are_refused() {
+        let now = Utc::now();
+        let approver = admin(Uuid::new_v4());
+
+        let expired = request(Uuid::new_v4(), "pending", now - ChronoDuration::seconds(1));
+        assert!(matches!(check_decidable(&expired, &approver, now), Err(AppError::Conflict(_))));
+
+        let rejected = request(Uuid::new_v4(), "rejected", now + ChronoDuration::hours(1));
+        assert!(matches!(check_decidable(&rejected, &approver, now), Err(AppError::Conflict(_))));
+    }
+}
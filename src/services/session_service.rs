@@ -0,0 +1,243 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Any, Transaction as DbTransaction};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    database::AnyPool,
+    domain::audit::{AuditAction, AuditActor, AuditEvent},
+    error::AppError,
+    services::{
+        admin_service::{self, Claims},
+        audit_service,
+    },
+};
+
+/// Tokens handed to an admin when a session starts or is refreshed.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionTokens {
+    pub access_token: String, // Short-lived JWT for the Authorization header
+    pub refresh_token: String, // Opaque; single-use, exchanged at /admin/token/refresh
+    pub expires_in: i64, // Seconds until the access token expires
+}
+
+/// Starts a session for an admin who has passed every login step.
+pub async fn start(db_pool: &AnyPool, config: &Config, admin_id: Uuid) -> Result<SessionTokens, AppError> {
+    let session_id = Uuid::new_v4();
+    let refresh_token = generate_refresh_token();
+    let expires_at = Utc::now() + ChronoDuration::seconds(config.admin_refresh_token_ttl_seconds);
+
+    sqlx::query(
+        r#"
+        INSERT INTO admin_sessions (id, admin_user_id, refresh_token_hash, expires_at, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, NOW(), NOW())
+        "#,
+    )
+    .bind(session_id)
+    .bind(admin_id)
+    .bind(hash_refresh_token(&refresh_token))
+    .bind(expires_at)
+    .execute(db_pool)
+    .await?;
+
+    Ok(SessionTokens {
+        access_token: admin_service::issue_access_token(config, admin_id, session_id)?,
+        refresh_token,
+        expires_in: config.admin_access_token_ttl_seconds,
+    })
+}
+
+/// Exchanges a refresh token for a new access token and a new refresh token. The old refresh
+/// token stops working; presenting it again revokes the whole session, since it must have leaked.
+pub async fn refresh(db_pool: &AnyPool, config: &Config, refresh_token: &str) -> Result<SessionTokens, AppError> {
+    let presented_hash = hash_refresh_token(refresh_token);
+    let next_refresh_token = generate_refresh_token();
+
+    let mut tx = db_pool.begin().await?;
+    let rotated: Option<(Uuid, Uuid)> = sqlx::query_as(
+        r#"
+        UPDATE admin_sessions
+        SET refresh_token_hash = $1, previous_refresh_token_hash = refresh_token_hash, last_used_at = NOW()
+        WHERE refresh_token_hash = $2 AND revoked_at IS NULL AND expires_at > NOW()
+        RETURNING id, admin_user_id
+        "#,
+    )
+    .bind(hash_refresh_token(&next_refresh_token))
+    .bind(&presented_hash)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((session_id, admin_id)) = rotated else {
+        drop(tx);
+        revoke_on_reuse(db_pool, &presented_hash).await?;
+        return Err(AppError::Unauthorized("Invalid or expired refresh token".to_string()));
+    };
+
+    // Deactivated admins, or admins whose password was reset, cannot keep their sessions alive
+    admin_service::load_authorized_admin(db_pool, admin_id).await?;
+    tx.commit().await?;
+
+    Ok(SessionTokens {
+        access_token: admin_service::issue_access_token(config, admin_id, session_id)?,
+        refresh_token: next_refresh_token,
+        expires_in: config.admin_access_token_ttl_seconds,
+    })
+}
+
+/// Ends the caller's session and denylists the access token they presented.
+pub async fn logout(
+    db_pool: &AnyPool,
+    actor: &AuditActor,
+    session_id: Uuid,
+    token_id: Uuid,
+    token_expires_at: DateTime<Utc>,
+) -> Result<(), AppError> {
+    let admin_id = actor
+        .admin_id
+        .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))?;
+
+    let mut tx = db_pool.begin().await?;
+    sqlx::query("DELETE FROM admin_revoked_tokens WHERE expires_at < NOW()")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO admin_revoked_tokens (jti, admin_user_id, expires_at, revoked_at) VALUES ($1, $2, $3, NOW()) ON CONFLICT (jti) DO NOTHING",
+    )
+    .bind(token_id)
+    .bind(admin_id)
+    .bind(token_expires_at)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "UPDATE admin_sessions SET revoked_at = NOW(), revoked_reason = 'logout' WHERE id = $1 AND admin_user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(session_id)
+    .bind(admin_id)
+    .execute(&mut *tx)
+    .await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::Logout).target("admin_session", session_id),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("Admin {} logged out of session {}", actor.username, session_id);
+    Ok(())
+}
+
+/// Revokes every open session of `admin_id` inside the caller's transaction. Returns how many were revoked.
+pub async fn revoke_all(
+    tx: &mut DbTransaction<'_, Any>,
+    admin_id: Uuid,
+    reason: &str,
+) -> Result<u64, AppError> {
+    let revoked = sqlx::query(
+        "UPDATE admin_sessions SET revoked_at = NOW(), revoked_reason = $1 WHERE admin_user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(reason)
+    .bind(admin_id)
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+    Ok(revoked)
+}
+
+/// Signs an admin out everywhere, e.g. after a lost laptop. Their access tokens stop working on the next request.
+pub async fn revoke_all_sessions(db_pool: &AnyPool, actor: &AuditActor, admin_id: Uuid) -> Result<u64, AppError> {
+    let mut tx = db_pool.begin().await?;
+    let revoked = revoke_all(&mut tx, admin_id, "revoked").await?;
+    audit_service::record(
+        &mut tx,
+        AuditEvent::new(actor, AuditAction::SessionsRevoked)
+            .target("admin_user", admin_id)
+            .after(&serde_json::json!({ "sessions_revoked": revoked })),
+    )
+    .await?;
+    tx.commit().await?;
+
+    info!("{} revoked {} sessions of admin {}", actor.username, revoked, admin_id);
+    Ok(revoked)
+}
+
+/// Refuses access tokens that were denylisted or whose session has been revoked. Run by the admin auth layer.
+pub async fn ensure_not_revoked(db_pool: &AnyPool, claims: &Claims) -> Result<(), AppError> {
+    let session_id = claims
+        .sid
+        .ok_or_else(|| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+
+    let revoked: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM admin_revoked_tokens WHERE jti = $1)
+            OR NOT EXISTS (SELECT 1 FROM admin_sessions WHERE id = $2 AND revoked_at IS NULL)
+        "#,
+    )
+    .bind(claims.jti)
+    .bind(session_id)
+    .fetch_one(db_pool)
+    .await?;
+
+    if revoked {
+        return Err(AppError::Unauthorized("Session has been revoked".to_string()));
+    }
+    Ok(())
+}
+
+/// A rotated-out refresh token was presented: whoever holds the current one may be an attacker,
+/// so the session is revoked for both.
+async fn revoke_on_reuse(db_pool: &AnyPool, presented_hash: &str) -> Result<(), AppError> {
+    let mut tx = db_pool.begin().await?;
+    let reused: Option<(Uuid, Uuid)> = sqlx::query_as(
+        r#"
+        UPDATE admin_sessions SET revoked_at = NOW(), revoked_reason = 'refresh_token_reuse'
+        WHERE previous_refresh_token_hash = $1 AND revoked_at IS NULL
+        RETURNING id, admin_user_id
+        "#,
+    )
+    .bind(presented_hash)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some((session_id, admin_id)) = reused {
+        warn!("Refresh token reuse on session {} of admin {}; session revoked", session_id, admin_id);
+        audit_service::record(
+            &mut tx,
+            AuditEvent::new(&AuditActor::system(), AuditAction::SessionsRevoked)
+                .target("admin_user", admin_id)
+                .after(&serde_json::json!({ "session_id": session_id, "reason": "refresh_token_reuse" })),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+fn generate_refresh_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Refresh tokens are 256 random bits, so a plain SHA-256 is enough to store them.
+fn hash_refresh_token(refresh_token: &str) -> String {
+    hex::encode(Sha256::digest(refresh_token.trim().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_tokens_are_unique_and_hashed_deterministically() {
+        let token = generate_refresh_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_refresh_token());
+
+        assert_eq!(hash_refresh_token(&token), hash_refresh_token(&format!(" {}\n", token)));
+        assert_ne!(hash_refresh_token(&token), token);
+    }
+}
@@ -0,0 +1,220 @@
+use chrono::{Duration as ChronoDuration, Utc};
+use reqwest::Client;
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use sqlx::{Any, Transaction as DbTransaction};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{app_state::AppState, config::Config, error::AppError, utils::phone_number::NigerianPhoneNumber};
+
+const AT_SMS_URL: &str = "https://api.africastalking.com/version1/messaging";
+const AT_SANDBOX_SMS_URL: &str = "https://api.sandbox.africastalking.com/version1/messaging";
+
+/// Messages are given up on after this many failed sends.
+pub const MAX_ATTEMPTS: i32 = 5;
+const BASE_BACKOFF_SECONDS: i64 = 60;
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BATCH_SIZE: i64 = 20;
+
+/// Queues an SMS inside the caller's transaction. It is sent only if that transaction commits.
+pub async fn queue(
+    tx: &mut DbTransaction<'_, Any>,
+    phone_number: &NigerianPhoneNumber,
+    body: &str,
+) -> Result<Uuid, AppError> {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO sms_messages (id, phone_number, body, next_attempt_at, created_at) VALUES ($1, $2, $3, NOW(), NOW())",
+    )
+    .bind(id)
+    .bind(phone_number.as_str())
+    .bind(body)
+    .execute(&mut **tx)
+    .await?;
+    Ok(id)
+}
+
+/// Starts the background worker that sends queued SMS, retrying failures with backoff.
+pub fn spawn_worker(app_state: Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        info!("SMS worker started.");
+        let client = match Client::builder().timeout(REQUEST_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                error!("SMS worker could not build an HTTP client: {:?}", e);
+                return;
+            }
+        };
+        loop {
+            if let Err(e) = send_due(&app_state, &client).await {
+                error!("SMS worker failed to poll for messages: {:?}", e);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    })
+}
+
+/// Sends every queued message that is due. Returns how many were attempted.
+pub async fn send_due(app_state: &AppState, client: &Client) -> Result<usize, AppError> {
+    let due: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM sms_messages
+        WHERE sent_at IS NULL AND attempts < $1 AND next_attempt_at <= NOW()
+        ORDER BY created_at
+        LIMIT $2
+        "#,
+    )
+    .bind(MAX_ATTEMPTS)
+    .bind(BATCH_SIZE)
+    .fetch_all(&app_state.db_pool)
+    .await?;
+
+    for id in &due {
+        send_one(app_state, client, *id).await?;
+    }
+    Ok(due.len())
+}
+
+async fn send_one(app_state: &AppState, client: &Client, id: Uuid) -> Result<(), AppError> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // SKIP LOCKED keeps two workers from sending the same message
+    let message: Option<(String, String, i32)> = sqlx::query_as(
+        "SELECT phone_number, body, attempts FROM sms_messages WHERE id = $1 AND sent_at IS NULL FOR UPDATE SKIP LOCKED",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some((phone_number, body, attempts)) = message else {
+        return Ok(());
+    };
+
+    match send_via_africas_talking(&app_state.config, client, &phone_number, &body).await {
+        Ok(provider_message_id) => {
+            sqlx::query(
+                "UPDATE sms_messages SET sent_at = NOW(), attempts = attempts + 1, last_error = NULL, provider_message_id = $2 WHERE id = $1",
+            )
+            .bind(id)
+            .bind(provider_message_id)
+            .execute(&mut *tx)
+            .await?;
+            info!("SMS {} sent to {}", id, phone_number);
+        }
+        Err(e) => {
+            let attempts = attempts + 1;
+            sqlx::query("UPDATE sms_messages SET attempts = $2, last_error = $3, next_attempt_at = $4 WHERE id = $1")
+                .bind(id)
+                .bind(attempts)
+                .bind(e.to_string())
+                .bind(Utc::now() + backoff_for(attempts))
+                .execute(&mut *tx)
+                .await?;
+            if attempts >= MAX_ATTEMPTS {
+                error!("Giving up on SMS {} to {} after {} attempts: {}", id, phone_number, attempts, e);
+            } else {
+                warn!("Failed to send SMS {} (attempt {}): {}", id, attempts, e);
+            }
+        }
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct AtResponse {
+    #[serde(rename = "SMSMessageData")]
+    data: AtMessageData,
+}
+
+#[derive(Debug, Deserialize)]
+struct AtMessageData {
+    #[serde(rename = "Message")]
+    message: String,
+    #[serde(rename = "Recipients", default)]
+    recipients: Vec<AtRecipient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AtRecipient {
+    #[serde(rename = "statusCode")]
+    status_code: i32,
+    status: String,
+    #[serde(rename = "messageId")]
+    message_id: String,
+}
+
+/// Sends one SMS through Africa's Talking and returns its message ID.
+async fn send_via_africas_talking(
+    config: &Config,
+    client: &Client,
+    phone_number: &str,
+    body: &str,
+) -> Result<String, AppError> {
+    let url = if config.at_username == "sandbox" {
+        AT_SANDBOX_SMS_URL
+    } else {
+        AT_SMS_URL
+    };
+    let mut form = vec![
+        ("username", config.at_username.as_str()),
+        ("to", phone_number),
+        ("message", body),
+    ];
+    if let Some(sender_id) = config.at_sms_sender_id.as_deref() {
+        form.push(("from", sender_id));
+    }
+
+    let response: AtResponse = client
+        .post(url)
+        .header("apiKey", config.at_api_key.expose_secret())
+        .header("Accept", "application/json")
+        .form(&form)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let recipient = response.data.recipients.into_iter().next().ok_or_else(|| {
+        AppError::Internal(format!("Africa's Talking accepted no recipients: {}", response.data.message))
+    })?;
+    // 100 Processed, 101 Sent, 102 Queued; anything else was rejected
+    if !(100..=102).contains(&recipient.status_code) {
+        return Err(AppError::Internal(format!(
+            "Africa's Talking rejected the SMS: {} ({})",
+            recipient.status, recipient.status_code
+        )));
+    }
+    Ok(recipient.message_id)
+}
+
+/// Doubles from a minute: 60s, 120s, 240s, ...
+fn backoff_for(attempts: i32) -> ChronoDuration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 10) as u32;
+    ChronoDuration::seconds(BASE_BACKOFF_SECONDS * 2i64.pow(exponent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles() {
+        assert_eq!(backoff_for(1), ChronoDuration::seconds(60));
+        assert_eq!(backoff_for(2), ChronoDuration::seconds(120));
+        assert_eq!(backoff_for(MAX_ATTEMPTS), ChronoDuration::seconds(960));
+    }
+
+    #[test]
+    fn test_parses_africas_talking_response() {
+        let response: AtResponse = serde_json::from_str(
+            r#"{"SMSMessageData":{"Message":"Sent to 1/1 Total Cost: NGN 2.2000","Recipients":[{"statusCode":101,"number":"+2348012345678","status":"Success","cost":"NGN 2.2000","messageId":"ATXid_1"}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(response.data.recipients[0].status_code, 101);
+        assert_eq!(response.data.recipients[0].message_id, "ATXid_1");
+    }
+}
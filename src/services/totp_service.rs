@@ -0,0 +1,311 @@
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::FromRow;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    crypto::{
+        envelope::{self, Envelope},
+        key_provider::KeyProvider,
+    },
+    database::AnyPool,
+    domain::totp,
+    error::AppError,
+};
+
+/// What an admin needs to add the account to an authenticator app.
+#[derive(Debug, Clone, Serialize)]
+pub struct TotpEnrollment {
+    pub secret: String, // Base32, for manual entry
+    pub provisioning_uri: String, // otpauth:// URI, for QR codes
+}
+
+/// Which kind of code satisfied the second factor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondFactor {
+    Totp,
+    RecoveryCode,
+}
+
+const TOTP_COLUMNS: &str =
+    "totp_enabled, totp_key_version, totp_wrapped_key, totp_nonce, totp_ciphertext, totp_last_used_step";
+
+#[derive(Debug, FromRow)]
+struct TotpRow {
+    totp_enabled: bool,
+    totp_key_version: Option<i32>,
+    totp_wrapped_key: Option<Vec<u8>>,
+    totp_nonce: Option<Vec<u8>>,
+    totp_ciphertext: Option<Vec<u8>>,
+    totp_last_used_step: Option<i64>,
+}
+
+/// Generates and stores a new, not yet active, TOTP secret. Enrollment only takes effect
+/// once `confirm_enrollment` sees a valid code, so a half-finished setup never locks anyone out.
+pub async fn begin_enrollment(
+    db_pool: &AnyPool,
+    key_provider: &dyn KeyProvider,
+    admin_id: Uuid,
+    username: &str,
+) -> Result<TotpEnrollment, AppError> {
+    if load_row(db_pool, admin_id).await?.totp_enabled {
+        return Err(AppError::Conflict("TOTP is already enabled for this admin".to_string()));
+    }
+
+    let secret = totp::generate_secret();
+    let (secret_base32, provisioning_uri) = totp::provisioning(&secret, username)?;
+    let sealed = envelope::seal(key_provider, &secret, admin_id.as_bytes()).await?;
+
+    sqlx::query(
+        r#"
+        UPDATE admin_users
+        SET totp_key_version = $1, totp_wrapped_key = $2, totp_nonce = $3, totp_ciphertext = $4,
+            totp_last_used_step = NULL, updated_at = NOW()
+        WHERE id = $5 AND totp_enabled = FALSE
+        "#,
+    )
+    .bind(sealed.key_version as i32)
+    .bind(sealed.wrapped_key)
+    .bind(sealed.nonce)
+    .bind(sealed.ciphertext)
+    .bind(admin_id)
+    .execute(db_pool)
+    .await?;
+
+    info!("Admin {} started TOTP enrollment", admin_id);
+    Ok(TotpEnrollment {
+        secret: secret_base32,
+        provisioning_uri,
+    })
+}
+
+/// Activates the pending secret if `code` is valid and returns fresh recovery codes.
+/// The codes are only ever shown here; just their hashes are stored.
+pub async fn confirm_enrollment(
+    db_pool: &AnyPool,
+    key_provider: &dyn KeyProvider,
+    admin_id: Uuid,
+    code: &str,
+) -> Result<Vec<String>, AppError> {
+    let row = load_row(db_pool, admin_id).await?;
+    if row.totp_enabled {
+        return Err(AppError::Conflict("TOTP is already enabled for this admin".to_string()));
+    }
+    let secret = open_secret(key_provider, admin_id, &row)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Start TOTP enrollment first".to_string()))?;
+
+    let step = totp::verify_code(&secret, code, Utc::now().timestamp() as u64, None)?
+        .ok_or_else(|| AppError::Unauthorized("Invalid TOTP code".to_string()))?;
+
+    let recovery_codes = totp::generate_recovery_codes();
+    let mut tx = db_pool.begin().await?;
+    sqlx::query(
+        "UPDATE admin_users SET totp_enabled = TRUE, totp_last_used_step = $1, updated_at = NOW() WHERE id = $2",
+    )
+    .bind(step as i64)
+    .bind(admin_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM admin_recovery_codes WHERE admin_user_id = $1")
+        .bind(admin_id)
+        .execute(&mut *tx)
+        .await?;
+    for recovery_code in &recovery_codes {
+        sqlx::query(
+            "INSERT INTO admin_recovery_codes (id, admin_user_id, code_hash, created_at) VALUES ($1, $2, $3, NOW())",
+        )
+        .bind(Uuid::new_v4())
+        .bind(admin_id)
+        .bind(totp::hash_recovery_code(recovery_code))
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    info!("Admin {} enabled TOTP", admin_id);
+    Ok(recovery_codes)
+}
+
+/// Whether the admin has to present a second factor at login.
+pub async fn is_enabled(db_pool: &AnyPool, admin_id: Uuid) -> Result<bool, AppError> {
+    Ok(load_row(db_pool, admin_id).await?.totp_enabled)
+}
+
+/// Accepts either a current TOTP code or an unused recovery code. Both are single-use.
+pub async fn verify_second_factor(
+    db_pool: &AnyPool,
+    key_provider: &dyn KeyProvider,
+    admin_id: Uuid,
+    code: &str,
+) -> Result<SecondFactor, AppError> {
+    let row = load_row(db_pool, admin_id).await?;
+    let secret = match open_secret(key_provider, admin_id, &row).await? {
+        Some(secret) if row.totp_enabled => secret,
+        _ => return Err(AppError::BadRequest("TOTP is not enabled for this admin".to_string())),
+    };
+
+    let last_used_step = row.totp_last_used_step.map(|step| step as u64);
+    if let Some(step) = totp::verify_code(&secret, code, Utc::now().timestamp() as u64, last_used_step)? {
+        // Conditional so two concurrent logins cannot both spend the same code
+        let updated = sqlx::query(
+            "UPDATE admin_users SET totp_last_used_step = $1 WHERE id = $2 AND (totp_last_used_step IS NULL OR totp_last_used_step < $1)",
+        )
+        .bind(step as i64)
+        .bind(admin_id)
+        .execute(db_pool)
+        .await?;
+        if updated.rows_affected() == 1 {
+            return Ok(SecondFactor::Totp);
+        }
+    }
+
+    let used: Option<Uuid> = sqlx::query_scalar(
+        "UPDATE admin_recovery_codes SET used_at = NOW() WHERE admin_user_id = $1 AND code_hash = $2 AND used_at IS NULL RETURNING id",
+    )
+    .bind(admin_id)
+    .bind(totp::hash_recovery_code(code))
+    .fetch_optional(db_pool)
+    .await?;
+    if used.is_some() {
+        warn!("Admin {} logged in with a recovery code", admin_id);
+        return Ok(SecondFactor::RecoveryCode);
+    }
+
+    Err(AppError::Unauthorized("Invalid TOTP or recovery code".to_string()))
+}
+
+/// Moves every stored TOTP secret, enabled or pending, onto the provider's current master key.
+/// Each admin is updated in its own transaction, so an interrupted run can simply be repeated.
+/// Returns how many secrets were re-wrapped.
+pub async fn reencrypt_all(db_pool: &AnyPool, key_provider: &dyn KeyProvider) -> Result<usize, AppError> {
+    let current_version = key_provider.current_version() as i32;
+    let ids: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM admin_users WHERE totp_key_version IS NOT NULL AND totp_key_version <> $1",
+    )
+    .bind(current_version)
+    .fetch_all(db_pool)
+    .await?;
+
+    let mut rewrapped = 0;
+    for admin_id in ids {
+        let mut tx = db_pool.begin().await?;
+        let row = sqlx::query_as::<_, TotpRow>(&format!(
+            "SELECT {} FROM admin_users WHERE id = $1 FOR UPDATE",
+            TOTP_COLUMNS
+        ))
+        .bind(admin_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // Deleted, reset or already rotated since the IDs were listed
+        let Some(row) = row else { continue };
+        if row.totp_key_version == Some(current_version) {
+            continue;
+        }
+        let Some(sealed) = reseal(key_provider, admin_id, &row).await? else { continue };
+
+        sqlx::query(
+            r#"
+            UPDATE admin_users
+            SET totp_key_version = $1, totp_wrapped_key = $2, totp_nonce = $3, totp_ciphertext = $4, updated_at = NOW()
+            WHERE id = $5
+            "#,
+        )
+        .bind(sealed.key_version as i32)
+        .bind(sealed.wrapped_key)
+        .bind(sealed.nonce)
+        .bind(sealed.ciphertext)
+        .bind(admin_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        rewrapped += 1;
+    }
+
+    info!("Master key rotation to version {}: {} TOTP secrets re-wrapped", current_version, rewrapped);
+    Ok(rewrapped)
+}
+
+async fn load_row(db_pool: &AnyPool, admin_id: Uuid) -> Result<TotpRow, AppError> {
+    sqlx::query_as::<_, TotpRow>(&format!("SELECT {} FROM admin_users WHERE id = $1", TOTP_COLUMNS))
+        .bind(admin_id)
+        .fetch_optional(db_pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Admin {} not found", admin_id)))
+}
+
+async fn open_secret(
+    key_provider: &dyn KeyProvider,
+    admin_id: Uuid,
+    row: &TotpRow,
+) -> Result<Option<Vec<u8>>, AppError> {
+    match envelope_of(admin_id, row)? {
+        Some(sealed) => envelope::open(key_provider, &sealed, admin_id.as_bytes()).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// The admin's TOTP secret re-wrapped under the current master key, if one is stored.
+async fn reseal(
+    key_provider: &dyn KeyProvider,
+    admin_id: Uuid,
+    row: &TotpRow,
+) -> Result<Option<Envelope>, AppError> {
+    match envelope_of(admin_id, row)? {
+        Some(sealed) => envelope::rewrap(key_provider, &sealed).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+fn envelope_of(admin_id: Uuid, row: &TotpRow) -> Result<Option<Envelope>, AppError> {
+    let (Some(version), Some(wrapped_key), Some(nonce), Some(ciphertext)) = (
+        row.totp_key_version,
+        &row.totp_wrapped_key,
+        &row.totp_nonce,
+        &row.totp_ciphertext,
+    ) else {
+        return Ok(None);
+    };
+
+    Ok(Some(Envelope {
+        key_version: u32::try_from(version)
+            .map_err(|_| AppError::Internal(format!("Invalid TOTP key version for admin {}", admin_id)))?,
+        wrapped_key: wrapped_key.clone(),
+        nonce: nonce.clone(),
+        ciphertext: ciphertext.clone(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::key_provider::test_provider;
+
+    fn row(sealed: Option<Envelope>) -> TotpRow {
+        TotpRow {
+            totp_enabled: sealed.is_some(),
+            totp_key_version: sealed.as_ref().map(|e| e.key_version as i32),
+            totp_wrapped_key: sealed.as_ref().map(|e| e.wrapped_key.clone()),
+            totp_nonce: sealed.as_ref().map(|e| e.nonce.clone()),
+            totp_ciphertext: sealed.map(|e| e.ciphertext),
+            totp_last_used_step: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reseal_moves_totp_secret_to_current_key() {
+        let admin_id = Uuid::new_v4();
+        let old = test_provider(&[1], 1);
+        let rotated = test_provider(&[1, 2], 2);
+        let secret = totp::generate_secret();
+
+        let sealed = envelope::seal(&old, &secret, admin_id.as_bytes()).await.unwrap();
+        let rotated_row = row(reseal(&rotated, admin_id, &row(Some(sealed))).await.unwrap());
+
+        assert_eq!(rotated_row.totp_key_version, Some(2));
+        assert_eq!(open_secret(&rotated, admin_id, &rotated_row).await.unwrap(), Some(secret));
+        assert!(reseal(&rotated, admin_id, &row(None)).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{any::AnyArguments, query::QueryAs, Any, FromRow};
+use uuid::Uuid;
+
+use crate::{
+    database::AnyPool,
+    domain::{
+        models::Transaction,
+        pagination::{self, Cursor},
+        transaction::{TransactionStatus, TransactionType},
+    },
+    error::AppError,
+    utils::phone_number::NigerianPhoneNumber,
+};
+
+/// Shared by the page and totals queries; binds $1..$9 in the order of `bind_filter`.
+const TRADE_FILTER_SQL: &str = r#"
+    FROM transactions t
+    JOIN wallets w ON w.id = t.wallet_id
+    JOIN users u ON u.id = w.user_id
+    WHERE ($1::TEXT IS NULL OR t.status = $1)
+      AND ($2::TEXT IS NULL OR t.tx_type = $2)
+      AND ($3::UUID IS NULL OR t.wallet_id = $3)
+      AND ($4::TEXT IS NULL OR u.phone_number = $4)
+      AND ($5::BIGINT IS NULL OR t.amount_sats >= $5)
+      AND ($6::BIGINT IS NULL OR t.amount_sats <= $6)
+      AND ($7::TIMESTAMPTZ IS NULL OR t.created_at >= $7)
+      AND ($8::TIMESTAMPTZ IS NULL OR t.created_at < $8)
+      AND ($9::TEXT IS NULL OR t.external_id = $9)
+"#;
+
+/// Which transactions an admin is looking for. Every field is optional; set fields are ANDed.
+#[derive(Debug, Clone, Default)]
+pub struct TradeFilter {
+    pub status: Option<TransactionStatus>,
+    pub tx_type: Option<TransactionType>,
+    pub wallet_id: Option<Uuid>,
+    pub phone_number: Option<String>, // Any Nigerian format; normalized to E.164
+    pub min_amount_sats: Option<i64>,
+    pub max_amount_sats: Option<i64>,
+    pub from: Option<DateTime<Utc>>, // Inclusive
+    pub to: Option<DateTime<Utc>>,   // Exclusive
+    pub external_id: Option<String>,
+}
+
+/// Count and volume of the matching transactions in one status, across all pages.
+#[derive(Debug, Clone, FromRow, Serialize)]
+pub struct StatusTotal {
+    pub status: TransactionStatus,
+    pub count: i64,
+    pub amount_sats: i64,
+}
+
+/// One page of matching transactions, newest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct TradePage {
+    pub trades: Vec<Transaction>,
+    pub next_cursor: Option<String>, // None on the last page
+    pub totals: Vec<StatusTotal>,
+}
+
+/// Searches transactions with keyset pagination. Pass the previous page's `next_cursor` to continue.
+pub async fn search(
+    db_pool: &AnyPool,
+    filter: TradeFilter,
+    cursor: Option<&str>,
+    limit: Option<i64>,
+) -> Result<TradePage, AppError> {
+    let filter = normalize(filter)?;
+    let cursor = cursor.map(Cursor::decode).transpose()?;
+    let limit = pagination::page_size(limit);
+
+    // One extra row tells us whether another page follows
+    let page_sql = format!(
+        r#"
+        SELECT t.id, t.wallet_id, t.tx_type, t.amount_sats, t.fee_sats, t.status, t.description, t.external_id, t.created_at, t.updated_at
+        {}
+          AND ($10::TIMESTAMPTZ IS NULL OR (t.created_at, t.id) < ($10, $11))
+        ORDER BY t.created_at DESC, t.id DESC
+        LIMIT $12
+        "#,
+        TRADE_FILTER_SQL
+    );
+    let mut trades = bind_filter(sqlx::query_as::<_, Transaction>(&page_sql), &filter)
+        .bind(cursor.map(|c| c.created_at))
+        .bind(cursor.map(|c| c.id))
+        .bind(limit + 1)
+        .fetch_all(db_pool)
+        .await?;
+
+    let next_cursor = if trades.len() as i64 > limit {
+        trades.truncate(limit as usize);
+        trades.last().map(|last| {
+            Cursor {
+                created_at: last.created_at,
+                id: last.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+
+    let totals_sql = format!(
+        "SELECT t.status, COUNT(*) AS count, COALESCE(SUM(t.amount_sats), 0)::BIGINT AS amount_sats {} GROUP BY t.status ORDER BY t.status",
+        TRADE_FILTER_SQL
+    );
+    let totals = bind_filter(sqlx::query_as::<_, StatusTotal>(&totals_sql), &filter)
+        .fetch_all(db_pool)
+        .await?;
+
+    Ok(TradePage {
+        trades,
+        next_cursor,
+        totals,
+    })
+}
+
+fn bind_filter<'q, O>(
+    query: QueryAs<'q, Any, O, AnyArguments<'q>>,
+    filter: &'q TradeFilter,
+) -> QueryAs<'q, Any, O, AnyArguments<'q>> {
+    query
+        .bind(filter.status.map(|status| status.as_str()))
+        .bind(filter.tx_type.map(|tx_type| tx_type.as_str()))
+        .bind(filter.wallet_id)
+        .bind(filter.phone_number.as_deref())
+        .bind(filter.min_amount_sats)
+        .bind(filter.max_amount_sats)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(filter.external_id.as_deref())
+}
+
+/// Canonicalizes the phone number and rejects ranges that can never match.
+fn normalize(mut filter: TradeFilter) -> Result<TradeFilter, AppError> {
+    if let Some(phone_number) = filter.phone_number.take() {
+        let phone_number = NigerianPhoneNumber::new(&phone_number)
+            .map_err(|e| AppError::BadRequest(format!("Invalid phone number: {}", e)))?;
+        filter.phone_number = Some(phone_number.into());
+    }
+    if let (Some(min), Some(max)) = (filter.min_amount_sats, filter.max_amount_sats) {
+        if min > max {
+            return Err(AppError::BadRequest("min_amount_sats is greater than max_amount_sats".to_string()));
+        }
+    }
+    if let (Some(from), Some(to)) = (filter.from, filter.to) {
+        if from >= to {
+            return Err(AppError::BadRequest("`from` must be before `to`".to_string()));
+        }
+    }
+    Ok(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+
+    #[test]
+    fn test_phone_number_is_normalized() {
+        let filter = normalize(TradeFilter {
+            phone_number: Some("08012345678".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(filter.phone_number.as_deref(), Some("+2348012345678"));
+    }
+
+    #[test]
+    fn test_empty_ranges_are_rejected() {
+        let amounts = TradeFilter {
+            min_amount_sats: Some(10),
+            max_amount_sats: Some(5),
+            ..Default::default()
+        };
+        assert!(matches!(normalize(amounts), Err(AppError::BadRequest(_))));
+
+        let now = Utc::now();
+        let dates = TradeFilter {
+            from: Some(now),
+            to: Some(now - ChronoDuration::days(1)),
+            ..Default::default()
+        };
+        assert!(matches!(normalize(dates), Err(AppError::BadRequest(_))));
+    }
+}
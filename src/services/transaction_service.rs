@@ -0,0 +1,108 @@
+use sqlx::{Any, Transaction as DbTransaction};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        models::Transaction,
+        transaction::{TransactionStatus, TransactionType},
+        types::Sats,
+    },
+    error::AppError,
+};
+
+pub const TRANSACTION_COLUMNS: &str =
+    "id, wallet_id, tx_type, amount_sats, fee_sats, status, description, external_id, created_at, updated_at";
+
+/// Records a new transaction. Every transaction starts out `pending`.
+pub async fn create(
+    tx: &mut DbTransaction<'_, Any>,
+    wallet_id: Uuid,
+    tx_type: TransactionType,
+    amount_sats: Sats,
+    fee_sats: Sats,
+    description: Option<&str>,
+    external_id: Option<&str>,
+) -> Result<Transaction, AppError> {
+    let transaction = sqlx::query_as::<_, Transaction>(&format!(
+        r#"
+        INSERT INTO transactions (id, wallet_id, tx_type, amount_sats, fee_sats, status, description, external_id, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW(), NOW())
+        RETURNING {}
+        "#,
+        TRANSACTION_COLUMNS
+    ))
+    .bind(Uuid::new_v4())
+    .bind(wallet_id)
+    .bind(tx_type)
+    .bind(amount_sats.0)
+    .bind(fee_sats.0)
+    .bind(TransactionStatus::Pending)
+    .bind(description)
+    .bind(external_id)
+    .fetch_one(&mut **tx)
+    .await?;
+    Ok(transaction)
+}
+
+/// Loads a transaction and locks it until the caller's transaction ends.
+pub async fn lock(tx: &mut DbTransaction<'_, Any>, transaction_id: Uuid) -> Result<Transaction, AppError> {
+    sqlx::query_as::<_, Transaction>(&format!(
+        "SELECT {} FROM transactions WHERE id = $1 FOR UPDATE",
+        TRANSACTION_COLUMNS
+    ))
+    .bind(transaction_id)
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Transaction {} not found", transaction_id)))
+}
+
+/// Like `lock`, by the payment hash or provider reference. `None` if nothing matches.
+pub async fn lock_by_external_id(
+    tx: &mut DbTransaction<'_, Any>,
+    external_id: &str,
+) -> Result<Option<Transaction>, AppError> {
+    let transaction = sqlx::query_as::<_, Transaction>(&format!(
+        "SELECT {} FROM transactions WHERE external_id = $1 FOR UPDATE",
+        TRANSACTION_COLUMNS
+    ))
+    .bind(external_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(transaction)
+}
+
+/// Moves a transaction to `next`, the only way its status may change. Illegal moves are refused
+/// with a `409`, as is a row whose status changed since it was read.
+pub async fn transition(
+    tx: &mut DbTransaction<'_, Any>,
+    transaction: &mut Transaction,
+    next: TransactionStatus,
+) -> Result<(), AppError> {
+    let from = transaction.status;
+    from.transition_to(next).map_err(|_| {
+        AppError::Conflict(format!(
+            "Transaction {} cannot move from {} to {}",
+            transaction.id, from, next
+        ))
+    })?;
+
+    let updated = sqlx::query(
+        "UPDATE transactions SET status = $1, updated_at = NOW() WHERE id = $2 AND status = $3",
+    )
+    .bind(next)
+    .bind(transaction.id)
+    .bind(from)
+    .execute(&mut **tx)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::Conflict(format!(
+            "Transaction {} is no longer {}",
+            transaction.id, from
+        )));
+    }
+
+    info!("Transaction {} moved from {} to {}", transaction.id, from, next);
+    transaction.status = next;
+    Ok(())
+}
@@ -1,9 +1,21 @@
 use anyhow::Result;
 use redis::{AsyncCommands, Client as RedisClient};
-use std::time::Duration;
+use std::sync::Arc;
 use tracing::{error, info};
-
-use crate::{database::AnyPool, error::AppError, utils::phone_number::NigerianPhoneNumber};
+use uuid::Uuid;
+
+use crate::{
+    bitcoin::lightning::LightningBackend,
+    database::AnyPool,
+    domain::{
+        ledger::{AccountRef, JournalEntry, LIGHTNING_ACCOUNT},
+        transaction::{TransactionStatus, TransactionType},
+        types::Sats,
+    },
+    error::AppError,
+    services::{ledger_service, lightning_payment_service, transaction_service},
+    utils::phone_number::NigerianPhoneNumber,
+};
 
 const USSD_SESSION_TTL_SECONDS: usize = 180; // 3 minutes as per requirement
 
@@ -46,6 +58,7 @@ impl UssdState {
 pub async fn handle_ussd_request(
     redis_client: RedisClient,
     db_pool: AnyPool, // Required for user/wallet lookup
+    lightning: Arc<dyn LightningBackend>,
     session_id: &str,
     phone_number: &str,
     text: &str,
@@ -64,13 +77,13 @@ pub async fn handle_ussd_request(
     let response = match current_state {
         UssdState::Start => handle_start_state(&mut con, &session_key, text).await?,
         UssdState::MainMenu => {
-            handle_main_menu_state(&mut con, &session_key, text, &normalized_phone_number, db_pool).await?
+            handle_main_menu_state(&mut con, &session_key, text, &normalized_phone_number, db_pool, lightning).await?
         }
         UssdState::CheckBalance => handle_check_balance_state(&mut con, &session_key, &normalized_phone_number, db_pool).await?,
         UssdState::SendBitcoin => handle_send_bitcoin_state(&mut con, &session_key, text).await?,
-        UssdState::ReceiveBitcoin => handle_receive_bitcoin_state(&mut con, &session_key, &normalized_phone_number, db_pool).await?,
+        UssdState::ReceiveBitcoin => handle_receive_bitcoin_state(&mut con, &session_key, &normalized_phone_number, db_pool, lightning).await?,
         UssdState::ConfirmSendBitcoin => {
-            handle_confirm_send_bitcoin_state(&mut con, &session_key, text, &normalized_phone_number, db_pool).await?
+            handle_confirm_send_bitcoin_state(&mut con, &session_key, text, &normalized_phone_number, db_pool, lightning).await?
         }
     };
 
@@ -103,6 +116,7 @@ async fn handle_main_menu_state(
     input: &str,
     phone_number: &str,
     db_pool: AnyPool,
+    lightning: Arc<dyn LightningBackend>,
 ) -> Result<String, AppError> {
     match input {
         "1" => {
@@ -115,7 +129,7 @@ async fn handle_main_menu_state(
             Ok("CON Enter amount in Sats and recipient's address (e.g., 1000 bc1...)".to_string())
         }
         "3" => {
-            let response = ussd_receive_bitcoin(db_pool, phone_number).await?;
+            let response = ussd_receive_bitcoin(db_pool, lightning, phone_number).await?;
             let _: () = con.set(session_key, UssdState::ReceiveBitcoin.as_str()).await?;
             Ok(format!("END {}", response))
         }
@@ -171,8 +185,9 @@ async fn handle_receive_bitcoin_state(
     _session_key: &str,
     phone_number: &str,
     db_pool: AnyPool,
+    lightning: Arc<dyn LightningBackend>,
 ) -> Result<String, AppError> {
-    let address = ussd_receive_bitcoin(db_pool, phone_number).await?;
+    let address = ussd_receive_bitcoin(db_pool, lightning, phone_number).await?;
     Ok(format!("END Your Bitcoin address is: {}", address))
 }
 
@@ -182,6 +197,7 @@ async fn handle_confirm_send_bitcoin_state(
     input: &str,
     phone_number: &str,
     db_pool: AnyPool,
+    lightning: Arc<dyn LightningBackend>,
 ) -> Result<String, AppError> {
     match input {
         "1" => {
@@ -192,7 +208,7 @@ async fn handle_confirm_send_bitcoin_state(
             })?;
 
             // Perform the actual send Bitcoin operation
-            let result = ussd_send_bitcoin(db_pool, phone_number, amount, &address).await;
+            let result = ussd_send_bitcoin(db_pool, lightning, phone_number, amount, &address).await;
 
             // Clear session data after use
             let _: () = con.del(session_key).await?;
@@ -228,18 +244,21 @@ async fn ussd_check_balance(db_pool: AnyPool, phone_number: &str) -> Result<Stri
     .await?
     .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
-    let balance_sats: i64 = sqlx::query_scalar!(
-        "SELECT balance_sats FROM wallets WHERE user_id = $1",
+    let wallet_id: Uuid = sqlx::query_scalar!(
+        "SELECT id FROM wallets WHERE user_id = $1",
         user_id
     )
     .fetch_one(&db_pool)
     .await?;
 
-    Ok(format!("{} Sats", balance_sats))
+    let balance = ledger_service::wallet_balance(&db_pool, wallet_id).await?;
+
+    Ok(format!("{} Sats", balance.0))
 }
 
 async fn ussd_send_bitcoin(
     db_pool: AnyPool,
+    lightning: Arc<dyn LightningBackend>,
     phone_number: &str,
     amount_sats: i64,
     address: &str,
@@ -249,17 +268,6 @@ async fn ussd_send_bitcoin(
         phone_number, amount_sats, address
     );
 
-    // TODO: Implement actual Bitcoin send logic using Breez SDK
-    // - Check if user has sufficient balance (atomic operation)
-    // - Create a new transaction record (pending)
-    // - Call Breez SDK to initiate payment
-    // - Update transaction status based on Breez SDK response (or webhook)
-
-    // Placeholder for Breez SDK call
-    // breez_sdk::send_payment(amount_sats, address)?;
-    tokio::time::sleep(Duration::from_secs(2)).await; // Simulate network call
-
-    // For now, assume success and update balance
     let user_id = sqlx::query_scalar!(
         "SELECT id FROM users WHERE phone_number = $1",
         phone_number
@@ -268,25 +276,98 @@ async fn ussd_send_bitcoin(
     .await?
     .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
-    // This should be done atomically with a proper transaction and balance check
-    sqlx::query!(
-        "UPDATE wallets SET balance_sats = balance_sats - $1 WHERE user_id = $2 AND balance_sats >= $1",
-        amount_sats,
+    // 1. Commit the pending withdrawal and its debit, so no lock is held while the payment is in flight
+    let mut tx = db_pool.begin().await?;
+
+    let wallet_id: Uuid = sqlx::query_scalar!(
+        "SELECT id FROM wallets WHERE user_id = $1",
         user_id
     )
-    .execute(&db_pool)
+    .fetch_optional(&mut *tx)
     .await?
-    .rows_affected()
-    .eq(&1)
-    .then_some(())
-    .ok_or_else(|| AppError::BadRequest("Insufficient balance or wallet not found".to_string()))?;
+    .ok_or_else(|| AppError::NotFound("Wallet not found".to_string()))?;
+
+    let description = format!("USSD send to {}", address);
+    let transaction = transaction_service::create(
+        &mut tx,
+        wallet_id,
+        TransactionType::BtcWithdrawal,
+        Sats(amount_sats),
+        Sats::ZERO, // Known once the Breez webhook reports the payment
+        Some(&description),
+        None,
+    )
+    .await?;
 
+    // The ledger rejects the entry if it would take the wallet below zero
+    let entry = JournalEntry::new("ussd_send")
+        .for_transaction(transaction.id)
+        .with_description(&description)
+        .transfer(
+            AccountRef::Wallet(wallet_id),
+            AccountRef::System(LIGHTNING_ACCOUNT),
+            Sats(amount_sats),
+        );
+    ledger_service::post_entry(&mut tx, &entry).await?;
+    tx.commit().await?;
+
+    // 2. Send outside any database transaction
+    let sent = lightning.send_payment(Sats(amount_sats), address).await;
+
+    // 3. Record the outcome
+    let mut tx = db_pool.begin().await?;
+    let mut transaction = transaction_service::lock(&mut tx, transaction.id).await?;
+    let payment = match sent {
+        Ok(payment) => payment,
+        Err(e) => {
+            // Should the payment have gone out regardless, its Breez notification is parked for an admin
+            transaction_service::transition(&mut tx, &mut transaction, TransactionStatus::Failed).await?;
+            let reversal = JournalEntry::new("ussd_send_reversal")
+                .for_transaction(transaction.id)
+                .transfer(
+                    AccountRef::System(LIGHTNING_ACCOUNT),
+                    AccountRef::Wallet(wallet_id),
+                    Sats(amount_sats),
+                );
+            ledger_service::post_entry(&mut tx, &reversal).await?;
+            tx.commit().await?;
+            error!(
+                "USSD send of {} Sats to {} for user {} failed; debit reversed: {}",
+                amount_sats, address, phone_number, e
+            );
+            return Err(e);
+        }
+    };
+
+    // The Breez webhook completes the transaction and books the routing fee by this hash
+    sqlx::query("UPDATE transactions SET external_id = $1 WHERE id = $2")
+        .bind(&payment.payment_hash)
+        .bind(transaction.id)
+        .execute(&mut *tx)
+        .await?;
+    lightning_payment_service::claim_early_notification(&mut tx, &payment.payment_hash, &mut transaction).await?;
+
+    if let Err(e) = tx.commit().await {
+        // The sats left and the debit stands; the node's notification will be parked for an admin to attribute
+        error!(
+            "USSD send {} went out as payment {} but recording it failed: {:?}",
+            transaction.id, payment.payment_hash, e
+        );
+        return Err(e.into());
+    }
 
-    info!("Simulated send of {} Sats to {} for user {}", amount_sats, address, phone_number);
+    info!(
+        "Sent {} Sats to {} for user {} (payment hash {})",
+        amount_sats, address, phone_number, payment.payment_hash
+    );
     Ok(())
 }
 
-async fn ussd_receive_bitcoin(db_pool: AnyPool, phone_number: &str) -> Result<String, AppError> {
+async fn ussd_receive_bitcoin(
+    db_pool: AnyPool,
+    lightning: Arc<dyn LightningBackend>,
+    phone_number: &str,
+) -> Result<String, AppError> {
     info!("USSD: User {} requesting Bitcoin address", phone_number);
 
     // Find user and their wallet
@@ -305,9 +386,8 @@ async fn ussd_receive_bitcoin(db_pool: AnyPool, phone_number: &str) -> Result<St
     .fetch_one(&db_pool)
     .await?;
 
-    // TODO: Generate new receive address using Breez SDK, or return an existing one
-    // For now, return a dummy address for the wallet.
-    // breez_sdk::receive_payment(wallet_id)?;
-    tokio::time::sleep(Duration::from_secs(1)).await; // Simulate network call
-    Ok(format!("bc1qdummyaddressforwallet{}", &wallet_id.to_string()[..8]))
+    // TODO: Map on-chain addresses to wallets so deposits can be attributed
+    let address = lightning.get_onchain_address().await?;
+    info!("Issued address {} for wallet {}", address, wallet_id);
+    Ok(address)
 }
@@ -1,9 +1,12 @@
 use crate::database::AnyPool;
+use crate::domain::payout::PayoutDestination;
+use crate::domain::recovery::RecoveryPolicy;
 use crate::domain::types::Sats;
 use crate::error::AppError;
+use crate::services::{ledger_service, recovery_service};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use sqlx::Row;
+use sqlx::{Any, Row, Transaction as DbTransaction};
 use tracing::info;
 use uuid::Uuid;
 
@@ -25,9 +28,13 @@ pub struct WalletInfo {
     pub user_id: Uuid,
     pub breez_wallet_id: String,
     pub nostr_npub: String,
+    /// Lightning address or LNURL each deposit is forwarded to; without one, deposits stay in the wallet
+    pub payout_destination: Option<String>,
     pub balance_sats: i64,
     pub backup_type: String,
     pub backup_status: String,
+    pub recovery_threshold: u8,
+    pub recovery_share_count: u8,
     pub connection_details: WalletConnectionDetails,
     pub created_at: String,
 }
@@ -42,6 +49,9 @@ impl WalletService {
     /// * `user_id` - The user ID
     /// * `phone_number` - User's phone number
     /// * `backup_type` - Backup type: 'none' | 'social' | 'seed'
+    /// * `recovery_policy` - k-of-n policy for social recovery
+    /// * `recovery_helpers` - Helper npubs for social backup, one per share; empty otherwise
+    /// * `payout_destination` - Where deposits are forwarded, if anywhere
     ///
     /// # Returns
     /// Created wallet info with connection details
@@ -50,17 +60,48 @@ impl WalletService {
         user_id: Uuid,
         phone_number: &str,
         backup_type: &str,
+        recovery_policy: RecoveryPolicy,
+        recovery_helpers: &[String],
+        payout_destination: Option<&PayoutDestination>,
     ) -> Result<WalletInfo, AppError> {
         info!("Creating Lightning wallet for user: {} with backup_type: {}", user_id, backup_type);
 
+        // Generate a mock Nostr public key (npub) - in production, this comes from Nostr account
+        let nostr_npub = format!("npub1{}", Uuid::new_v4().to_string().replace("-", "").chars().take(56).collect::<String>());
+
+        let mut tx = pool.begin().await?;
+        let wallet_info = Self::create_wallet_in_tx(
+            &mut tx,
+            user_id,
+            &nostr_npub,
+            backup_type,
+            recovery_policy,
+            payout_destination,
+        )
+        .await?;
+        if backup_type == "social" {
+            recovery_service::store_helpers(&mut tx, wallet_info.id, recovery_policy, recovery_helpers).await?;
+        }
+        tx.commit().await?;
+
+        Ok(wallet_info)
+    }
+
+    /// Creates the wallet row and its ledger account inside the caller's transaction,
+    /// so a wallet can be provisioned together with its user and first deposit.
+    pub async fn create_wallet_in_tx(
+        tx: &mut DbTransaction<'_, Any>,
+        user_id: Uuid,
+        nostr_npub: &str,
+        backup_type: &str,
+        recovery_policy: RecoveryPolicy,
+        payout_destination: Option<&PayoutDestination>,
+    ) -> Result<WalletInfo, AppError> {
         // Generate wallet identifiers
         let wallet_id = Uuid::new_v4();
         let breez_wallet_id = format!("breez_{}", wallet_id.to_string().replace("-", ""));
         let node_id = format!("node_{}", Uuid::new_v4().to_string().replace("-", ""));
 
-        // Generate a mock Nostr public key (npub) - in production, this comes from Nostr account
-        let nostr_npub = format!("npub1{}", Uuid::new_v4().to_string().replace("-", "").chars().take(56).collect::<String>());
-
         // For now, generate a mock node address (in production, this comes from Breez SDK)
         let node_address = format!("lnd_node_{}@127.0.0.1:9735", &node_id[5..15]);
 
@@ -70,25 +111,27 @@ impl WalletService {
             _ => "pending",
         };
 
-        // Insert wallet into database
+        // Insert wallet and its ledger account atomically
         let now = Utc::now();
         let result = sqlx::query(
             r#"
-            INSERT INTO wallets (id, user_id, nostr_npub, breez_wallet_id, balance_sats, backup_type, backup_status, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-            RETURNING id, user_id, breez_wallet_id, nostr_npub, balance_sats, backup_type, backup_status, created_at
+            INSERT INTO wallets (id, user_id, nostr_npub, breez_wallet_id, payout_destination, backup_type, backup_status, recovery_threshold, recovery_share_count, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING id, user_id, breez_wallet_id, payout_destination, nostr_npub, backup_type, backup_status, created_at
             "#
         )
         .bind(wallet_id)
         .bind(user_id)
-        .bind(&nostr_npub)
+        .bind(nostr_npub)
         .bind(&breez_wallet_id)
-        .bind(0i64)  // Initial balance: 0 sats
+        .bind(payout_destination.map(PayoutDestination::as_str))
         .bind(backup_type)
         .bind(backup_status)
+        .bind(recovery_policy.threshold as i16)
+        .bind(recovery_policy.share_count as i16)
         .bind(now)
         .bind(now)
-        .fetch_one(pool)
+        .fetch_one(&mut **tx)
         .await
         .map_err(|e| {
             match e {
@@ -102,14 +145,19 @@ impl WalletService {
 
         let created_at: chrono::DateTime<chrono::Utc> = result.get("created_at");
 
+        ledger_service::ensure_wallet_account(tx, wallet_id).await?;
+
         let wallet_info = WalletInfo {
             id: wallet_id,
             user_id,
             breez_wallet_id: result.get("breez_wallet_id"),
             nostr_npub: result.get("nostr_npub"),
-            balance_sats: result.get("balance_sats"),
+            payout_destination: result.get("payout_destination"),
+            balance_sats: 0, // New wallets start with no postings
             backup_type: result.get("backup_type"),
             backup_status: result.get("backup_status"),
+            recovery_threshold: recovery_policy.threshold,
+            recovery_share_count: recovery_policy.share_count,
             connection_details: WalletConnectionDetails {
                 wallet_id: wallet_id.to_string(),
                 user_id: user_id.to_string(),
@@ -134,7 +182,7 @@ impl WalletService {
 
         let wallet_row = sqlx::query(
             r#"
-            SELECT id, user_id, breez_wallet_id, nostr_npub, balance_sats, backup_type, backup_status, created_at
+            SELECT id, user_id, breez_wallet_id, payout_destination, nostr_npub, backup_type, backup_status, recovery_threshold, recovery_share_count, created_at
             FROM wallets
             WHERE user_id = $1
             LIMIT 1
@@ -151,6 +199,7 @@ impl WalletService {
         let wallet_id: Uuid = wallet_row.get("id");
         let breez_wallet_id: String = wallet_row.get("breez_wallet_id");
         let created_at: chrono::DateTime<chrono::Utc> = wallet_row.get("created_at");
+        let balance = ledger_service::wallet_balance(pool, wallet_id).await?;
 
         // Generate connection details
         let node_id = format!("node_{}", Uuid::new_v4().to_string().replace("-", ""));
@@ -161,9 +210,12 @@ impl WalletService {
             user_id,
             breez_wallet_id,
             nostr_npub: wallet_row.get("nostr_npub"),
-            balance_sats: wallet_row.get("balance_sats"),
+            payout_destination: wallet_row.get("payout_destination"),
+            balance_sats: balance.0,
             backup_type: wallet_row.get("backup_type"),
             backup_status: wallet_row.get("backup_status"),
+            recovery_threshold: wallet_row.get::<i16, _>("recovery_threshold") as u8,
+            recovery_share_count: wallet_row.get::<i16, _>("recovery_share_count") as u8,
             connection_details: WalletConnectionDetails {
                 wallet_id: wallet_id.to_string(),
                 user_id: user_id.to_string(),
@@ -0,0 +1,291 @@
+use axum::http::HeaderMap;
+use chrono::{Duration as ChronoDuration, Utc};
+use sqlx::{Any, Transaction as DbTransaction};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    bitcoin::breez_webhook,
+    database::AnyPool,
+    domain::{models::WebhookEvent, money::ExchangeRate},
+    error::AppError,
+    paystack,
+    services::{fiat_service, lightning_payment_service, payout_service},
+};
+
+/// Events are given up on (left unprocessed for manual replay) after this many failed attempts.
+pub const MAX_ATTEMPTS: i32 = 8;
+const BASE_BACKOFF_SECONDS: i64 = 30;
+const MAX_BACKOFF_SECONDS: i64 = 3600; // 1 hour
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const BATCH_SIZE: i64 = 20;
+
+const WEBHOOK_EVENT_COLUMNS: &str = "id, provider, event_id, event_type, payload, raw_body, headers, processed, attempts, next_attempt_at, last_error, processed_at, created_at";
+
+/// A webhook delivery as received, before any processing.
+pub struct IncomingWebhook<'a> {
+    pub provider: &'a str,
+    pub event_id: String,
+    pub event_type: String,
+    pub headers: &'a HeaderMap,
+    pub raw_body: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestOutcome {
+    /// Stored for processing.
+    Accepted(Uuid),
+    /// Already stored under the same provider and event ID; nothing was written.
+    Duplicate(Uuid),
+}
+
+/// Persists the exact payload and headers of a webhook, deduplicating on `(provider, event_id)`.
+pub async fn ingest(db_pool: &AnyPool, incoming: IncomingWebhook<'_>) -> Result<IngestOutcome, AppError> {
+    let payload: serde_json::Value =
+        serde_json::from_slice(incoming.raw_body).unwrap_or(serde_json::Value::Null);
+
+    let inserted: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        INSERT INTO webhook_events (id, provider, event_id, event_type, payload, raw_body, headers, processed, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, FALSE, NOW())
+        ON CONFLICT (provider, event_id) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(incoming.provider)
+    .bind(&incoming.event_id)
+    .bind(&incoming.event_type)
+    .bind(payload)
+    .bind(incoming.raw_body)
+    .bind(headers_to_json(incoming.headers))
+    .fetch_optional(db_pool)
+    .await?;
+
+    if let Some(id) = inserted {
+        info!(
+            "Stored {} webhook {} ({}) as {}",
+            incoming.provider, incoming.event_id, incoming.event_type, id
+        );
+        return Ok(IngestOutcome::Accepted(id));
+    }
+
+    let existing_id: Uuid =
+        sqlx::query_scalar("SELECT id FROM webhook_events WHERE provider = $1 AND event_id = $2")
+            .bind(incoming.provider)
+            .bind(&incoming.event_id)
+            .fetch_one(db_pool)
+            .await?;
+
+    info!(
+        "Duplicate {} webhook {} ignored (already stored as {})",
+        incoming.provider, incoming.event_id, existing_id
+    );
+    Ok(IngestOutcome::Duplicate(existing_id))
+}
+
+/// Starts the background worker that processes stored webhooks.
+/// It wakes on `AppState::webhook_notify` and otherwise polls for events whose backoff has elapsed.
+pub fn spawn_worker(app_state: Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        info!("Webhook worker started.");
+        loop {
+            if let Err(e) = process_due_events(&app_state).await {
+                error!("Webhook worker failed to poll for events: {:?}", e);
+            }
+
+            tokio::select! {
+                _ = app_state.webhook_notify.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    })
+}
+
+/// Processes every unprocessed event that is due. Returns how many were attempted.
+/// An event that errors is logged and left for the next poll; it doesn't hold up the rest of the batch.
+pub async fn process_due_events(app_state: &Arc<AppState>) -> Result<usize, AppError> {
+    let due: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM webhook_events
+        WHERE processed = FALSE AND attempts < $1 AND next_attempt_at <= NOW()
+        ORDER BY created_at
+        LIMIT $2
+        "#,
+    )
+    .bind(MAX_ATTEMPTS)
+    .bind(BATCH_SIZE)
+    .fetch_all(&app_state.db_pool)
+    .await?;
+
+    for id in &due {
+        if let Err(e) = process_event(app_state, *id).await {
+            error!("Webhook event {} could not be processed: {:?}", id, e);
+        }
+    }
+
+    Ok(due.len())
+}
+
+/// Processes a single event. The business logic and the `processed` flag are committed in
+/// one database transaction; on failure everything is rolled back and a retry is scheduled.
+pub async fn process_event(app_state: &Arc<AppState>, id: Uuid) -> Result<(), AppError> {
+    // A rate source can be slow, so the rate is fetched before any row is locked.
+    // Only deposits that have to be re-quoted use it, and only they fail if it is unavailable.
+    let current_rate = app_state.rate_oracle.current_rate().await.map(|rate| rate.naira_per_btc);
+
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // SKIP LOCKED lets several workers run without processing the same event twice
+    let event: Option<WebhookEvent> = sqlx::query_as(&format!(
+        "SELECT {} FROM webhook_events WHERE id = $1 AND processed = FALSE FOR UPDATE SKIP LOCKED",
+        WEBHOOK_EVENT_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(event) = event else {
+        return Ok(());
+    };
+
+    match dispatch(app_state, &mut tx, &event, current_rate).await {
+        Ok(()) => {
+            sqlx::query(
+                "UPDATE webhook_events SET processed = TRUE, processed_at = NOW(), attempts = attempts + 1, last_error = NULL WHERE id = $1",
+            )
+            .bind(event.id)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            info!("Processed {} webhook {}", event.provider, event.event_id);
+            // Deposits queue their payout in the same transaction
+            payout_service::wake(&app_state.redis_client).await;
+        }
+        Err(e) => {
+            tx.rollback().await?;
+
+            let attempts = event.attempts + 1;
+            let next_attempt_at = Utc::now() + backoff_for(attempts);
+            sqlx::query(
+                "UPDATE webhook_events SET attempts = $2, last_error = $3, next_attempt_at = $4 WHERE id = $1",
+            )
+            .bind(event.id)
+            .bind(attempts)
+            .bind(e.to_string())
+            .bind(next_attempt_at)
+            .execute(&app_state.db_pool)
+            .await?;
+
+            if attempts >= MAX_ATTEMPTS {
+                error!(
+                    "Giving up on {} webhook {} after {} attempts: {}",
+                    event.provider, event.event_id, attempts, e
+                );
+            } else {
+                warn!(
+                    "Failed to process {} webhook {} (attempt {}), retrying at {}: {}",
+                    event.provider, event.event_id, attempts, next_attempt_at, e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Resets a stored webhook so it is processed again from its raw payload.
+pub async fn replay(db_pool: &AnyPool, id: Uuid) -> Result<(), AppError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE webhook_events
+        SET processed = FALSE, processed_at = NULL, attempts = 0, last_error = NULL, next_attempt_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(db_pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound(format!("Webhook event {} not found", id)));
+    }
+
+    info!("Webhook event {} queued for replay", id);
+    Ok(())
+}
+
+/// Fetches a stored webhook event.
+pub async fn get_event(db_pool: &AnyPool, id: Uuid) -> Result<WebhookEvent, AppError> {
+    sqlx::query_as(&format!(
+        "SELECT {} FROM webhook_events WHERE id = $1",
+        WEBHOOK_EVENT_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(db_pool)
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("Webhook event {} not found", id)))
+}
+
+async fn dispatch(
+    app_state: &Arc<AppState>,
+    tx: &mut DbTransaction<'_, Any>,
+    event: &WebhookEvent,
+    current_rate: Result<ExchangeRate, AppError>,
+) -> Result<(), AppError> {
+    let raw_body = event.raw_body.as_deref().ok_or_else(|| {
+        AppError::BadRequest(format!(
+            "Webhook event {} has no stored raw payload and cannot be processed",
+            event.id
+        ))
+    })?;
+
+    match event.provider.as_str() {
+        paystack::webhook::PROVIDER => {
+            fiat_service::handle_paystack_event(tx, app_state, raw_body, current_rate).await
+        }
+        breez_webhook::PROVIDER => lightning_payment_service::handle_breez_event(tx, app_state, raw_body).await,
+        other => Err(AppError::Internal(format!(
+            "No handler registered for webhook provider '{}'",
+            other
+        ))),
+    }
+}
+
+fn headers_to_json(headers: &HeaderMap) -> serde_json::Value {
+    let map = headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str().to_string(),
+                serde_json::Value::String(String::from_utf8_lossy(value.as_bytes()).into_owned()),
+            )
+        })
+        .collect();
+    serde_json::Value::Object(map)
+}
+
+/// Exponential backoff: 30s, 60s, 120s, ... capped at one hour.
+fn backoff_for(attempts: i32) -> ChronoDuration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    let seconds = BASE_BACKOFF_SECONDS
+        .saturating_mul(2i64.saturating_pow(exponent))
+        .min(MAX_BACKOFF_SECONDS);
+    ChronoDuration::seconds(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        assert_eq!(backoff_for(1), ChronoDuration::seconds(30));
+        assert_eq!(backoff_for(2), ChronoDuration::seconds(60));
+        assert_eq!(backoff_for(4), ChronoDuration::seconds(240));
+        assert_eq!(backoff_for(MAX_ATTEMPTS), ChronoDuration::seconds(MAX_BACKOFF_SECONDS));
+        assert_eq!(backoff_for(1_000), ChronoDuration::seconds(MAX_BACKOFF_SECONDS));
+    }
+}
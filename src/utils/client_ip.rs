@@ -0,0 +1,50 @@
+use axum::http::HeaderMap;
+use std::net::{IpAddr, SocketAddr};
+
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// The address a request came from. With `trust_forwarded_for`, this is the last entry of
+/// `X-Forwarded-For`, i.e. the address our own proxy saw; earlier entries are client-supplied.
+/// Falls back to the TCP peer if the header is missing or malformed.
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr, trust_forwarded_for: bool) -> IpAddr {
+    if trust_forwarded_for {
+        let forwarded = headers
+            .get(FORWARDED_FOR_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+    peer.ip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn peer() -> SocketAddr {
+        "10.0.0.1:443".parse().unwrap()
+    }
+
+    #[test]
+    fn test_forwarded_for_is_ignored_unless_trusted() {
+        let mut headers = HeaderMap::new();
+        headers.insert(FORWARDED_FOR_HEADER, HeaderValue::from_static("203.0.113.7"));
+
+        assert_eq!(client_ip(&headers, peer(), false), peer().ip());
+        assert_eq!(client_ip(&headers, peer(), true), "203.0.113.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_spoofed_leading_entries_are_ignored() {
+        let mut headers = HeaderMap::new();
+        headers.insert(FORWARDED_FOR_HEADER, HeaderValue::from_static("1.2.3.4, 203.0.113.7"));
+        assert_eq!(client_ip(&headers, peer(), true), "203.0.113.7".parse::<IpAddr>().unwrap());
+
+        headers.insert(FORWARDED_FOR_HEADER, HeaderValue::from_static("not-an-ip"));
+        assert_eq!(client_ip(&headers, peer(), true), peer().ip());
+    }
+}
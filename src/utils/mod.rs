@@ -1 +1,3 @@
+pub mod client_ip;
 pub mod phone_number;
+pub mod signature;
@@ -0,0 +1,78 @@
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha512};
+
+type HmacSha256 = Hmac<Sha256>;
+type HmacSha512 = Hmac<Sha512>;
+
+/// Verifies a hex-encoded HMAC-SHA512 of `body` keyed with `secret`.
+/// The comparison is constant-time; malformed hex simply fails verification.
+pub fn verify_hmac_sha512(secret: &[u8], body: &[u8], signature_hex: &str) -> bool {
+    let Ok(signature) = hex::decode(signature_hex.trim()) else {
+        return false;
+    };
+
+    let mut mac = HmacSha512::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Computes the hex-encoded HMAC-SHA512 of `body`. Used for signing test fixtures and outbound calls.
+pub fn sign_hmac_sha512(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = HmacSha512::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Verifies a hex-encoded HMAC-SHA256 of `body` keyed with `secret`, like `verify_hmac_sha512`.
+pub fn verify_hmac_sha256(secret: &[u8], body: &[u8], signature_hex: &str) -> bool {
+    let Ok(signature) = hex::decode(signature_hex.trim()) else {
+        return false;
+    };
+
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Computes the hex-encoded HMAC-SHA256 of `body`.
+pub fn sign_hmac_sha256(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_signature_is_accepted() {
+        let body = br#"{"event":"charge.success"}"#;
+        let signature = sign_hmac_sha512(b"sk_test_secret", body);
+
+        assert!(verify_hmac_sha512(b"sk_test_secret", body, &signature));
+        assert!(verify_hmac_sha512(b"sk_test_secret", body, &signature.to_uppercase()));
+    }
+
+    #[test]
+    fn test_invalid_signatures_are_rejected() {
+        let body = br#"{"event":"charge.success"}"#;
+        let signature = sign_hmac_sha512(b"sk_test_secret", body);
+
+        assert!(!verify_hmac_sha512(b"sk_other_secret", body, &signature));
+        assert!(!verify_hmac_sha512(b"sk_test_secret", br#"{"event":"charge.failed"}"#, &signature));
+        assert!(!verify_hmac_sha512(b"sk_test_secret", body, "not-hex"));
+        assert!(!verify_hmac_sha512(b"sk_test_secret", body, ""));
+    }
+
+    #[test]
+    fn test_sha256_signatures_round_trip() {
+        let body = br#"{"payment_hash":"abc"}"#;
+        let signature = sign_hmac_sha256(b"breez_secret", body);
+
+        assert_eq!(signature.len(), 64);
+        assert!(verify_hmac_sha256(b"breez_secret", body, &signature));
+        assert!(!verify_hmac_sha256(b"other_secret", body, &signature));
+        assert!(!verify_hmac_sha256(b"breez_secret", body, &sign_hmac_sha512(b"breez_secret", body)));
+    }
+}